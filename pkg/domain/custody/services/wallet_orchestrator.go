@@ -369,7 +369,7 @@ func (o *WalletOrchestrator) deploySolanaWallet(
 	}
 
 	instrReq := &custody_out.ProgramInstructionRequest{
-		ProgramID: "LeetWa11etPr0gram1111111111111111111111111", // Program ID
+		ProgramID: "LeetWa11etProgram11111111111111111111111111", // Program ID
 		Accounts: []custody_out.AccountMeta{
 			{Address: wallet.Addresses[chainID], IsSigner: false, IsWritable: true}, // Wallet PDA
 			{Address: solanaPublicKey, IsSigner: false, IsWritable: false},          // Owner
@@ -1,11 +1,23 @@
 // LeetGaming Solana Smart Wallet Program
 // Uses PDAs for deterministic wallet addresses and supports MPC signatures
+//
+// "Guardian co-signers" in instruction docs means guardian_threshold (at least
+// one) guardians signing, passed in remaining_accounts as
+// [guardian_pda, guardian_signer] pairs; see count_guardian_signers.
+
+// Anchor 0.31's generated IDL instructions call AccountInfo::realloc and the
+// stake CPIs go through solana_program::stake, both deprecated in Solana 2.2
+#![allow(deprecated)]
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::{ed25519_program, hash, stake, sysvar, sysvar::instructions as ix_sysvar};
+use anchor_spl::token::{self, Approve, CloseAccount, Mint, Revoke, SyncNative, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::{self, get_associated_token_address, AssociatedToken};
 
-declare_id!("LeetWa11etPr0gram1111111111111111111111111");
+declare_id!("LeetWa11etProgram11111111111111111111111111");
 
 #[program]
 pub mod leet_wallet {
@@ -19,28 +31,141 @@ pub mod leet_wallet {
         daily_limit: u64,
         recovery_delay: i64,
     ) -> Result<()> {
+        require!(
+            (0..=MAX_RECOVERY_DELAY).contains(&recovery_delay),
+            WalletError::InvalidRecoveryDelay
+        );
+        // Also the initial recovery and unfreeze threshold, so 0 would let
+        // recovery execute without a single approval
+        require!(
+            guardian_threshold > 0 && guardian_threshold as usize <= MAX_GUARDIANS,
+            WalletError::InvalidThreshold
+        );
+
         let wallet = &mut ctx.accounts.wallet;
 
         wallet.owner = ctx.accounts.owner.key();
+        wallet.owners = vec![wallet.owner];
+        wallet.owner_threshold = 1;
         wallet.wallet_id = wallet_id;
         wallet.authority = ctx.accounts.authority.key();
         wallet.guardian_threshold = guardian_threshold;
         wallet.guardian_count = 0;
+        wallet.next_guardian_index = 0;
         wallet.daily_limit = daily_limit;
+        let now = Clock::get()?.unix_timestamp;
         wallet.daily_spent = 0;
-        wallet.last_reset_day = Clock::get()?.unix_timestamp / 86400;
+        wallet.last_reset_day = now / 86400;
+        wallet.created_at = now;
         wallet.recovery_delay = recovery_delay;
-        wallet.pending_recovery = None;
+        wallet.recovery_pending = false;
         wallet.nonce = 0;
         wallet.is_frozen = false;
+        wallet.freeze_reason = None;
+        wallet.require_diverse_guardians = false;
+        wallet.safe_address = None;
+        wallet.pending_safe_address = None;
+        wallet.safe_address_effective_at = 0;
+        wallet.allowed_start_second = 0;
+        wallet.allowed_end_second = 0;
+        wallet.min_transfer_interval = 0;
+        wallet.last_transfer_at = 0;
+        wallet.next_transfer_request_id = 0;
+        wallet.lock_from = 0;
+        wallet.lock_until = 0;
+        wallet.daily_limit_bps = 0;
+        wallet.transfer_nonce = 0;
+        wallet.exec_nonce = 0;
+        wallet.emit_events = true;
+        wallet.next_deposit_id = 0;
+        wallet.recovery_threshold = guardian_threshold;
+        wallet.total_guardian_weight = 0;
+        wallet.anomaly_threshold = 0;
+        wallet.anomaly_window = 0;
+        wallet.anomaly_window_start = 0;
+        wallet.anomaly_window_spent = 0;
+        wallet.pending_limit_change = None;
+        wallet.limit_ramp_per_day = 0;
+        wallet.limit_ramp_target = 0;
+        wallet.panic_enabled = false;
+        wallet.days_skipped = 0;
+        wallet.compliance_hold = false;
+        wallet.compliance_admin = None;
+        wallet.mpc_mode = false;
+        wallet.pending_item_count = 0;
+        wallet.max_pending_items = DEFAULT_MAX_PENDING_ITEMS;
+        wallet.lifetime_limit = None;
+        wallet.lifetime_spent = 0;
+        wallet.limit_change_requires_guardians = false;
+        wallet.notify_program = None;
+        wallet.strict_hook = false;
+        wallet.vesting = None;
+        wallet.migrated_to = None;
+        wallet.exec_daily_count = 0;
+        wallet.max_exec_daily_count = 0;
+        wallet.exec_count_day = now / 86400;
+        wallet.exec_nonce_window = 0;
+        wallet.exec_nonce_used = 0;
+        wallet.last_recovery_executed_at = 0;
+        wallet.post_recovery_cooldown = 0;
+        wallet.spend_window_seconds = 0;
+        wallet.guardians_added_today = 0;
+        wallet.max_guardians_per_day = 0;
+        wallet.guardian_add_day = now / 86400;
+        wallet.unfreeze_threshold = guardian_threshold;
+        wallet.required_types_mask = 0;
+        wallet.enforce_guardian_types = false;
+        wallet.observe_only = false;
+        wallet.guardian_group_key = None;
+        wallet.guardian_group_threshold = 0;
+        wallet.recent_idempotency_keys = [[0; 16]; IDEMPOTENCY_KEY_SLOTS];
+        wallet.idempotency_head = 0;
+        wallet.max_exec_payload_len = MAX_EXEC_PAYLOAD_LEN;
+        wallet.require_owner_inactivity = false;
+        wallet.last_owner_activity_at = now;
+        wallet.pending_recovery_delay = None;
+        wallet.reference_budget = None;
+        wallet.reference_spent = 0;
+        wallet.last_recovery_cancel_reason = None;
+        wallet.recovery_session_grace = DEFAULT_RECOVERY_SESSION_GRACE;
+        wallet.weekday_limits = None;
+        wallet.review_window = 0;
+        wallet.review_threshold = 0;
+        wallet.next_held_transfer_id = 0;
+        wallet.limit_penalty_bps = 0;
+        wallet.limit_penalty_duration = 0;
+        wallet.limit_penalty_until = 0;
+        wallet.max_approvals_per_type = 0;
+        wallet.authority_is_group = false;
+        wallet.limit_decrease_floor = 0;
+        wallet.limit_drop_alert_bps = 0;
+        wallet.freeze_threshold = 1;
+        wallet.pending_freeze = None;
+        wallet.reconcile_account = Pubkey::default();
+        wallet.last_reconciled_balance = 0;
+        wallet.last_reconciled_spent = 0;
+        wallet.reconcile_corrects = false;
+        wallet.pending_reference_budget = None;
         wallet.bump = ctx.bumps.wallet;
+        wallet.is_initialized = true;
 
         emit!(WalletInitialized {
             wallet: wallet.key(),
             owner: wallet.owner,
             wallet_id,
+            created_at: now,
         });
 
+        // Only an owner that signs is indexed, so nobody can fill a
+        // stranger's index with wallets naming them as owner
+        if let Some(index) = ctx.accounts.owner_index.as_mut() {
+            if index.owner == Pubkey::default() {
+                index.owner = wallet.owner;
+                index.bump = ctx.bumps.owner_index.unwrap_or_default();
+            }
+            add_to_owner_index(index, &wallet.key(), wallet_id);
+        }
+
         Ok(())
     }
 
@@ -49,60 +174,407 @@ pub mod leet_wallet {
         ctx: Context<AddGuardian>,
         guardian_pubkey: Pubkey,
         guardian_type: GuardianType,
+        weight: u8,
+        expires_at: i64,
     ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
         let guardian = &mut ctx.accounts.guardian;
 
-        require!(wallet.guardian_count < 7, WalletError::TooManyGuardians);
+        require!(weight > 0, WalletError::InvalidGuardianWeight);
+        require!(
+            ctx.accounts.blocked_key.data_is_empty(),
+            WalletError::GuardianBlocked
+        );
 
-        guardian.wallet = wallet.key();
-        guardian.pubkey = guardian_pubkey;
-        guardian.guardian_type = guardian_type;
-        guardian.added_at = Clock::get()?.unix_timestamp;
-        guardian.is_active = true;
-        guardian.bump = ctx.bumps.guardian;
+        let now = Clock::get()?.unix_timestamp;
+        wallet.register_guardian(weight, now)?;
+        guardian.activate(
+            wallet.key(),
+            guardian_pubkey,
+            guardian_type,
+            weight,
+            expires_at,
+            now,
+            ctx.bumps.guardian,
+        );
 
-        wallet.guardian_count += 1;
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::GuardianAdded, ctx.accounts.authority.key(), guardian.added_at);
+        }
 
         emit!(GuardianAdded {
             wallet: wallet.key(),
             guardian: guardian_pubkey,
             guardian_type,
+            weight,
         });
 
         Ok(())
     }
 
     /// Transfer SPL tokens with spending limit checks
+    ///
+    /// `reference` is an external correlation id surfaced only in
+    /// TransferExecuted; it is never stored on-chain.
+    ///
+    /// When the platform fee is on, the recipient still receives `amount` and
+    /// the fee is moved to `fee_token_account` on top; limits are charged
+    /// for both.
+    ///
+    /// A relayer retrying after an uncertain timeout passes the same
+    /// `idempotency_key`; if the first attempt landed, the retry fails with
+    /// DuplicateRequest, which the caller should treat as already done.
+    ///
+    /// With transfer review on, amounts at or above `review_threshold` fail
+    /// with TransferRequiresReview and must go through hold_transfer.
     pub fn transfer_spl(
         ctx: Context<TransferSPL>,
         amount: u64,
+        category: u8,
+        reference: Option<[u8; 32]>,
+        idempotency_key: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(!wallet.requires_review(amount), WalletError::TransferRequiresReview);
+        if let Some(key) = idempotency_key.as_ref() {
+            wallet.check_idempotency_key(key)?;
+        }
+
+        // The failed transaction's logs still carry the event, so indexers
+        // can tell a disabled wallet apart from an exhausted limit
+        if wallet.spending_disabled() {
+            emit!(SpendingDisabledAttempt {
+                wallet: wallet.key(),
+                amount,
+            });
+        }
+
+        let spent = spend_from_wallet(
+            SpendAccounts {
+                wallet: &mut ctx.accounts.wallet,
+                global_config: &ctx.accounts.global_config,
+                from_token_account: &ctx.accounts.from_token_account,
+                to_token_account: ctx.accounts.to_token_account.to_account_info(),
+                fee_token_account: ctx.accounts.fee_token_account.as_ref(),
+                frozen_mint: ctx.accounts.frozen_mint.to_account_info(),
+                spend_window: ctx.accounts.spend_window.as_mut(),
+                category_limit: Some(ctx.accounts.category_limit.to_account_info()),
+                price_quote: ctx.accounts.price_quote.as_ref().map(|quote| quote.to_account_info()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            amount,
+            idempotency_key,
+            now,
+        )?;
+        if spent.is_none() {
+            return Ok(());
+        }
+
+        let wallet = &ctx.accounts.wallet;
+        if wallet.emit_events {
+            emit!(TransferExecuted {
+                wallet: wallet.key(),
+                to: ctx.accounts.to_token_account.key(),
+                amount,
+                nonce: wallet.nonce,
+                category,
+                reference: reference.unwrap_or_default(),
+                mint: ctx.accounts.from_token_account.mint,
+                decimals: ctx.accounts.mint.as_ref().map(|mint| mint.decimals),
+            });
+        }
+
+        // A failing callback always aborts the transaction on Solana, so
+        // isolation means a non-strict hook may be skipped by omitting the
+        // notify_program account
+        if ctx.accounts.wallet.notify_program.is_some() {
+            match ctx.accounts.notify_program.as_ref() {
+                Some(program) => notify_transfer(
+                    program,
+                    &ctx.accounts.wallet.to_account_info(),
+                    amount,
+                    &ctx.accounts.to_token_account.key(),
+                )?,
+                None => require!(!ctx.accounts.wallet.strict_hook, WalletError::NotifyProgramMissing),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// transfer_spl to `recipient`'s associated token account for the mint,
+    /// creating it first (payer funds the rent) if it doesn't exist yet
+    ///
+    /// Goes through the same spend_from_wallet checks as transfer_spl, plus
+    /// its authorization and review. Category budgets and idempotency keys
+    /// are not supported on this path.
+    pub fn transfer_spl_create_ata(
+        ctx: Context<TransferSPLCreateAta>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let wallet_key = wallet.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(amount > 0, WalletError::InvalidAmount);
+        require!(!wallet.requires_review(amount), WalletError::TransferRequiresReview);
+
+        // The ATA must exist before the transfer; if the spend is rejected the
+        // whole transaction, creation included, is rolled back
+        let created = ctx.accounts.to_token_account.data_is_empty();
+        if created {
+            let cpi_accounts = associated_token::Create {
+                payer: ctx.accounts.payer.to_account_info(),
+                associated_token: ctx.accounts.to_token_account.to_account_info(),
+                authority: ctx.accounts.recipient.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.associated_token_program.to_account_info();
+            associated_token::create(CpiContext::new(cpi_program, cpi_accounts))?;
+
+            emit!(AssociatedAccountCreated {
+                wallet: wallet_key,
+                recipient: ctx.accounts.recipient.key(),
+                mint: ctx.accounts.mint.key(),
+                token_account: ctx.accounts.to_token_account.key(),
+            });
+        }
+
+        let spent = spend_from_wallet(
+            SpendAccounts {
+                wallet: &mut ctx.accounts.wallet,
+                global_config: &ctx.accounts.global_config,
+                from_token_account: &ctx.accounts.from_token_account,
+                to_token_account: ctx.accounts.to_token_account.to_account_info(),
+                fee_token_account: ctx.accounts.fee_token_account.as_ref(),
+                frozen_mint: ctx.accounts.frozen_mint.to_account_info(),
+                spend_window: ctx.accounts.spend_window.as_mut(),
+                category_limit: None,
+                price_quote: ctx.accounts.price_quote.as_ref().map(|quote| quote.to_account_info()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            amount,
+            None,
+            now,
+        )?;
+        if spent.is_none() {
+            return Ok(());
+        }
+
+        let wallet = &ctx.accounts.wallet;
+        if wallet.emit_events {
+            emit!(TransferExecuted {
+                wallet: wallet_key,
+                to: ctx.accounts.to_token_account.key(),
+                amount,
+                nonce: wallet.nonce,
+                category: 0,
+                reference: [0; 32],
+                mint: ctx.accounts.mint.key(),
+                decimals: Some(ctx.accounts.mint.decimals),
+            });
+        }
+
+        if ctx.accounts.wallet.notify_program.is_some() {
+            match ctx.accounts.notify_program.as_ref() {
+                Some(program) => notify_transfer(
+                    program,
+                    &ctx.accounts.wallet.to_account_info(),
+                    amount,
+                    &ctx.accounts.to_token_account.key(),
+                )?,
+                None => require!(!ctx.accounts.wallet.strict_hook, WalletError::NotifyProgramMissing),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run every transfer_spl validation without moving funds or mutating state
+    pub fn simulate_transfer(
+        ctx: Context<SimulateTransfer>,
+        amount: u64,
+        _category: u8,
     ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let wallet = &ctx.accounts.wallet;
+        let category_info = ctx.accounts.category_limit.to_account_info();
+        let checks = SpendChecks::evaluate(
+            wallet,
+            &ctx.accounts.global_config,
+            &ctx.accounts.from_token_account,
+            ctx.accounts.spend_window.as_deref(),
+            Some(&category_info),
+            ctx.accounts.price_quote.as_ref().map(|quote| quote.as_ref()),
+            amount,
+            now,
+        )?;
+        wallet.check_transfer_gates(checks.total, now)?;
+        require!(ctx.accounts.frozen_mint.data_is_empty(), WalletError::MintFrozen);
+
+        checks.enforce(wallet, &wallet.key())
+    }
+
+    /// Report how much can still be spent today, applying the day rollover
+    pub fn get_remaining_limit(ctx: Context<ReadWallet>) -> Result<u64> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+        let remaining = wallet.remaining_today(now);
+
+        emit!(RemainingLimit {
+            wallet: wallet.key(),
+            remaining,
+            day: now / 86400,
+        });
+
+        Ok(remaining)
+    }
+
+    /// Check recorded spend against the token account's actual balance
+    /// movement since the last reconciliation
+    ///
+    /// Inflows can't be told apart from a smaller outflow, so only outflow
+    /// beyond what `lifetime_spent` grew by is reported, as `discrepancy`.
+    /// Spend of other mints also grows `lifetime_spent` and can mask it.
+    /// Reporting against the pinned token account is permissionless; pinning
+    /// a token account (the first call, or switching) only sets the baseline
+    /// and needs the authority. With `reconcile_corrects`, the discrepancy is
+    /// charged to the daily and lifetime totals, so every call needs the
+    /// authority.
+    pub fn reconcile_spend(ctx: Context<ReconcileSpend>) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
+        let token_account = &ctx.accounts.token_account;
+        let now = Clock::get()?.unix_timestamp;
 
-        require!(!wallet.is_frozen, WalletError::WalletFrozen);
+        let same_account = wallet.reconcile_account == token_account.key();
+        let by_authority = ctx.accounts.signer.key() == wallet.authority;
+        require!(
+            by_authority || (same_account && !wallet.reconcile_corrects),
+            WalletError::Unauthorized
+        );
+
+        let balance = token_account.amount;
+        let recorded = wallet.lifetime_spent.saturating_sub(wallet.last_reconciled_spent);
+        let observed = wallet.last_reconciled_balance.saturating_sub(balance);
+        let discrepancy = if same_account { observed.saturating_sub(recorded) } else { 0 };
 
-        // Reset daily limit if new day
-        let current_day = Clock::get()?.unix_timestamp / 86400;
-        if current_day > wallet.last_reset_day {
-            wallet.daily_spent = 0;
-            wallet.last_reset_day = current_day;
+        let corrected = discrepancy > 0 && wallet.reconcile_corrects;
+        if corrected {
+            wallet.reset_daily_spent(now);
+            wallet.record_spend(discrepancy);
         }
 
-        // Check daily limit
+        wallet.reconcile_account = token_account.key();
+        wallet.last_reconciled_balance = balance;
+        wallet.last_reconciled_spent = wallet.lifetime_spent;
+
+        emit!(SpendReconciled {
+            wallet: wallet.key(),
+            token_account: token_account.key(),
+            mint: token_account.mint,
+            balance,
+            recorded_spend: if same_account { recorded } else { 0 },
+            observed_outflow: if same_account { observed } else { 0 },
+            discrepancy,
+            corrected,
+        });
+
+        Ok(())
+    }
+
+    /// Choose whether reconcile_spend charges a discrepancy to the spend
+    /// totals or only reports it
+    pub fn set_reconcile_mode(
+        ctx: Context<UpdateConfig>,
+        corrects: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.reconcile_corrects = corrects;
+
+        emit!(ReconcileModeUpdated {
+            wallet: wallet.key(),
+            corrects,
+        });
+
+        Ok(())
+    }
+
+    /// Diagnostic: re-derive the wallet PDA from `wallet_id` and compare it
+    /// with the passed account's key, stored wallet_id and stored bump.
+    /// Mutates nothing; returns whether everything matched.
+    pub fn verify_wallet_pda(ctx: Context<ReadWallet>, wallet_id: [u8; 32]) -> Result<bool> {
+        let wallet = &ctx.accounts.wallet;
+        let (expected, bump) = wallet_pda(&wallet_id);
+        let valid = expected == wallet.key() && wallet.wallet_id == wallet_id && wallet.bump == bump;
+
+        emit!(PdaVerified {
+            wallet: wallet.key(),
+            wallet_id,
+            expected,
+            expected_bump: bump,
+            stored_bump: wallet.bump,
+            valid,
+        });
+
+        Ok(valid)
+    }
+
+    /// Report the program VERSION and the wallet's enabled opt-in features
+    /// (FEATURE_* bits) for client capability discovery
+    pub fn get_version(ctx: Context<ReadWallet>) -> Result<u64> {
+        let wallet = &ctx.accounts.wallet;
+        let feature_flags = wallet.feature_flags();
+
+        emit!(WalletVersion {
+            wallet: wallet.key(),
+            program_version: VERSION,
+            feature_flags,
+        });
+
+        Ok(feature_flags)
+    }
+
+    /// Sweep the full token balance to a destination with guardian co-signing
+    ///
+    /// Bypasses the daily limit, so the authority must be joined by enough
+    /// guardians to meet `guardian_threshold`, and at least one; a wallet
+    /// without guardians can't sweep. Guardians are passed in
+    /// remaining_accounts as `[guardian_pda, guardian_signer]` pairs, after
+    /// the first `owner_signers` accounts, which are co-owner signers for a
+    /// shared wallet. The lifetime limit still applies; the guardian quorum
+    /// stands in for the rest of spend_from_wallet, so there is no platform
+    /// fee, and the spend window, frozen-mint, category, reference-budget
+    /// and anomaly checks are skipped.
+    pub fn sweep_all(ctx: Context<SweepAll>, owner_signers: u8) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            wallet.daily_spent + amount <= wallet.daily_limit,
-            WalletError::DailyLimitExceeded
+            owner_signers as usize <= ctx.remaining_accounts.len(),
+            WalletError::InvalidGuardianAccounts
+        );
+        let (owner_accounts, guardian_accounts) =
+            ctx.remaining_accounts.split_at(owner_signers as usize);
+        wallet.authorize_spend(&ctx.accounts.authority, owner_accounts)?;
+        let approvals = count_guardian_signers(&wallet.key(), guardian_accounts, now)?;
+        require!(
+            wallet.guardian_count > 0 && approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
         );
 
-        // Perform transfer using PDA authority
-        let wallet_id = wallet.wallet_id;
-        let bump = wallet.bump;
-        let seeds = &[
-            b"wallet",
-            wallet_id.as_ref(),
-            &[bump],
-        ];
+        let amount = ctx.accounts.from_token_account.amount;
+        require!(amount > 0, WalletError::NothingToSweep);
+        require!(!wallet.requires_review(amount), WalletError::TransferRequiresReview);
+        wallet.check_transfer_except_daily(amount, now)?;
+        wallet.reset_daily_spent(now);
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
         let signer_seeds = &[&seeds[..]];
 
         let cpi_accounts = Transfer {
@@ -115,429 +587,9066 @@ pub mod leet_wallet {
 
         token::transfer(cpi_ctx, amount)?;
 
-        wallet.daily_spent += amount;
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.record_spend(amount);
+        wallet.last_transfer_at = now;
         wallet.nonce += 1;
 
-        emit!(TransferExecuted {
+        emit!(WalletSwept {
             wallet: wallet.key(),
+            mint: ctx.accounts.from_token_account.mint,
             to: ctx.accounts.to_token_account.key(),
             amount,
-            nonce: wallet.nonce,
+        });
+
+        Ok(())
+    }
+
+    /// Grant a delegate an SPL allowance from a wallet token account
+    ///
+    /// The allowance is charged against today's and the lifetime spending
+    /// limits and the spend window up front. It doesn't go through
+    /// spend_from_wallet, since nothing moves yet: there is no platform fee,
+    /// and the frozen-mint, category, reference-budget and anomaly checks
+    /// are skipped.
+    pub fn approve_delegate(
+        ctx: Context<ApproveDelegate>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(!wallet.requires_review(amount), WalletError::TransferRequiresReview);
+        let balance = ctx.accounts.token_account.amount;
+        wallet.check_transfer(amount, now, balance)?;
+        wallet.charge_spend_window(ctx.accounts.spend_window.as_deref_mut(), amount, now, balance)?;
+        wallet.reset_daily_spent(now);
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Approve {
+            to: ctx.accounts.token_account.to_account_info(),
+            delegate: ctx.accounts.delegate.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::approve(cpi_ctx, amount)?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.record_spend(amount);
+        wallet.last_transfer_at = now;
+        wallet.nonce += 1;
+
+        emit!(DelegateApproved {
+            wallet: wallet.key(),
+            token_account: ctx.accounts.token_account.key(),
+            delegate: ctx.accounts.delegate.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke any SPL allowance on a wallet token account
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Revoke {
+            source: ctx.accounts.token_account.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::revoke(cpi_ctx)?;
+
+        emit!(DelegateRevoked {
+            wallet: ctx.accounts.wallet.key(),
+            token_account: ctx.accounts.token_account.key(),
         });
 
         Ok(())
     }
 
     /// Execute a transaction with MPC signature verification
+    ///
+    /// Signatures must cover `operation_message(OperationType::Execute, ..)` at
+    /// `nonce`, so a signature produced for another operation or an earlier
+    /// nonce can't be replayed here. See `consume_exec_nonce` for which nonces
+    /// are accepted.
+    ///
+    /// Outside MPC mode, the individual signatures must come from at least
+    /// `guardian_threshold` (and at least one) distinct active guardians,
+    /// whose PDAs are passed in remaining_accounts.
     pub fn execute_transaction(
         ctx: Context<ExecuteTransaction>,
         instruction_data: Vec<u8>,
-        signatures: Vec<[u8; 64]>,
+        signatures: ExecSignatures,
+        nonce: u64,
     ) -> Result<()> {
-        let wallet = &ctx.accounts.wallet;
+        let wallet = &mut ctx.accounts.wallet;
 
-        require!(!wallet.is_frozen, WalletError::WalletFrozen);
-        require!(signatures.len() >= wallet.guardian_threshold as usize, WalletError::InsufficientSignatures);
+        wallet.require_spendable()?;
+        let max_len = wallet.max_exec_payload_len as usize;
+        require!(instruction_data.len() <= max_len, WalletError::PayloadTooLarge);
+        if let ExecSignatures::Individual(signatures) = &signatures {
+            require!(signatures.len() * 64 <= max_len, WalletError::PayloadTooLarge);
+        }
+        wallet.consume_exec_nonce(nonce)?;
 
-        // Verify MPC signatures (threshold signature verification)
-        // In production, this would verify the aggregated signature
-        // For MPC (FROST/GG20), we receive a single aggregated signature
-        // that can be verified against the wallet's public key
+        let now = Clock::get()?.unix_timestamp;
+        wallet.record_execution(now)?;
 
-        emit!(TransactionExecuted {
-            wallet: wallet.key(),
-            instruction_hash: anchor_lang::solana_program::hash::hash(&instruction_data).to_bytes(),
-            nonce: wallet.nonce,
-        });
+        let message = operation_message(OperationType::Execute, &wallet.key(), nonce, &instruction_data);
+        match (wallet.mpc_mode, &signatures) {
+            // FROST over the Ed25519 ciphersuite aggregates into a standard
+            // Ed25519 signature under the group key, so the precompile can
+            // verify it against `authority` directly
+            (true, ExecSignatures::Aggregated(signature)) => {
+                require!(
+                    has_ed25519_signature(&ctx.accounts.instructions, &wallet.authority, &message, signature),
+                    WalletError::InvalidSignature
+                );
+            }
+            // Ed25519 signatures are deterministic, so each one must map to a
+            // distinct guardian rather than be counted per entry
+            (false, ExecSignatures::Individual(signatures)) => {
+                let signers = count_guardian_signatures(
+                    &wallet.key(),
+                    ctx.remaining_accounts,
+                    &ctx.accounts.instructions,
+                    &message,
+                    signatures,
+                    now,
+                    0,
+                )?;
+                require!(
+                    signers >= wallet.guardian_threshold.max(1),
+                    WalletError::InsufficientSignatures
+                );
+            }
+            _ => return err!(WalletError::InvalidSignature),
+        }
+
+        if wallet.emit_events {
+            emit!(TransactionExecuted {
+                wallet: wallet.key(),
+                instruction_hash: hash::hash(&instruction_data).to_bytes(),
+                nonce: wallet.nonce,
+            });
+        }
 
         Ok(())
     }
 
-    /// Initiate social recovery
+    /// Initiate social recovery, opening the wallet's RecoverySession PDA
+    ///
+    /// `new_authority_is_group` marks `new_authority` as a freshly derived
+    /// MPC threshold group key rather than a single signer.
     pub fn initiate_recovery(
         ctx: Context<InitiateRecovery>,
         new_authority: Pubkey,
+        new_owner: Option<Pubkey>,
+        new_authority_is_group: bool,
     ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
         let clock = Clock::get()?;
 
-        require!(wallet.pending_recovery.is_none(), WalletError::RecoveryAlreadyPending);
+        require!(
+            ctx.accounts.guardian.is_usable(clock.unix_timestamp),
+            WalletError::GuardianInactive
+        );
+        require!(wallet.is_initialized, WalletError::WalletNotInitialized);
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        require!(
+            clock.unix_timestamp >= wallet.last_recovery_executed_at + wallet.post_recovery_cooldown,
+            WalletError::RecoveryCooldownActive
+        );
+
+        // Unique per recovery instance: the nonce advances on every execution
+        let challenge = hash::hashv(&[
+            MESSAGE_DOMAIN,
+            b"recovery",
+            wallet.key().as_ref(),
+            new_authority.as_ref(),
+            &[new_authority_is_group as u8],
+            &clock.unix_timestamp.to_le_bytes(),
+            &wallet.nonce.to_le_bytes(),
+        ])
+        .to_bytes();
 
-        wallet.pending_recovery = Some(PendingRecovery {
+        ctx.accounts.recovery_session.set_inner(RecoverySession {
+            wallet: wallet.key(),
             new_authority,
+            new_authority_is_group,
+            new_owner,
+            challenge,
             initiated_at: clock.unix_timestamp,
+            expires_at: clock
+                .unix_timestamp
+                .saturating_add(wallet.recovery_delay)
+                .saturating_add(wallet.recovery_session_grace),
             approvals: 0,
-            executed: false,
+            approvers: [Pubkey::default(); MAX_GUARDIANS],
+            approved_weight: 0,
+            approved_types: 0,
+            type_approvals: [0; GuardianType::COUNT],
+            payer: ctx.accounts.payer.key(),
+            bump: ctx.bumps.recovery_session,
+        });
+        wallet.recovery_pending = true;
+        wallet.last_recovery_cancel_reason = None;
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(
+                AuditAction::RecoveryInitiated,
+                ctx.accounts.initiator.key(),
+                clock.unix_timestamp,
+            );
+        }
+
+        let guardian = &mut ctx.accounts.guardian;
+        guardian.record_action(clock.unix_timestamp);
+
+        emit!(GuardianActed {
+            wallet: wallet.key(),
+            guardian: guardian.pubkey,
+            action: GuardianAction::InitiateRecovery,
+            action_count: guardian.action_count,
         });
 
         emit!(RecoveryInitiated {
             wallet: wallet.key(),
             new_authority,
             executable_at: clock.unix_timestamp + wallet.recovery_delay,
+            challenge,
         });
 
         Ok(())
     }
 
     /// Guardian approves recovery
-    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
-        let wallet = &mut ctx.accounts.wallet;
-        let guardian = &ctx.accounts.guardian;
+    ///
+    /// The guardian must also sign the recovery's challenge through an
+    /// Ed25519 program instruction in the same transaction.
+    pub fn approve_recovery(
+        ctx: Context<ApproveRecovery>,
+        challenge_signature: [u8; 64],
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let recovery = &mut ctx.accounts.recovery_session;
+        let guardian = &mut ctx.accounts.guardian;
+        let now = Clock::get()?.unix_timestamp;
 
-        require!(guardian.is_active, WalletError::GuardianInactive);
-        require!(wallet.pending_recovery.is_some(), WalletError::NoRecoveryPending);
+        require!(guardian.is_usable(now), WalletError::GuardianInactive);
+        require!(guardian.can_approve_recovery, WalletError::GuardianCannotApprove);
+        require!(now <= recovery.expires_at, WalletError::RecoverySessionExpired);
+        // The challenge signature is deterministic, so without this a single
+        // guardian could replay it until the threshold is met
+        let approved = &recovery.approvers[..recovery.approvals as usize];
+        require!(!approved.contains(&guardian.pubkey), WalletError::AlreadyApproved);
+        require!(
+            (recovery.approvals as usize) < MAX_GUARDIANS,
+            WalletError::TooManyGuardians
+        );
+        let challenge = recovery.challenge;
+        require!(
+            has_ed25519_signature(
+                &ctx.accounts.instructions,
+                &guardian.pubkey,
+                &challenge,
+                &challenge_signature,
+            ),
+            WalletError::InvalidSignature
+        );
 
-        let recovery = wallet.pending_recovery.as_mut().unwrap();
+        let wallet_key = wallet.key();
+        let index = recovery.approvals as usize;
+        recovery.approvers[index] = guardian.pubkey;
         recovery.approvals += 1;
+        recovery.approved_types |= guardian.guardian_type.mask();
+        // Approvers are distinct, so this counts guardians per type; those
+        // past the per-type cap are recorded but add no weight
+        let type_approvals = &mut recovery.type_approvals[guardian.guardian_type as usize];
+        *type_approvals += 1;
+        if wallet.max_approvals_per_type == 0 || *type_approvals <= wallet.max_approvals_per_type {
+            recovery.approved_weight += guardian.weight as u16;
+        }
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::RecoveryApproved, ctx.accounts.approver.key(), now);
+        }
+
+        guardian.record_action(now);
+
+        emit!(GuardianActed {
+            wallet: wallet_key,
+            guardian: guardian.pubkey,
+            action: GuardianAction::ApproveRecovery,
+            action_count: guardian.action_count,
+        });
 
         emit!(RecoveryApproved {
-            wallet: wallet.key(),
+            wallet: wallet_key,
             guardian: guardian.pubkey,
             total_approvals: recovery.approvals,
+            total_weight: recovery.approved_weight,
         });
 
         Ok(())
     }
 
-    /// Execute recovery after delay and threshold met
-    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+    /// Execute recovery after delay and threshold met, closing the
+    /// RecoverySession and refunding its rent
+    ///
+    /// Blocked while the wallet is frozen: a freeze usually answers a
+    /// compromise, so an attacker-driven recovery must not complete under it.
+    /// Initiation and approvals still proceed, and the queued recovery can be
+    /// executed once the wallet is unfrozen.
+    ///
+    /// Instead of approve_recovery calls, wallets with a guardian group key
+    /// may pass `aggregated_signature`: a FROST aggregate by the group over
+    /// the recovery challenge, verified through an Ed25519 program
+    /// instruction. It can't show which guardian types signed, so it is
+    /// rejected while guardian diversity is required.
+    ///
+    /// With `require_owner_inactivity`, an owner heartbeat (or any other
+    /// owner-signed action) after initiation counts as a veto: the recovery
+    /// is cancelled instead of executed and the session closed.
+    pub fn execute_recovery(
+        ctx: Context<ExecuteRecovery>,
+        aggregated_signature: Option<[u8; 64]>,
+    ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
         let clock = Clock::get()?;
 
-        let recovery = wallet.pending_recovery.as_ref().ok_or(WalletError::NoRecoveryPending)?;
+        require!(!wallet.is_frozen, WalletError::WalletFrozen);
 
-        require!(
-            recovery.approvals >= wallet.guardian_threshold,
-            WalletError::InsufficientApprovals
-        );
+        let recovery = &ctx.accounts.recovery_session;
+        require!(clock.unix_timestamp <= recovery.expires_at, WalletError::RecoverySessionExpired);
+
+        // Returns Ok so the cancellation and session close are not rolled back
+        if wallet.require_owner_inactivity && wallet.last_owner_activity_at > recovery.initiated_at {
+            let wallet_key = wallet.key();
+            wallet.cancel_recovery(wallet_key, RecoveryCancelReason::OwnerActivity, clock.unix_timestamp);
+            return Ok(());
+        }
+
+        match aggregated_signature {
+            Some(signature) => {
+                let group_key = wallet.guardian_group_key.ok_or(WalletError::NoGuardianGroupKey)?;
+                require!(
+                    wallet.guardian_group_threshold >= wallet.recovery_threshold,
+                    WalletError::InsufficientApprovals
+                );
+                require!(
+                    has_ed25519_signature(&ctx.accounts.instructions, &group_key, &recovery.challenge, &signature),
+                    WalletError::InvalidSignature
+                );
+                require!(!wallet.require_diverse_guardians, WalletError::InsufficientGuardianDiversity);
+                // The group signature can't show which types took part
+                require!(wallet.max_approvals_per_type == 0, WalletError::GuardianTypeCapActive);
+            }
+            // recovery_threshold is the total approval weight required for recovery
+            None => require!(
+                recovery.approved_weight >= wallet.recovery_threshold as u16,
+                WalletError::InsufficientApprovals
+            ),
+        }
         require!(
             clock.unix_timestamp >= recovery.initiated_at + wallet.recovery_delay,
             WalletError::RecoveryDelayNotMet
         );
 
+        // Approvals must come from independent factors when diversity is required
+        if wallet.require_diverse_guardians {
+            require!(
+                recovery.approved_types.count_ones() >= 2,
+                WalletError::InsufficientGuardianDiversity
+            );
+        }
+
+        // Guardian policy: the active set must cover every required type.
+        // Guardian PDAs are passed in remaining_accounts.
+        if wallet.enforce_guardian_types {
+            let covered = guardian_type_coverage(&wallet.key(), ctx.remaining_accounts, clock.unix_timestamp)?;
+            require!(
+                covered & wallet.required_types_mask == wallet.required_types_mask,
+                WalletError::GuardianSetIncomplete
+            );
+        }
+
         let new_authority = recovery.new_authority;
+        let authority_is_group = recovery.new_authority_is_group;
+        let new_owner = recovery.new_owner;
         wallet.authority = new_authority;
-        wallet.pending_recovery = None;
+        wallet.authority_is_group = authority_is_group;
+        wallet.recovery_pending = false;
+        wallet.last_recovery_executed_at = clock.unix_timestamp;
         wallet.nonce += 1;
 
+        let previous_owner = wallet.owner;
+        if let Some(new_owner) = new_owner {
+            wallet.owner = new_owner;
+            wallet.owners.retain(|owner| *owner != previous_owner);
+            if !wallet.owners.contains(&new_owner) {
+                wallet.owners.push(new_owner);
+            }
+            // The new owner may already have been a co-owner, shrinking the set
+            wallet.owner_threshold = wallet.owner_threshold.min(wallet.owners.len() as u8);
+
+            // The new owner indexes the wallet itself via index_wallet
+            let wallet_id = wallet.wallet_id;
+            if let Some(index) = ctx.accounts.previous_owner_index.as_mut() {
+                index.remove(&wallet_id);
+            }
+        }
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::RecoveryExecuted, Pubkey::default(), clock.unix_timestamp);
+        }
+
         emit!(RecoveryExecuted {
             wallet: wallet.key(),
             new_authority,
+            rotated_by_owner: false,
+            authority_is_group,
+            group_pubkey: authority_is_group.then_some(new_authority),
         });
 
+        if let Some(new_owner) = new_owner {
+            emit!(OwnershipTransferred {
+                wallet: wallet.key(),
+                previous_owner,
+                new_owner,
+            });
+        }
+
         Ok(())
     }
 
     /// Freeze wallet in emergency
-    pub fn freeze_wallet(ctx: Context<FreezeWallet>) -> Result<()> {
+    ///
+    /// Only while `freeze_threshold` is 1; otherwise freezing goes through
+    /// approve_freeze.
+    pub fn freeze_wallet(
+        ctx: Context<FreezeWallet>,
+        reason: FreezeReason,
+    ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
+        require!(wallet.freeze_threshold <= 1, WalletError::FreezeNeedsApprovals);
         wallet.is_frozen = true;
+        wallet.freeze_reason = Some(reason);
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(
+                AuditAction::Freeze,
+                ctx.accounts.authority.key(),
+                Clock::get()?.unix_timestamp,
+            );
+        }
 
         emit!(WalletFrozen {
             wallet: wallet.key(),
             frozen_by: ctx.accounts.authority.key(),
+            reason,
         });
 
         Ok(())
     }
 
-    /// Unfreeze wallet
-    pub fn unfreeze_wallet(ctx: Context<UnfreezeWallet>) -> Result<()> {
+    /// Approve freezing the wallet; it freezes once `freeze_threshold`
+    /// distinct parties (the authority, owners or active guardians) approve
+    /// within FREEZE_APPROVAL_WINDOW. An expired round starts over. The
+    /// reason of the approval that completes the round is recorded.
+    pub fn approve_freeze(
+        ctx: Context<ApproveFreeze>,
+        reason: FreezeReason,
+    ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
+        let approver = ctx.accounts.approver.key();
+        let now = Clock::get()?.unix_timestamp;
 
-        // Requires guardian threshold approval for unfreeze
-        wallet.is_frozen = false;
+        require!(!wallet.is_frozen, WalletError::WalletFrozen);
+        let is_guardian = ctx.accounts.guardian.as_ref().is_some_and(|g| {
+            g.wallet == wallet.key() && g.pubkey == approver && g.is_usable(now)
+        });
+        require!(
+            approver == wallet.authority || wallet.owners.contains(&approver) || is_guardian,
+            WalletError::Unauthorized
+        );
 
-        emit!(WalletUnfrozen {
+        let expired = wallet
+            .pending_freeze
+            .as_ref()
+            .is_none_or(|pending| now >= pending.started_at + FREEZE_APPROVAL_WINDOW);
+        if expired {
+            wallet.pending_freeze = Some(PendingFreeze {
+                approvers: [Pubkey::default(); MAX_FREEZE_APPROVALS],
+                approvals: 0,
+                started_at: now,
+            });
+        }
+
+        let pending = wallet.pending_freeze.as_mut().unwrap();
+        let approved = &pending.approvers[..pending.approvals as usize];
+        require!(!approved.contains(&approver), WalletError::AlreadyApproved);
+        require!(
+            (pending.approvals as usize) < MAX_FREEZE_APPROVALS,
+            WalletError::TooManyGuardians
+        );
+        pending.approvers[pending.approvals as usize] = approver;
+        pending.approvals += 1;
+        let approvals = pending.approvals;
+
+        if is_guardian {
+            if let Some(guardian) = ctx.accounts.guardian.as_mut() {
+                guardian.record_action(now);
+            }
+        }
+
+        emit!(FreezeApproved {
             wallet: wallet.key(),
+            approver,
+            approvals,
+            threshold: wallet.freeze_threshold,
         });
 
+        if approvals >= wallet.freeze_threshold {
+            wallet.is_frozen = true;
+            wallet.freeze_reason = Some(reason);
+            wallet.pending_freeze = None;
+
+            emit!(WalletFrozen {
+                wallet: wallet.key(),
+                frozen_by: approver,
+                reason,
+            });
+        }
+
         Ok(())
     }
 
-    /// Update daily spending limit
-    pub fn update_daily_limit(
-        ctx: Context<UpdateLimit>,
-        new_limit: u64,
+    /// Set how many distinct approvals freezing needs (1 keeps the
+    /// single-signer freeze_wallet). Always needs guardian co-signers.
+    pub fn set_freeze_threshold(
+        ctx: Context<UpdateConfig>,
+        freeze_threshold: u8,
     ) -> Result<()> {
         let wallet = &mut ctx.accounts.wallet;
-        wallet.daily_limit = new_limit;
+        let now = Clock::get()?.unix_timestamp;
 
-        emit!(LimitUpdated {
+        let parties = 1 + wallet.owners.len() + wallet.guardian_count as usize;
+        require!(
+            freeze_threshold >= 1
+                && (freeze_threshold as usize) <= parties.min(MAX_FREEZE_APPROVALS),
+            WalletError::InvalidThreshold
+        );
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold,
+            WalletError::InsufficientApprovals
+        );
+
+        wallet.freeze_threshold = freeze_threshold;
+        wallet.pending_freeze = None;
+
+        emit!(FreezeThresholdUpdated {
             wallet: wallet.key(),
-            new_limit,
+            freeze_threshold,
         });
 
         Ok(())
     }
-}
 
-// ============ Account Structures ============
+    /// Unfreeze wallet
+    ///
+    /// The authority must be joined by `unfreeze_threshold` guardians, capped
+    /// at the current guardian count so a wallet without guardians isn't
+    /// stuck frozen, and at least one while it has any. Guardians either
+    /// co-sign as `[guardian_pda, guardian_signer]` pairs in
+    /// remaining_accounts, or, when `guardian_signatures` is given, sign
+    /// `operation_message(OperationType::Unfreeze, wallet, nonce, &[])`
+    /// off-chain with their guardian PDAs in remaining_accounts and the
+    /// signatures verified through Ed25519 program instructions.
+    pub fn unfreeze_wallet(
+        ctx: Context<UnfreezeWallet>,
+        guardian_signatures: Option<Vec<[u8; 64]>>,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
 
-#[account]
-#[derive(Default)]
-pub struct SmartWallet {
-    pub owner: Pubkey,              // Platform user identifier
-    pub wallet_id: [u8; 32],        // Unique wallet ID
-    pub authority: Pubkey,          // MPC-derived signing authority
-    pub guardian_threshold: u8,     // Required guardian approvals
+        require!(!wallet.compliance_hold, WalletError::ComplianceHold);
+
+        let approvals = match guardian_signatures.as_ref() {
+            Some(signatures) => {
+                let message = operation_message(OperationType::Unfreeze, &wallet.key(), wallet.nonce, &[]);
+                count_guardian_signatures(
+                    &wallet.key(),
+                    ctx.remaining_accounts,
+                    &ctx.accounts.instructions,
+                    &message,
+                    signatures,
+                    now,
+                    wallet.max_approvals_per_type,
+                )?
+            }
+            None => count_guardian_signers_capped(
+                &wallet.key(),
+                ctx.remaining_accounts,
+                now,
+                wallet.max_approvals_per_type,
+            )?,
+        };
+        let required = match wallet.guardian_count {
+            0 => 0,
+            guardian_count => wallet.unfreeze_threshold.min(guardian_count).max(1),
+        };
+        require!(approvals >= required, WalletError::InsufficientApprovals);
+
+        wallet.is_frozen = false;
+        wallet.freeze_reason = None;
+        // Retires the signed unfreeze message
+        wallet.nonce += 1;
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::Unfreeze, ctx.accounts.authority.key(), now);
+        }
+
+        emit!(WalletUnfrozen {
+            wallet: wallet.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Update daily spending limit
+    ///
+    /// Decreases apply immediately. Increases are queued as a
+    /// PendingLimitChange and only take effect via apply_limit_change after
+    /// LIMIT_INCREASE_DELAY, so a stolen authority can't raise-and-drain.
+    /// With `limit_change_requires_guardians`, increases also need guardian
+    /// co-signers.
+    ///
+    /// Under the opt-in decrease policy, a nonzero limit below
+    /// `limit_decrease_floor` is queued the same way unless guardians co-sign,
+    /// and drops of `limit_drop_alert_bps` or more emit LimitDecreaseFlagged.
+    pub fn update_daily_limit(
+        ctx: Context<UpdateLimit>,
+        new_limit: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        if new_limit > wallet.daily_limit {
+            if wallet.limit_change_requires_guardians {
+                let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+                require!(
+                    approvals >= wallet.guardian_threshold,
+                    WalletError::InsufficientApprovals
+                );
+            }
+
+            let effective_at = now + LIMIT_INCREASE_DELAY;
+            wallet.pending_limit_change = Some(PendingLimitChange {
+                new_limit,
+                effective_at,
+                ramp: None,
+            });
+
+            emit!(LimitChangeRequested {
+                wallet: wallet.key(),
+                new_limit,
+                effective_at,
+            });
+
+            return Ok(());
+        }
+
+        // Decreases that drop sharply are flagged for monitoring; those below
+        // the floor could quietly lock the user out, so without guardian
+        // co-signers they are queued like an increase and stay cancellable.
+        // 0 is exempt: disabling spending outright is a visible emergency brake.
+        let previous_limit = wallet.daily_limit;
+        let drop_bps = ((previous_limit - new_limit) as u128 * 10_000 / previous_limit.max(1) as u128) as u16;
+        if wallet.limit_drop_alert_bps > 0 && drop_bps >= wallet.limit_drop_alert_bps {
+            emit!(LimitDecreaseFlagged {
+                wallet: wallet.key(),
+                previous_limit,
+                new_limit,
+                drop_bps,
+            });
+        }
+        if new_limit != 0 && new_limit < wallet.limit_decrease_floor {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            if approvals < wallet.guardian_threshold || wallet.guardian_threshold == 0 {
+                // Don't silently replace a queued increase or ramp; it has to
+                // be cancelled or applied first
+                require!(wallet.pending_limit_change.is_none(), WalletError::LimitChangeAlreadyPending);
+                let effective_at = now + LIMIT_INCREASE_DELAY;
+                wallet.pending_limit_change = Some(PendingLimitChange {
+                    new_limit,
+                    effective_at,
+                    ramp: None,
+                });
+
+                emit!(LimitChangeRequested {
+                    wallet: wallet.key(),
+                    new_limit,
+                    effective_at,
+                });
+
+                return Ok(());
+            }
+        }
+
+        wallet.daily_limit = new_limit;
+        wallet.pending_limit_change = None;
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::LimitUpdate, ctx.accounts.authority.key(), now);
+        }
+
+        emit!(LimitUpdated {
+            wallet: wallet.key(),
+            new_limit,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the limit-decrease policy: decreases below `floor` need
+    /// guardian approval (0 disables), and drops of `alert_bps` or more of the
+    /// current limit are flagged (0 disables).
+    /// Lowering or disabling the floor needs guardian co-signers.
+    pub fn set_limit_decrease_policy(
+        ctx: Context<UpdateConfig>,
+        floor: u64,
+        alert_bps: u16,
+    ) -> Result<()> {
+        require!(alert_bps <= 10_000, WalletError::InvalidBasisPoints);
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        if floor < wallet.limit_decrease_floor {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.limit_decrease_floor = floor;
+        wallet.limit_drop_alert_bps = alert_bps;
+
+        emit!(LimitDecreasePolicyUpdated {
+            wallet: wallet.key(),
+            floor,
+            alert_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Propose a new emergency safe address, effective after SAFE_ADDRESS_DELAY
+    pub fn set_safe_address(
+        ctx: Context<UpdateConfig>,
+        safe_address: Pubkey,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.promote_safe_address(now);
+
+        let effective_at = now + SAFE_ADDRESS_DELAY;
+        wallet.pending_safe_address = Some(safe_address);
+        wallet.safe_address_effective_at = effective_at;
+
+        emit!(SafeAddressSet {
+            wallet: wallet.key(),
+            safe_address,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Move the full token balance to the pre-registered safe address, even
+    /// when frozen. Still-locked vesting amounts stay in the wallet.
+    pub fn emergency_withdraw_to_safe(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(!wallet.compliance_hold, WalletError::ComplianceHold);
+
+        wallet.promote_safe_address(now);
+        let safe_address = wallet.safe_address.ok_or(WalletError::NoSafeAddress)?;
+        require!(
+            ctx.accounts.to_token_account.owner == safe_address,
+            WalletError::InvalidSafeAddress
+        );
+
+        let mut amount = ctx.accounts.from_token_account.amount;
+        if let Some(vesting) = wallet.vesting {
+            amount = amount.min(vesting.unlocked(now).saturating_sub(wallet.lifetime_spent));
+        }
+        require!(amount > 0, WalletError::NothingToSweep);
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.from_token_account.to_account_info(),
+            to: ctx.accounts.to_token_account.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.lifetime_spent = wallet.lifetime_spent.saturating_add(amount);
+        wallet.nonce += 1;
+
+        emit!(EmergencyWithdrawal {
+            wallet: wallet.key(),
+            mint: ctx.accounts.from_token_account.mint,
+            safe_address,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Restrict transfers to a UTC seconds-of-day window (0, 0 disables)
+    ///
+    /// A window with start > end wraps past midnight.
+    pub fn set_transfer_window(
+        ctx: Context<UpdateConfig>,
+        start_second: u32,
+        end_second: u32,
+    ) -> Result<()> {
+        require!(
+            start_second < 86400 && end_second < 86400,
+            WalletError::InvalidTransferWindow
+        );
+        // An empty nonzero window would silently block every transfer; 0/0 is "off"
+        require!(
+            start_second != end_second || start_second == 0,
+            WalletError::InvalidTransferWindow
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.allowed_start_second = start_second;
+        wallet.allowed_end_second = end_second;
+
+        emit!(TransferWindowUpdated {
+            wallet: wallet.key(),
+            start_second,
+            end_second,
+        });
+
+        Ok(())
+    }
+
+    /// Set the minimum number of seconds between transfers (0 disables)
+    pub fn set_transfer_cooldown(
+        ctx: Context<UpdateConfig>,
+        min_interval: i64,
+    ) -> Result<()> {
+        require!(min_interval >= 0, WalletError::InvalidTransferCooldown);
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.min_transfer_interval = min_interval;
+
+        emit!(TransferCooldownUpdated {
+            wallet: wallet.key(),
+            min_interval,
+        });
+
+        Ok(())
+    }
+
+    /// Close an expired guardian's PDA and release its slot (permissionless)
+    pub fn prune_expired_guardian(ctx: Context<PruneExpiredGuardian>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let guardian = &ctx.accounts.guardian;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(guardian.is_expired(now), WalletError::GuardianNotExpired);
+
+        wallet.unregister_guardian(guardian.weight)?;
+
+        if let Some(log) = ctx.accounts.audit_log.as_mut() {
+            log.record(AuditAction::GuardianRemoved, Pubkey::default(), now);
+        }
+
+        emit!(GuardianExpired {
+            wallet: wallet.key(),
+            guardian: guardian.pubkey,
+            expired_at: guardian.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Require recovery approvals to span multiple guardian types
+    ///
+    /// Needs guardian co-signers, spanning two types to turn it on.
+    pub fn set_guardian_diversity(
+        ctx: Context<UpdateConfig>,
+        required: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        if wallet.guardian_count > 0 {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold.max(1),
+                WalletError::InsufficientApprovals
+            );
+        }
+        if required {
+            let guardians: Vec<AccountInfo> = ctx.remaining_accounts.iter().step_by(2).cloned().collect();
+            let covered = guardian_type_coverage(&wallet.key(), &guardians, now)?;
+            require!(covered.count_ones() >= 2, WalletError::InsufficientGuardianDiversity);
+        }
+        wallet.require_diverse_guardians = required;
+
+        emit!(GuardianDiversityUpdated {
+            wallet: wallet.key(),
+            required,
+        });
+
+        Ok(())
+    }
+
+    /// Open a pending transfer request that guardians must approve
+    pub fn request_transfer(
+        ctx: Context<RequestTransfer>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let request = &mut ctx.accounts.request;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        wallet.require_spendable()?;
+        require!(amount > 0, WalletError::InvalidAmount);
+        require!(!wallet.requires_review(amount), WalletError::TransferRequiresReview);
+        wallet.open_pending_item()?;
+
+        request.wallet = wallet.key();
+        request.id = wallet.next_transfer_request_id;
+        request.destination = ctx.accounts.to_token_account.key();
+        request.mint = ctx.accounts.to_token_account.mint;
+        request.amount = amount;
+        request.approvals = 0;
+        request.approvers = [Pubkey::default(); MAX_GUARDIANS];
+        request.payer = ctx.accounts.payer.key();
+        request.created_at = Clock::get()?.unix_timestamp;
+        request.bump = ctx.bumps.request;
+
+        wallet.next_transfer_request_id += 1;
+
+        emit!(TransferRequested {
+            wallet: wallet.key(),
+            request: request.key(),
+            destination: request.destination,
+            mint: request.mint,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Guardian approves a pending transfer request
+    pub fn approve_transfer_request(ctx: Context<ApproveTransferRequest>) -> Result<()> {
+        let request = &mut ctx.accounts.request;
+        let guardian = &mut ctx.accounts.guardian;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(guardian.is_usable(now), WalletError::GuardianInactive);
+
+        let approved = &request.approvers[..request.approvals as usize];
+        require!(!approved.contains(&guardian.pubkey), WalletError::AlreadyApproved);
+        require!(
+            (request.approvals as usize) < MAX_GUARDIANS,
+            WalletError::TooManyGuardians
+        );
+
+        let index = request.approvals as usize;
+        request.approvers[index] = guardian.pubkey;
+        request.approvals += 1;
+
+        guardian.record_action(now);
+
+        emit!(GuardianActed {
+            wallet: request.wallet,
+            guardian: guardian.pubkey,
+            action: GuardianAction::ApproveTransfer,
+            action_count: guardian.action_count,
+        });
+
+        emit!(TransferRequestApproved {
+            wallet: request.wallet,
+            request: request.key(),
+            guardian: guardian.pubkey,
+            total_approvals: request.approvals,
+        });
+
+        Ok(())
+    }
+
+    /// Execute an approved transfer request and close it (permissionless)
+    ///
+    /// Needs `guardian_threshold` approvals, and at least one. Every
+    /// transfer_spl check still applies, through spend_from_wallet. If the
+    /// anomaly breaker trips instead, the request is still closed.
+    pub fn execute_transfer_request(ctx: Context<ExecuteTransferRequest>) -> Result<()> {
+        let request = &ctx.accounts.request;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            request.approvals >= ctx.accounts.wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+
+        let amount = request.amount;
+        let spent = spend_from_wallet(
+            SpendAccounts {
+                wallet: &mut ctx.accounts.wallet,
+                global_config: &ctx.accounts.global_config,
+                from_token_account: &ctx.accounts.from_token_account,
+                to_token_account: ctx.accounts.to_token_account.to_account_info(),
+                fee_token_account: ctx.accounts.fee_token_account.as_ref(),
+                frozen_mint: ctx.accounts.frozen_mint.to_account_info(),
+                spend_window: ctx.accounts.spend_window.as_mut(),
+                category_limit: None,
+                price_quote: ctx.accounts.price_quote.as_ref().map(|quote| quote.to_account_info()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            amount,
+            None,
+            now,
+        )?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.close_pending_item();
+        if spent.is_none() {
+            return Ok(());
+        }
+
+        emit!(TransferRequestExecuted {
+            wallet: wallet.key(),
+            request: ctx.accounts.request.key(),
+            to: ctx.accounts.to_token_account.key(),
+            amount,
+            nonce: wallet.nonce,
+        });
+
+        Ok(())
+    }
+
+    /// Authority cancels a pending transfer request
+    pub fn cancel_transfer_request(ctx: Context<CancelTransferRequest>) -> Result<()> {
+        ctx.accounts.wallet.close_pending_item();
+
+        emit!(TransferRequestCancelled {
+            wallet: ctx.accounts.wallet.key(),
+            request: ctx.accounts.request.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Configure guardian review of large transfers. `review_window` 0 turns
+    /// review off.
+    /// Disabling, shortening or raising the threshold needs guardian co-signers.
+    pub fn set_transfer_review(
+        ctx: Context<UpdateConfig>,
+        review_window: i64,
+        review_threshold: u64,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_REVIEW_WINDOW).contains(&review_window),
+            WalletError::InvalidReviewWindow
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let loosens = wallet.review_window > 0
+            && (review_window < wallet.review_window || review_threshold > wallet.review_threshold);
+        if loosens {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.review_window = review_window;
+        wallet.review_threshold = review_threshold;
+
+        emit!(TransferReviewUpdated {
+            wallet: wallet.key(),
+            review_window,
+            review_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Hold a transfer for guardian review. It can be released by anyone once
+    /// `review_window` passes without a guardian veto.
+    pub fn hold_transfer(
+        ctx: Context<HoldTransfer>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(wallet.review_window > 0, WalletError::TransferReviewDisabled);
+        require!(amount > 0, WalletError::InvalidAmount);
+        wallet.check_transfer_gates(amount, now)?;
+        wallet.open_pending_item()?;
+
+        let releasable_at = now + wallet.review_window;
+        ctx.accounts.held_transfer.set_inner(HeldTransfer {
+            wallet: wallet.key(),
+            id: wallet.next_held_transfer_id,
+            destination: ctx.accounts.to_token_account.key(),
+            mint: ctx.accounts.to_token_account.mint,
+            amount,
+            held_at: now,
+            releasable_at,
+            payer: ctx.accounts.payer.key(),
+            bump: ctx.bumps.held_transfer,
+        });
+        wallet.next_held_transfer_id += 1;
+
+        emit!(TransferHeld {
+            wallet: wallet.key(),
+            held_transfer: ctx.accounts.held_transfer.key(),
+            destination: ctx.accounts.to_token_account.key(),
+            amount,
+            releasable_at,
+        });
+
+        Ok(())
+    }
+
+    /// Any active guardian vetoes a held transfer, closing it
+    pub fn guardian_veto(ctx: Context<GuardianVeto>) -> Result<()> {
+        let guardian = &mut ctx.accounts.guardian;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(guardian.is_usable(now), WalletError::GuardianInactive);
+        guardian.record_action(now);
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.close_pending_item();
+
+        emit!(GuardianActed {
+            wallet: wallet.key(),
+            guardian: guardian.pubkey,
+            action: GuardianAction::VetoTransfer,
+            action_count: guardian.action_count,
+        });
+
+        emit!(TransferVetoed {
+            wallet: wallet.key(),
+            held_transfer: ctx.accounts.held_transfer.key(),
+            guardian: guardian.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Execute a held transfer whose review window passed unvetoed, closing
+    /// it (permissionless). Limits are checked at release, not at hold,
+    /// through the same spend_from_wallet path as transfer_spl. If the
+    /// anomaly breaker trips instead, the held transfer is still closed.
+    pub fn release_held_transfer(ctx: Context<ReleaseHeldTransfer>) -> Result<()> {
+        let held = &ctx.accounts.held_transfer;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(now >= held.releasable_at, WalletError::ReviewWindowActive);
+
+        let amount = held.amount;
+        let spent = spend_from_wallet(
+            SpendAccounts {
+                wallet: &mut ctx.accounts.wallet,
+                global_config: &ctx.accounts.global_config,
+                from_token_account: &ctx.accounts.from_token_account,
+                to_token_account: ctx.accounts.to_token_account.to_account_info(),
+                fee_token_account: ctx.accounts.fee_token_account.as_ref(),
+                frozen_mint: ctx.accounts.frozen_mint.to_account_info(),
+                spend_window: ctx.accounts.spend_window.as_mut(),
+                category_limit: None,
+                price_quote: ctx.accounts.price_quote.as_ref().map(|quote| quote.to_account_info()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            amount,
+            None,
+            now,
+        )?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.close_pending_item();
+        if spent.is_none() {
+            return Ok(());
+        }
+
+        emit!(HeldTransferExecuted {
+            wallet: wallet.key(),
+            held_transfer: ctx.accounts.held_transfer.key(),
+            to: ctx.accounts.to_token_account.key(),
+            amount,
+            nonce: wallet.nonce,
+        });
+
+        Ok(())
+    }
+
+    /// Set the daily budget for a spending category
+    pub fn set_category_limit(
+        ctx: Context<SetCategoryLimit>,
+        category: u8,
+        daily_limit: u64,
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let category_limit = &mut ctx.accounts.category_limit;
+
+        if category_limit.wallet == Pubkey::default() {
+            category_limit.wallet = wallet.key();
+            category_limit.category = category;
+            category_limit.daily_spent = 0;
+            category_limit.last_reset_day = Clock::get()?.unix_timestamp / 86400;
+            category_limit.bump = ctx.bumps.category_limit;
+        }
+        category_limit.daily_limit = daily_limit;
+
+        emit!(CategoryLimitUpdated {
+            wallet: wallet.key(),
+            category,
+            daily_limit,
+        });
+
+        Ok(())
+    }
+
+    /// Transfer a single NFT (0 decimals, supply 1) held by the wallet. The
+    /// spending limits count fungible units, so an NFT is not charged to them.
+    pub fn transfer_nft(ctx: Context<TransferNft>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let mint = &ctx.accounts.mint;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(!wallet.requires_review(1), WalletError::TransferRequiresReview);
+        wallet.check_transfer_gates(1, now)?;
+        require!(mint.decimals == 0 && mint.supply == 1, WalletError::NotAnNft);
+        require!(ctx.accounts.frozen_mint.data_is_empty(), WalletError::MintFrozen);
+        require!(
+            ctx.accounts.soulbound.data_is_empty(),
+            WalletError::SoulboundAsset
+        );
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.from_token_account.to_account_info(),
+            to: ctx.accounts.to_token_account.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::transfer(cpi_ctx, 1)?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.last_transfer_at = now;
+        wallet.nonce += 1;
+
+        emit!(NftTransferred {
+            wallet: wallet.key(),
+            mint: ctx.accounts.mint.key(),
+            to: ctx.accounts.to_token_account.key(),
+            nonce: wallet.nonce,
+        });
+
+        Ok(())
+    }
+
+    /// Mark an NFT as soulbound so transfer_nft refuses to move it
+    pub fn mark_soulbound(ctx: Context<MarkSoulbound>) -> Result<()> {
+        let soulbound = &mut ctx.accounts.soulbound;
+        soulbound.wallet = ctx.accounts.wallet.key();
+        soulbound.mint = ctx.accounts.mint.key();
+        soulbound.bump = ctx.bumps.soulbound;
+
+        emit!(SoulboundMarked {
+            wallet: soulbound.wallet,
+            mint: soulbound.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Create the program-level guardian blocklist with its admin. Only the
+    /// program's upgrade authority can call it, so the admin can't be
+    /// front-run.
+    pub fn initialize_blocklist(
+        ctx: Context<InitializeBlocklist>,
+        admin: Pubkey,
+    ) -> Result<()> {
+        let blocklist = &mut ctx.accounts.blocklist;
+        blocklist.admin = admin;
+        blocklist.blocked_count = 0;
+        blocklist.bump = ctx.bumps.blocklist;
+
+        emit!(BlocklistInitialized {
+            blocklist: blocklist.key(),
+            admin,
+        });
+
+        Ok(())
+    }
+
+    /// Block a key from ever being added as a guardian
+    pub fn block_pubkey(
+        ctx: Context<BlockPubkey>,
+        pubkey: Pubkey,
+    ) -> Result<()> {
+        let blocked_key = &mut ctx.accounts.blocked_key;
+        blocked_key.pubkey = pubkey;
+        blocked_key.blocked_at = Clock::get()?.unix_timestamp;
+        blocked_key.bump = ctx.bumps.blocked_key;
+
+        ctx.accounts.blocklist.blocked_count += 1;
+
+        emit!(PubkeyBlocked {
+            pubkey,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Remove a key from the guardian blocklist
+    pub fn unblock_pubkey(ctx: Context<UnblockPubkey>) -> Result<()> {
+        let blocklist = &mut ctx.accounts.blocklist;
+        blocklist.blocked_count = blocklist.blocked_count.saturating_sub(1);
+
+        emit!(PubkeyUnblocked {
+            pubkey: ctx.accounts.blocked_key.pubkey,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Block a key from having its escrowed deposits claimed
+    ///
+    /// Kept apart from the guardian blocklist: blocking a sender does not
+    /// stop it from being a guardian, and vice versa.
+    pub fn block_sender(
+        ctx: Context<BlockSender>,
+        sender: Pubkey,
+    ) -> Result<()> {
+        let blocked_sender = &mut ctx.accounts.blocked_sender;
+        blocked_sender.pubkey = sender;
+        blocked_sender.blocked_at = Clock::get()?.unix_timestamp;
+        blocked_sender.bump = ctx.bumps.blocked_sender;
+
+        emit!(SenderBlocked {
+            sender,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Remove a key from the sender blocklist
+    pub fn unblock_sender(ctx: Context<UnblockSender>) -> Result<()> {
+        emit!(SenderUnblocked {
+            sender: ctx.accounts.blocked_sender.pubkey,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Schedule a self-clearing vacation lock over [lock_from, lock_until)
+    pub fn set_vacation_lock(
+        ctx: Context<UpdateConfig>,
+        lock_from: i64,
+        lock_until: i64,
+    ) -> Result<()> {
+        require!(lock_until >= lock_from, WalletError::InvalidVacationLock);
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.lock_from = lock_from;
+        wallet.lock_until = lock_until;
+
+        emit!(VacationLockSet {
+            wallet: wallet.key(),
+            lock_from,
+            lock_until,
+        });
+
+        Ok(())
+    }
+
+    /// Cap daily spend at a percentage of the current balance (0 disables)
+    ///
+    /// Tightening needs guardian co-signers under the decrease policy.
+    pub fn set_daily_limit_bps(
+        ctx: Context<UpdateConfig>,
+        daily_limit_bps: u16,
+    ) -> Result<()> {
+        require!(daily_limit_bps <= 10_000, WalletError::InvalidBasisPoints);
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let tightens = daily_limit_bps != 0
+            && (wallet.daily_limit_bps == 0 || daily_limit_bps < wallet.daily_limit_bps);
+        if tightens && wallet.limit_decrease_floor > 0 {
+            wallet.require_floor_approval(&wallet.key(), ctx.remaining_accounts, now)?;
+        }
+
+        wallet.daily_limit_bps = daily_limit_bps;
+
+        emit!(DailyLimitBpsUpdated {
+            wallet: wallet.key(),
+            daily_limit_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Set or clear per-weekday daily caps, indexed Monday = 0 in UTC days.
+    ///
+    /// Each entry caps `daily_limit` on that day rather than replacing it;
+    /// raising business-day limits still goes through update_daily_limit.
+    /// Clearing or raising caps, or going below `limit_decrease_floor`, needs guardian co-signers.
+    pub fn set_weekday_limits(
+        ctx: Context<UpdateConfig>,
+        weekday_limits: Option<[u64; 7]>,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let loosens = match (wallet.weekday_limits, weekday_limits) {
+            (Some(current), Some(new)) => new.iter().zip(current.iter()).any(|(new, current)| new > current),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let current = wallet.weekday_limits.unwrap_or([u64::MAX; 7]);
+        let under_floor = weekday_limits.is_some_and(|new| {
+            new.iter()
+                .zip(current.iter())
+                .any(|(new, current)| new < current && *new < wallet.limit_decrease_floor)
+        });
+        if under_floor {
+            wallet.require_floor_approval(&wallet.key(), ctx.remaining_accounts, now)?;
+        } else if loosens {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.weekday_limits = weekday_limits;
+
+        emit!(WeekdayLimitsUpdated {
+            wallet: wallet.key(),
+            weekday_limits,
+        });
+
+        Ok(())
+    }
+
+    /// Toggle event emission on the hot transfer/execute paths to save compute
+    pub fn set_event_emission(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.emit_events = enabled;
+
+        // Always emitted so the toggle itself stays auditable
+        emit!(EventEmissionUpdated {
+            wallet: wallet.key(),
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Deposit tokens into the wallet's escrow, pending a policy-checked claim
+    ///
+    /// Raw SPL transfers can't be refused, so policy-aware senders deposit
+    /// through escrow and the wallet claims after the sender is vetted.
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, WalletError::InvalidAmount);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.sender_token_account.to_account_info(),
+            to: ctx.accounts.escrow_token_account.to_account_info(),
+            authority: ctx.accounts.sender.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        let pending = &mut ctx.accounts.pending_deposit;
+        pending.wallet = wallet.key();
+        pending.id = wallet.next_deposit_id;
+        pending.sender = ctx.accounts.sender.key();
+        pending.mint = ctx.accounts.escrow_token_account.mint;
+        pending.amount = amount;
+        pending.deposited_at = Clock::get()?.unix_timestamp;
+        pending.bump = ctx.bumps.pending_deposit;
+
+        wallet.next_deposit_id += 1;
+
+        emit!(DepositReceived {
+            wallet: wallet.key(),
+            deposit: pending.key(),
+            sender: pending.sender,
+            mint: pending.mint,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Credit an escrowed deposit to the wallet after vetting the sender
+    pub fn claim_deposit(ctx: Context<ClaimDeposit>) -> Result<()> {
+        require!(
+            ctx.accounts.blocked_sender.data_is_empty(),
+            WalletError::SenderBlocked
+        );
+
+        let wallet_key = ctx.accounts.wallet.key();
+        let amount = ctx.accounts.pending_deposit.amount;
+        let bump = ctx.bumps.escrow_authority;
+        let seeds = &[
+            b"escrow",
+            wallet_key.as_ref(),
+            &[bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_token_account.to_account_info(),
+            to: ctx.accounts.wallet_token_account.to_account_info(),
+            authority: ctx.accounts.escrow_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(DepositClaimed {
+            wallet: wallet_key,
+            deposit: ctx.accounts.pending_deposit.key(),
+            sender: ctx.accounts.pending_deposit.sender,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Return an escrowed deposit to its sender instead of claiming it
+    ///
+    /// The only way out for deposits from a blocked sender, which
+    /// claim_deposit refuses.
+    pub fn refund_deposit(ctx: Context<RefundDeposit>) -> Result<()> {
+        let wallet_key = ctx.accounts.wallet.key();
+        let amount = ctx.accounts.pending_deposit.amount;
+        let bump = ctx.bumps.escrow_authority;
+        let seeds = &[
+            b"escrow",
+            wallet_key.as_ref(),
+            &[bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_token_account.to_account_info(),
+            to: ctx.accounts.sender_token_account.to_account_info(),
+            authority: ctx.accounts.escrow_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(DepositRefunded {
+            wallet: wallet_key,
+            deposit: ctx.accounts.pending_deposit.key(),
+            sender: ctx.accounts.pending_deposit.sender,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Add a co-owner; requires the current owner quorum
+    pub fn add_owner(
+        ctx: Context<ManageOwners>,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.require_owner_quorum(ctx.remaining_accounts)?;
+
+        require!(wallet.owners.len() < MAX_OWNERS, WalletError::TooManyOwners);
+        require!(!wallet.owners.contains(&new_owner), WalletError::DuplicateOwner);
+
+        wallet.owners.push(new_owner);
+
+        emit!(OwnerAdded {
+            wallet: wallet.key(),
+            owner: new_owner,
+            owner_count: wallet.owners.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Remove a co-owner; requires the current owner quorum. The primary
+    /// owner can't be removed here: it changes only through recovery, which
+    /// also moves its OwnerIndex entry.
+    pub fn remove_owner(
+        ctx: Context<ManageOwners>,
+        owner: Pubkey,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.require_owner_quorum(ctx.remaining_accounts)?;
+
+        let index = wallet
+            .owners
+            .iter()
+            .position(|o| *o == owner)
+            .ok_or(WalletError::OwnerNotFound)?;
+        require!(owner != wallet.owner, WalletError::PrimaryOwnerNotRemovable);
+        require!(wallet.owners.len() > 1, WalletError::InvalidOwnerThreshold);
+        require!(
+            (wallet.owner_threshold as usize) < wallet.owners.len(),
+            WalletError::InvalidOwnerThreshold
+        );
+
+        wallet.owners.remove(index);
+
+        emit!(OwnerRemoved {
+            wallet: wallet.key(),
+            owner,
+            owner_count: wallet.owners.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Set how many owner signatures spending requires on a shared wallet
+    pub fn set_owner_threshold(
+        ctx: Context<ManageOwners>,
+        owner_threshold: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.require_owner_quorum(ctx.remaining_accounts)?;
+
+        require!(
+            owner_threshold >= 1 && owner_threshold as usize <= wallet.owners.len(),
+            WalletError::InvalidOwnerThreshold
+        );
+        wallet.owner_threshold = owner_threshold;
+
+        emit!(OwnerThresholdUpdated {
+            wallet: wallet.key(),
+            owner_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Create the wallet's audit-log ring buffer
+    pub fn initialize_audit_log(ctx: Context<InitializeAuditLog>) -> Result<()> {
+        let log = &mut ctx.accounts.audit_log;
+        log.wallet = ctx.accounts.wallet.key();
+        log.head = 0;
+        log.len = 0;
+        log.entries = [AuditEntry::default(); AUDIT_LOG_CAPACITY];
+        log.bump = ctx.bumps.audit_log;
+
+        Ok(())
+    }
+
+    /// Set the approval weight required for recovery, independent of guardian_threshold
+    ///
+    /// Needs guardian co-signers and no pending recovery.
+    pub fn update_recovery_threshold(
+        ctx: Context<UpdateConfig>,
+        recovery_threshold: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        require!(
+            recovery_threshold > 0 && recovery_threshold as u16 <= wallet.total_guardian_weight,
+            WalletError::InvalidThreshold
+        );
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+        wallet.recovery_threshold = recovery_threshold;
+
+        emit!(RecoveryThresholdUpdated {
+            wallet: wallet.key(),
+            recovery_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Set the signing threshold used by execute_transaction and co-signed spends
+    ///
+    /// Needs guardian co-signers at the current threshold and no pending recovery.
+    pub fn update_guardian_threshold(
+        ctx: Context<UpdateConfig>,
+        guardian_threshold: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        require!(
+            guardian_threshold > 0 && guardian_threshold <= wallet.guardian_count,
+            WalletError::InvalidThreshold
+        );
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+        wallet.guardian_threshold = guardian_threshold;
+
+        emit!(GuardianThresholdUpdated {
+            wallet: wallet.key(),
+            guardian_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the automatic freeze on anomalous spend (threshold 0 disables)
+    ///
+    /// Disabling or loosening an active breaker needs guardian co-signers.
+    pub fn set_anomaly_threshold(
+        ctx: Context<UpdateConfig>,
+        threshold: u64,
+        window: i64,
+    ) -> Result<()> {
+        require!(window >= 0, WalletError::InvalidAnomalyWindow);
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let softens = wallet.anomaly_threshold != 0
+            && (threshold == 0 || threshold > wallet.anomaly_threshold || window < wallet.anomaly_window);
+        if softens && wallet.guardian_count > 0 {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold.max(1),
+                WalletError::InsufficientApprovals
+            );
+        }
+        if softens || wallet.anomaly_threshold == 0 {
+            wallet.anomaly_window_start = 0;
+            wallet.anomaly_window_spent = 0;
+        }
+        wallet.anomaly_threshold = threshold;
+        wallet.anomaly_window = window;
+
+        emit!(AnomalyThresholdUpdated {
+            wallet: wallet.key(),
+            threshold,
+            window,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the graduated response to anomalous spend: for `duration`
+    /// seconds the daily limit drops to `penalty_bps` of itself instead of
+    /// the wallet freezing. `duration` 0 restores the plain freeze.
+    /// Enabling or easing the penalty needs guardian co-signers.
+    pub fn set_limit_penalty(
+        ctx: Context<UpdateConfig>,
+        penalty_bps: u16,
+        duration: i64,
+    ) -> Result<()> {
+        require!(penalty_bps < 10_000, WalletError::InvalidBasisPoints);
+        require!(
+            (0..=MAX_LIMIT_PENALTY_DURATION).contains(&duration),
+            WalletError::InvalidPenaltyDuration
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let softens = duration > 0
+            && (wallet.limit_penalty_duration == 0
+                || penalty_bps > wallet.limit_penalty_bps
+                || duration < wallet.limit_penalty_duration);
+        if softens {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.limit_penalty_bps = penalty_bps;
+        wallet.limit_penalty_duration = duration;
+
+        emit!(LimitPenaltyConfigUpdated {
+            wallet: wallet.key(),
+            penalty_bps,
+            duration,
+        });
+
+        Ok(())
+    }
+
+    /// Guardian flags suspicious activity, starting (or restarting) the
+    /// configured limit penalty
+    pub fn apply_limit_penalty(ctx: Context<GuardianFlag>) -> Result<()> {
+        let guardian = &mut ctx.accounts.guardian;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(guardian.is_usable(now), WalletError::GuardianInactive);
+        guardian.record_action(now);
+
+        let wallet = &mut ctx.accounts.wallet;
+        require!(wallet.limit_penalty_duration > 0, WalletError::LimitPenaltyNotConfigured);
+        let wallet_key = wallet.key();
+        wallet.apply_limit_penalty(wallet_key, guardian.pubkey, now);
+
+        emit!(GuardianActed {
+            wallet: wallet_key,
+            guardian: guardian.pubkey,
+            action: GuardianAction::ApplyLimitPenalty,
+            action_count: guardian.action_count,
+        });
+
+        Ok(())
+    }
+
+    /// Wrap lamports held by the wallet PDA into its wSOL associated token account
+    ///
+    /// The payer funds the wSOL account's rent if it has to be created; the
+    /// wrapped amount counts against the daily and lifetime limits and the
+    /// spend window. The lamports stay in the wallet's own wSOL account, so
+    /// unlike spend_from_wallet there is no platform fee, and the
+    /// frozen-mint, category, reference-budget and anomaly checks are skipped.
+    pub fn wrap_sol(
+        ctx: Context<WrapSol>,
+        amount: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(amount > 0, WalletError::InvalidAmount);
+        let balance = wallet.to_account_info().lamports();
+        wallet.check_transfer(amount, now, balance)?;
+        wallet.charge_spend_window(ctx.accounts.spend_window.as_deref_mut(), amount, now, balance)?;
+        wallet.reset_daily_spent(now);
+
+        let wallet_info = ctx.accounts.wallet.to_account_info();
+        assert_rent_exempt_after(&wallet_info, amount)?;
+
+        // Program-owned PDA: move lamports directly, then let the token program sync
+        **wallet_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.wsol_account.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        let cpi_accounts = SyncNative {
+            account: ctx.accounts.wsol_account.to_account_info(),
+        };
+        token::sync_native(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts))?;
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.record_spend(amount);
+        wallet.nonce += 1;
+
+        emit!(SolWrapped {
+            wallet: wallet.key(),
+            wsol_account: ctx.accounts.wsol_account.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Close the wallet's wSOL account, returning its balance and rent to the PDA
+    pub fn unwrap_sol(ctx: Context<UnwrapSol>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+
+        wallet.require_spendable()?;
+
+        let amount = ctx.accounts.wsol_account.amount;
+        let lamports_returned = ctx.accounts.wsol_account.to_account_info().lamports();
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.wsol_account.to_account_info(),
+            destination: ctx.accounts.wallet.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+        token::close_account(cpi_ctx)?;
+
+        emit!(SolUnwrapped {
+            wallet: ctx.accounts.wallet.key(),
+            amount,
+            lamports_returned,
+        });
+
+        Ok(())
+    }
+
+    /// Activate a queued limit change once its timelock has elapsed (permissionless)
+    pub fn apply_limit_change(ctx: Context<ApplyLimitChange>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let pending = wallet
+            .pending_limit_change
+            .as_ref()
+            .ok_or(WalletError::NoLimitChangePending)?;
+        require!(now >= pending.effective_at, WalletError::LimitChangeTimelocked);
+
+        let new_limit = pending.new_limit;
+        let ramp = pending.ramp;
+        wallet.pending_limit_change = None;
+
+        match ramp {
+            Some(ramp) => {
+                wallet.limit_ramp_per_day = ramp.per_day;
+                wallet.limit_ramp_target = ramp.target;
+
+                emit!(LimitRampUpdated {
+                    wallet: wallet.key(),
+                    per_day: ramp.per_day,
+                    target: ramp.target,
+                });
+            }
+            None => {
+                wallet.daily_limit = new_limit;
+
+                emit!(LimitUpdated {
+                    wallet: wallet.key(),
+                    new_limit,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Change `recovery_delay`. Lengthening applies immediately; shortening
+    /// is queued for the current delay unless guardians co-sign. Can't be
+    /// changed while a recovery is pending, since the session's expiry was
+    /// fixed from the delay at initiation.
+    pub fn update_recovery_delay(
+        ctx: Context<UpdateConfig>,
+        new_delay: i64,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_RECOVERY_DELAY).contains(&new_delay),
+            WalletError::InvalidRecoveryDelay
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+
+        let immediate = new_delay >= wallet.recovery_delay
+            || (wallet.guardian_threshold > 0
+                && count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)? >= wallet.guardian_threshold);
+        let effective_at = if immediate {
+            wallet.recovery_delay = new_delay;
+            wallet.pending_recovery_delay = None;
+            now
+        } else {
+            let effective_at = now + wallet.recovery_delay;
+            wallet.pending_recovery_delay = Some(PendingDelayChange {
+                new_delay,
+                effective_at,
+            });
+            effective_at
+        };
+
+        emit!(RecoveryDelayUpdated {
+            wallet: wallet.key(),
+            new_delay,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Activate a queued recovery delay shortening once its timelock has
+    /// elapsed (permissionless). Waits until no recovery is pending.
+    pub fn apply_recovery_delay(ctx: Context<ApplyRecoveryDelay>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+
+        let pending = wallet
+            .pending_recovery_delay
+            .as_ref()
+            .ok_or(WalletError::NoDelayChangePending)?;
+        require!(now >= pending.effective_at, WalletError::DelayChangeTimelocked);
+
+        let new_delay = pending.new_delay;
+        wallet.recovery_delay = new_delay;
+        wallet.pending_recovery_delay = None;
+
+        emit!(RecoveryDelayUpdated {
+            wallet: wallet.key(),
+            new_delay,
+            effective_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a queued limit change; callable by the authority, an owner, or a guardian
+    pub fn cancel_limit_change(ctx: Context<CancelLimitChange>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let canceller = ctx.accounts.canceller.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let is_guardian = ctx.accounts.guardian.as_ref().is_some_and(|g| {
+            g.wallet == wallet.key() && g.pubkey == canceller && g.is_usable(now)
+        });
+        require!(
+            canceller == wallet.authority || wallet.owners.contains(&canceller) || is_guardian,
+            WalletError::Unauthorized
+        );
+
+        let pending = wallet
+            .pending_limit_change
+            .take()
+            .ok_or(WalletError::NoLimitChangePending)?;
+
+        if is_guardian {
+            if let Some(guardian) = ctx.accounts.guardian.as_mut() {
+                guardian.record_action(now);
+
+                emit!(GuardianActed {
+                    wallet: wallet.key(),
+                    guardian: guardian.pubkey,
+                    action: GuardianAction::CancelLimitChange,
+                    action_count: guardian.action_count,
+                });
+            }
+        }
+
+        emit!(LimitChangeCancelled {
+            wallet: wallet.key(),
+            new_limit: pending.new_limit,
+            cancelled_by: canceller,
+        });
+
+        Ok(())
+    }
+
+    /// Grow the daily limit automatically with wallet age up to a target (0 disables)
+    ///
+    /// Disabling the ramp or lowering its rate and target applies
+    /// immediately. Anything that could raise the limit is queued as a
+    /// PendingLimitChange like an update_daily_limit increase, including its
+    /// guardian co-signing under `limit_change_requires_guardians`.
+    pub fn set_limit_ramp(
+        ctx: Context<UpdateConfig>,
+        per_day: u64,
+        target: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let disables = per_day == 0 || target <= wallet.daily_limit;
+        let tightens = per_day <= wallet.limit_ramp_per_day && target <= wallet.limit_ramp_target;
+        if !disables && !tightens {
+            if wallet.limit_change_requires_guardians {
+                let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+                require!(
+                    approvals >= wallet.guardian_threshold,
+                    WalletError::InsufficientApprovals
+                );
+            }
+
+            let effective_at = now + LIMIT_INCREASE_DELAY;
+            wallet.pending_limit_change = Some(PendingLimitChange {
+                new_limit: target,
+                effective_at,
+                ramp: Some(LimitRamp { per_day, target }),
+            });
+
+            emit!(LimitChangeRequested {
+                wallet: wallet.key(),
+                new_limit: target,
+                effective_at,
+            });
+
+            return Ok(());
+        }
+
+        wallet.limit_ramp_per_day = per_day;
+        wallet.limit_ramp_target = target;
+
+        emit!(LimitRampUpdated {
+            wallet: wallet.key(),
+            per_day,
+            target,
+        });
+
+        Ok(())
+    }
+
+    /// Add a labeled recipient to the wallet's address book
+    pub fn add_address(
+        ctx: Context<AddAddress>,
+        recipient: Pubkey,
+        label: [u8; 32],
+    ) -> Result<()> {
+        let entry = &mut ctx.accounts.entry;
+        entry.wallet = ctx.accounts.wallet.key();
+        entry.recipient = recipient;
+        entry.label = label;
+        entry.added_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.entry;
+
+        emit!(AddressAdded {
+            wallet: entry.wallet,
+            recipient,
+            label,
+        });
+
+        Ok(())
+    }
+
+    /// Remove a recipient from the address book
+    pub fn remove_address(ctx: Context<RemoveAddress>) -> Result<()> {
+        emit!(AddressRemoved {
+            wallet: ctx.accounts.wallet.key(),
+            recipient: ctx.accounts.entry.recipient,
+        });
+
+        Ok(())
+    }
+
+    /// Guardian panic button: freeze, drop any pending recovery, and rotate
+    /// the authority in one step, bypassing the recovery delay.
+    ///
+    /// Needs `guardian_threshold` co-signing guardians passed in
+    /// remaining_accounts as `[guardian_pda, guardian_signer]` pairs.
+    pub fn panic(
+        ctx: Context<Panic>,
+        new_authority: Pubkey,
+        new_authority_is_group: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(wallet.panic_enabled, WalletError::PanicDisabled);
+        require!(new_authority != Pubkey::default(), WalletError::InvalidAuthority);
+
+        // `caller` is arbitrary, so at least one guardian must always co-sign
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            wallet.guardian_count > 0 && approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+
+        wallet.is_frozen = true;
+        wallet.freeze_reason = Some(FreezeReason::SuspectedCompromise);
+        // Any open RecoverySession is now stale; see close_stale_recovery
+        if wallet.recovery_pending {
+            let wallet_key = wallet.key();
+            wallet.cancel_recovery(wallet_key, RecoveryCancelReason::Superseded, now);
+        }
+        wallet.authority = new_authority;
+        wallet.authority_is_group = new_authority_is_group;
+        wallet.nonce += 1;
+
+        emit!(WalletFrozen {
+            wallet: wallet.key(),
+            frozen_by: ctx.accounts.caller.key(),
+            reason: FreezeReason::SuspectedCompromise,
+        });
+
+        emit!(RecoveryExecuted {
+            wallet: wallet.key(),
+            new_authority,
+            rotated_by_owner: false,
+            authority_is_group: new_authority_is_group,
+            group_pubkey: new_authority_is_group.then_some(new_authority),
+        });
+
+        Ok(())
+    }
+
+    /// Opt in or out of the guardian panic instruction
+    pub fn set_panic_enabled(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        if enabled {
+            require!(wallet.guardian_threshold > 0, WalletError::InvalidThreshold);
+        }
+        wallet.panic_enabled = enabled;
+
+        emit!(PanicEnabledUpdated {
+            wallet: wallet.key(),
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Delegate a stake account whose staker is the wallet PDA to an allowlisted validator
+    ///
+    /// The stake account is created and funded off-chain with the wallet PDA
+    /// as its staker authority; this only performs the delegation.
+    pub fn delegate_stake(ctx: Context<DelegateStake>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+
+        wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        wallet.require_spendable()?;
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let ix = stake::instruction::delegate_stake(
+            &ctx.accounts.stake_account.key(),
+            &wallet.key(),
+            &ctx.accounts.vote_account.key(),
+        );
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.stake_account.to_account_info(),
+                ctx.accounts.vote_account.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.stake_history.to_account_info(),
+                ctx.accounts.stake_config.to_account_info(),
+                ctx.accounts.wallet.to_account_info(),
+                ctx.accounts.stake_program.to_account_info(),
+            ],
+            signer_seeds,
+        )?;
+
+        emit!(StakeDelegated {
+            wallet: ctx.accounts.wallet.key(),
+            stake_account: ctx.accounts.stake_account.key(),
+            vote_account: ctx.accounts.vote_account.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Deactivate a stake account whose staker is the wallet PDA
+    pub fn deactivate_stake(ctx: Context<DeactivateStake>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let ix = stake::instruction::deactivate_stake(&ctx.accounts.stake_account.key(), &wallet.key());
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.stake_account.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.wallet.to_account_info(),
+                ctx.accounts.stake_program.to_account_info(),
+            ],
+            signer_seeds,
+        )?;
+
+        emit!(StakeDeactivated {
+            wallet: ctx.accounts.wallet.key(),
+            stake_account: ctx.accounts.stake_account.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Allow delegating the wallet's stake to a validator vote account
+    pub fn allow_validator(ctx: Context<AllowValidator>) -> Result<()> {
+        let allowed = &mut ctx.accounts.allowed_validator;
+        allowed.wallet = ctx.accounts.wallet.key();
+        allowed.vote_account = ctx.accounts.vote_account.key();
+        allowed.bump = ctx.bumps.allowed_validator;
+
+        emit!(ValidatorAllowed {
+            wallet: allowed.wallet,
+            vote_account: allowed.vote_account,
+        });
+
+        Ok(())
+    }
+
+    /// Invite a guardian; the guardian only counts once the invited key accepts
+    pub fn invite_guardian(
+        ctx: Context<InviteGuardian>,
+        guardian_pubkey: Pubkey,
+        guardian_type: GuardianType,
+        weight: u8,
+        expires_at: i64,
+    ) -> Result<()> {
+        require!(weight > 0, WalletError::InvalidGuardianWeight);
+        require!(
+            ctx.accounts.blocked_key.data_is_empty(),
+            WalletError::GuardianBlocked
+        );
+
+        ctx.accounts.wallet.open_pending_item()?;
+
+        let invite = &mut ctx.accounts.invite;
+        invite.wallet = ctx.accounts.wallet.key();
+        invite.pubkey = guardian_pubkey;
+        invite.guardian_type = guardian_type;
+        invite.weight = weight;
+        invite.expires_at = expires_at;
+        invite.payer = ctx.accounts.payer.key();
+        invite.invited_at = Clock::get()?.unix_timestamp;
+        invite.bump = ctx.bumps.invite;
+
+        emit!(GuardianInvited {
+            wallet: invite.wallet,
+            guardian: guardian_pubkey,
+            guardian_type,
+            weight,
+        });
+
+        Ok(())
+    }
+
+    /// Invited key accepts, activating its Guardian PDA
+    pub fn accept_guardian(ctx: Context<AcceptGuardian>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let invite = &ctx.accounts.invite;
+        let guardian = &mut ctx.accounts.guardian;
+
+        let now = Clock::get()?.unix_timestamp;
+        wallet.register_guardian(invite.weight, now)?;
+        wallet.close_pending_item();
+        guardian.activate(
+            wallet.key(),
+            invite.pubkey,
+            invite.guardian_type,
+            invite.weight,
+            invite.expires_at,
+            now,
+            ctx.bumps.guardian,
+        );
+
+        emit!(GuardianAccepted {
+            wallet: wallet.key(),
+            guardian: invite.pubkey,
+        });
+
+        emit!(GuardianAdded {
+            wallet: wallet.key(),
+            guardian: invite.pubkey,
+            guardian_type: invite.guardian_type,
+            weight: invite.weight,
+        });
+
+        Ok(())
+    }
+
+    /// Authority withdraws a pending guardian invite
+    pub fn cancel_invite(ctx: Context<CancelInvite>) -> Result<()> {
+        ctx.accounts.wallet.close_pending_item();
+
+        emit!(GuardianInviteCancelled {
+            wallet: ctx.accounts.wallet.key(),
+            guardian: ctx.accounts.invite.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Invited key declines a pending guardian invite
+    pub fn decline_invite(ctx: Context<DeclineInvite>) -> Result<()> {
+        ctx.accounts.wallet.close_pending_item();
+
+        emit!(GuardianInviteDeclined {
+            wallet: ctx.accounts.wallet.key(),
+            guardian: ctx.accounts.invite.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Add several guardians (weight 1, no expiry) in one instruction
+    ///
+    /// remaining_accounts holds `[guardian_pda, blocked_key_pda]` per entry, with
+    /// guardian PDAs at consecutive seed indexes from `next_guardian_index`.
+    /// Any invalid entry fails the whole batch.
+    pub fn add_guardians_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddGuardiansBatch<'info>>,
+        entries: Vec<GuardianEntry>,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let wallet_key = wallet.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!entries.is_empty(), WalletError::InvalidGuardianAccounts);
+        require!(
+            ctx.remaining_accounts.len() == entries.len() * 2,
+            WalletError::InvalidGuardianAccounts
+        );
+        require!(
+            wallet.guardian_count as usize + entries.len() <= MAX_GUARDIANS,
+            WalletError::TooManyGuardians
+        );
+        for (i, entry) in entries.iter().enumerate() {
+            require!(
+                !entries[..i].iter().any(|other| other.pubkey == entry.pubkey),
+                WalletError::DuplicateGuardian
+            );
+        }
+
+        let space = 8 + std::mem::size_of::<Guardian>();
+
+        for (entry, accounts) in entries.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let (guardian_info, blocked_info) = (&accounts[0], &accounts[1]);
+
+            let (blocked_pda, _) =
+                Pubkey::find_program_address(&[b"blocked", entry.pubkey.as_ref()], &crate::ID);
+            require!(blocked_info.key() == blocked_pda, WalletError::InvalidGuardianAccounts);
+            require!(blocked_info.data_is_empty(), WalletError::GuardianBlocked);
+
+            let index = wallet.next_guardian_index;
+            let (guardian_pda, bump) = Pubkey::find_program_address(
+                &[b"guardian", wallet_key.as_ref(), &[index]],
+                &crate::ID,
+            );
+            require!(guardian_info.key() == guardian_pda, WalletError::InvalidGuardianAccounts);
+
+            let guardian_seeds: &[&[u8]] = &[b"guardian", wallet_key.as_ref(), &[index], &[bump]];
+            create_pda_account(
+                &ctx.accounts.payer.to_account_info(),
+                guardian_info,
+                &ctx.accounts.system_program.to_account_info(),
+                space,
+                guardian_seeds,
+            )?;
+
+            let mut guardian = Guardian::default();
+            guardian.activate(wallet_key, entry.pubkey, entry.guardian_type, 1, 0, now, bump);
+            guardian.try_serialize(&mut &mut guardian_info.data.borrow_mut()[..])?;
+
+            wallet.register_guardian(1, now)?;
+        }
+
+        emit!(GuardiansAdded {
+            wallet: wallet_key,
+            count: entries.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Grant a program a bounded token budget it can draw via CPI without
+    /// the authority signing each transfer. Draws can only pay `destination`.
+    /// Re-granting replaces the budget. Shared wallets need the owner quorum,
+    /// as for any spend.
+    pub fn grant_program_budget(
+        ctx: Context<GrantProgramBudget>,
+        program_id: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        ctx.accounts.wallet.authorize_spend(&ctx.accounts.authority, ctx.remaining_accounts)?;
+        require!(
+            expires_at > Clock::get()?.unix_timestamp,
+            WalletError::InvalidExpiry
+        );
+
+        let budget = &mut ctx.accounts.program_budget;
+        budget.wallet = ctx.accounts.wallet.key();
+        budget.program_id = program_id;
+        budget.destination = destination;
+        budget.remaining = amount;
+        budget.expires_at = expires_at;
+        budget.bump = ctx.bumps.program_budget;
+
+        emit!(ProgramBudgetGranted {
+            wallet: budget.wallet,
+            program_id,
+            destination,
+            amount,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke a program budget and reclaim its rent
+    pub fn revoke_program_budget(ctx: Context<RevokeProgramBudget>) -> Result<()> {
+        emit!(ProgramBudgetRevoked {
+            wallet: ctx.accounts.wallet.key(),
+            program_id: ctx.accounts.program_budget.program_id,
+        });
+
+        Ok(())
+    }
+
+    /// Transfer tokens out of the wallet against a program budget. The
+    /// budgeted program proves it is the caller by signing with its
+    /// `[BUDGET_CALLER_SEED]` PDA; wallet limits still apply through
+    /// spend_from_wallet, as for transfer_spl.
+    pub fn draw_program_budget(ctx: Context<DrawProgramBudget>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+
+        let budget = &ctx.accounts.program_budget;
+        require!(now < budget.expires_at, WalletError::ProgramBudgetExpired);
+        require!(amount <= budget.remaining, WalletError::ProgramBudgetExhausted);
+
+        let spent = spend_from_wallet(
+            SpendAccounts {
+                wallet: &mut ctx.accounts.wallet,
+                global_config: &ctx.accounts.global_config,
+                from_token_account: &ctx.accounts.from_token_account,
+                to_token_account: ctx.accounts.to_token_account.to_account_info(),
+                fee_token_account: ctx.accounts.fee_token_account.as_ref(),
+                frozen_mint: ctx.accounts.frozen_mint.to_account_info(),
+                spend_window: ctx.accounts.spend_window.as_mut(),
+                category_limit: None,
+                price_quote: ctx.accounts.price_quote.as_ref().map(|quote| quote.to_account_info()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            amount,
+            None,
+            now,
+        )?;
+        if spent.is_none() {
+            return Ok(());
+        }
+
+        let budget = &mut ctx.accounts.program_budget;
+        budget.remaining -= amount;
+
+        emit!(ProgramBudgetDrawn {
+            wallet: ctx.accounts.wallet.key(),
+            program_id: budget.program_id,
+            amount,
+            remaining: budget.remaining,
+        });
+
+        Ok(())
+    }
+
+    /// Designate the compliance admin. The authority may only set it while
+    /// unset; afterwards only the current admin can replace or clear it.
+    pub fn set_compliance_admin(
+        ctx: Context<SetComplianceAdmin>,
+        admin: Option<Pubkey>,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let signer = ctx.accounts.signer.key();
+
+        match wallet.compliance_admin {
+            Some(current) => require!(signer == current, WalletError::Unauthorized),
+            None => require!(signer == wallet.authority, WalletError::Unauthorized),
+        }
+        require!(!wallet.compliance_hold, WalletError::ComplianceHold);
+
+        let previous_admin = wallet.compliance_admin;
+        wallet.compliance_admin = admin;
+
+        emit!(ComplianceAdminUpdated {
+            wallet: wallet.key(),
+            previous_admin,
+            admin,
+        });
+
+        Ok(())
+    }
+
+    /// Place a compliance hold, blocking transfers and unfreeze
+    pub fn set_compliance_hold(ctx: Context<ManageComplianceHold>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.compliance_hold = true;
+
+        emit!(ComplianceHoldSet {
+            wallet: wallet.key(),
+            admin: ctx.accounts.compliance_admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Release a compliance hold; any user freeze stays in place
+    pub fn release_compliance_hold(ctx: Context<ManageComplianceHold>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.compliance_hold = false;
+
+        emit!(ComplianceHoldReleased {
+            wallet: wallet.key(),
+            admin: ctx.accounts.compliance_admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Switch execute_transaction between a single MPC aggregate signature
+    /// and individual signatures
+    ///
+    /// Needs guardian co-signers once the wallet has guardians.
+    pub fn set_mpc_mode(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        if wallet.guardian_count > 0 {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold.max(1),
+                WalletError::InsufficientApprovals
+            );
+        }
+        wallet.mpc_mode = enabled;
+
+        emit!(MpcModeUpdated {
+            wallet: wallet.key(),
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Store or clear a guardian's identity commitment
+    pub fn set_guardian_commitment(
+        ctx: Context<SetGuardianCommitment>,
+        commitment: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let guardian = &mut ctx.accounts.guardian;
+        guardian.identity_commitment = commitment;
+
+        emit!(GuardianCommitmentUpdated {
+            wallet: guardian.wallet,
+            guardian: guardian.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Check an identifier and salt against a guardian's stored commitment
+    ///
+    /// The preimage is part of the instruction data, so callers should run
+    /// this via simulation rather than landing it on-chain.
+    pub fn verify_guardian_commitment(
+        ctx: Context<VerifyGuardianCommitment>,
+        identifier: Vec<u8>,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let stored = ctx
+            .accounts
+            .guardian
+            .identity_commitment
+            .ok_or(WalletError::NoIdentityCommitment)?;
+        require!(
+            identity_commitment(&salt, &identifier) == stored,
+            WalletError::IdentityCommitmentMismatch
+        );
+
+        Ok(())
+    }
+
+    /// Cap the number of open transfer requests and guardian invites
+    pub fn set_max_pending_items(
+        ctx: Context<UpdateConfig>,
+        max_pending_items: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.max_pending_items = max_pending_items;
+
+        emit!(MaxPendingItemsUpdated {
+            wallet: wallet.key(),
+            max_pending_items,
+        });
+
+        Ok(())
+    }
+
+    /// Set the lifetime spending cap. Once set it can only be lowered.
+    pub fn set_lifetime_limit(
+        ctx: Context<UpdateConfig>,
+        lifetime_limit: u64,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        if let Some(current) = wallet.lifetime_limit {
+            require!(lifetime_limit <= current, WalletError::LifetimeLimitIncrease);
+        }
+        wallet.lifetime_limit = Some(lifetime_limit);
+
+        emit!(LifetimeLimitUpdated {
+            wallet: wallet.key(),
+            lifetime_limit,
+            lifetime_spent: wallet.lifetime_spent,
+        });
+
+        Ok(())
+    }
+
+    /// Require guardian co-signers for daily limit increases. Turning the
+    /// requirement off needs the same guardian quorum.
+    pub fn set_limit_change_requires_guardians(
+        ctx: Context<UpdateConfig>,
+        enabled: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+
+        if wallet.limit_change_requires_guardians && !enabled {
+            let now = Clock::get()?.unix_timestamp;
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+        wallet.limit_change_requires_guardians = enabled;
+
+        emit!(LimitChangeGuardiansUpdated {
+            wallet: wallet.key(),
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Evaluate every time-dependent spending condition at the current clock
+    /// and emit them as one PolicySnapshot. Mutates nothing.
+    ///
+    /// Pass the wallet's token account to apply the percentage-of-balance cap.
+    pub fn get_policy_snapshot(ctx: Context<GetPolicySnapshot>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let effective_daily_limit = match ctx.accounts.token_account.as_ref() {
+            Some(account) => wallet.effective_daily_limit(now, account.amount),
+            None => wallet.ramped_daily_limit(now),
+        };
+        let remaining_today = if wallet.require_spendable().is_ok() {
+            effective_daily_limit.saturating_sub(wallet.spent_today(now))
+        } else {
+            0
+        };
+
+        emit!(PolicySnapshot {
+            wallet: wallet.key(),
+            effective_daily_limit,
+            remaining_today,
+            is_frozen: wallet.is_frozen,
+            compliance_hold: wallet.compliance_hold,
+            vacation_locked: wallet.is_vacation_locked(now),
+            within_transfer_window: wallet.within_transfer_window(now),
+            cooldown_elapsed: wallet.transfer_cooldown_elapsed(now),
+            next_reset_at: (now / 86400 + 1) * 86400,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the transfer notification program (see notify_transfer)
+    pub fn set_notify_program(
+        ctx: Context<UpdateConfig>,
+        notify_program: Option<Pubkey>,
+        strict_hook: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.notify_program = notify_program;
+        wallet.strict_hook = strict_hook;
+
+        emit!(NotifyProgramUpdated {
+            wallet: wallet.key(),
+            notify_program,
+            strict_hook,
+        });
+
+        Ok(())
+    }
+
+    /// Set a one-time vesting schedule capping cumulative spend
+    pub fn set_vesting_schedule(
+        ctx: Context<UpdateConfig>,
+        schedule: VestingSchedule,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+
+        require!(wallet.vesting.is_none(), WalletError::VestingAlreadySet);
+        require!(
+            schedule.duration > 0 && schedule.cliff >= 0 && schedule.cliff <= schedule.duration,
+            WalletError::InvalidVestingSchedule
+        );
+        wallet.vesting = Some(schedule);
+
+        emit!(VestingScheduleSet {
+            wallet: wallet.key(),
+            start: schedule.start,
+            cliff: schedule.cliff,
+            duration: schedule.duration,
+            total: schedule.total,
+        });
+
+        Ok(())
+    }
+
+    /// Retire this wallet_id: create a wallet PDA under `new_wallet_id`
+    /// carrying over the authority, owners and policy, and mark this wallet
+    /// migrated. Needs guardian co-signers.
+    ///
+    /// Guardian PDAs are bound to the old wallet address, so guardians must be
+    /// re-added on the new wallet. Guardian thresholds carry over and stay
+    /// unmet until then, keeping guardian-gated changes locked; freezing
+    /// falls back to a single signer. Policy and spend accounting carry over,
+    /// pending changes and other in-flight state do not. Token balances move
+    /// with sweep_to_migrated.
+    ///
+    /// If the owner's OwnerIndex is passed, the new wallet replaces the old
+    /// one in it.
+    pub fn migrate_to_new_wallet(
+        ctx: Context<MigrateWallet>,
+        new_wallet_id: [u8; 32],
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        wallet.require_spendable()?;
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold,
+            WalletError::InsufficientApprovals
+        );
+
+        // daily_spent, lifetime_spent and the other spend counters carry over
+        // so migrating can't reset a limit
+        let new_wallet_key = ctx.accounts.new_wallet.key();
+        ctx.accounts.new_wallet.set_inner(SmartWallet {
+            wallet_id: new_wallet_id,
+            guardian_count: 0,
+            next_guardian_index: 0,
+            total_guardian_weight: 0,
+            guardians_added_today: 0,
+            guardian_add_day: now / 86400,
+            guardian_group_key: None,
+            guardian_group_threshold: 0,
+            freeze_threshold: 1,
+            pending_freeze: None,
+            freeze_reason: None,
+            pending_item_count: 0,
+            next_transfer_request_id: 0,
+            next_deposit_id: 0,
+            next_held_transfer_id: 0,
+            pending_safe_address: None,
+            safe_address_effective_at: 0,
+            pending_limit_change: None,
+            pending_recovery_delay: None,
+            pending_reference_budget: None,
+            last_recovery_cancel_reason: None,
+            recent_idempotency_keys: [[0; 16]; IDEMPOTENCY_KEY_SLOTS],
+            idempotency_head: 0,
+            reconcile_account: Pubkey::default(),
+            last_reconciled_balance: 0,
+            last_reconciled_spent: 0,
+            last_owner_activity_at: now,
+            migrated_to: None,
+            bump: ctx.bumps.new_wallet,
+            ..(**wallet).clone()
+        });
+
+        wallet.migrated_to = Some(new_wallet_key);
+
+        let wallet_id = wallet.wallet_id;
+        if let Some(index) = ctx.accounts.owner_index.as_mut() {
+            index.remove(&wallet_id);
+            add_to_owner_index(index, &new_wallet_key, new_wallet_id);
+        }
+
+        emit!(WalletMigrated {
+            old_wallet: wallet.key(),
+            new_wallet: new_wallet_key,
+            new_wallet_id,
+        });
+
+        Ok(())
+    }
+
+    /// Move a token balance from a migrated wallet to its successor (permissionless)
+    pub fn sweep_to_migrated(ctx: Context<SweepToMigrated>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+
+        require!(!wallet.compliance_hold, WalletError::ComplianceHold);
+        let amount = ctx.accounts.from_token_account.amount;
+        require!(amount > 0, WalletError::NothingToSweep);
+
+        let seeds = ctx.accounts.wallet.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.from_token_account.to_account_info(),
+            to: ctx.accounts.to_token_account.to_account_info(),
+            authority: ctx.accounts.wallet.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds), amount)?;
+
+        emit!(WalletSwept {
+            wallet: ctx.accounts.wallet.key(),
+            mint: ctx.accounts.from_token_account.mint,
+            to: ctx.accounts.to_token_account.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Cap execute_transaction calls per day (0 = no cap)
+    pub fn set_max_exec_daily_count(
+        ctx: Context<UpdateConfig>,
+        max_exec_daily_count: u32,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.max_exec_daily_count = max_exec_daily_count;
+
+        emit!(ExecRateLimitUpdated {
+            wallet: wallet.key(),
+            max_exec_daily_count,
+        });
+
+        Ok(())
+    }
+
+    /// Configure or clear the daily budget valued in a reference token
+    ///
+    /// Quotes come from GlobalConfig's `price_program`. Setting a first
+    /// budget or tightening the current one applies immediately. Clearing it,
+    /// switching the reference mint, raising the limit or accepting older
+    /// quotes loosens it, so that is queued for LIMIT_INCREASE_DELAY and
+    /// activated by apply_reference_budget.
+    /// Setting or lowering it needs guardian co-signers under the decrease policy.
+    pub fn set_reference_budget(
+        ctx: Context<UpdateConfig>,
+        budget: Option<ReferenceBudget>,
+    ) -> Result<()> {
+        if let Some(budget) = budget.as_ref() {
+            require!(budget.max_price_age > 0, WalletError::InvalidPriceQuote);
+        }
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let loosens = wallet
+            .reference_budget
+            .is_some_and(|current| current.loosened_by(budget.as_ref()));
+        let tightens = budget.is_some_and(|new| {
+            wallet.reference_budget.is_none_or(|current| new.daily_limit < current.daily_limit)
+        });
+        if tightens && wallet.limit_decrease_floor > 0 {
+            wallet.require_floor_approval(&wallet.key(), ctx.remaining_accounts, now)?;
+        }
+        let effective_at = if loosens {
+            let effective_at = now + LIMIT_INCREASE_DELAY;
+            wallet.pending_reference_budget = Some(PendingReferenceBudget {
+                budget,
+                effective_at,
+            });
+            effective_at
+        } else {
+            wallet.reference_budget = budget;
+            wallet.pending_reference_budget = None;
+            now
+        };
+
+        emit!(ReferenceBudgetUpdated {
+            wallet: wallet.key(),
+            reference_mint: budget.map(|budget| budget.reference_mint),
+            daily_limit: budget.map_or(0, |budget| budget.daily_limit),
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Activate a queued reference budget loosening once its timelock has
+    /// elapsed (permissionless)
+    pub fn apply_reference_budget(ctx: Context<ApplyReferenceBudget>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let pending = wallet
+            .pending_reference_budget
+            .as_ref()
+            .ok_or(WalletError::NoLimitChangePending)?;
+        require!(now >= pending.effective_at, WalletError::LimitChangeTimelocked);
+
+        let budget = pending.budget;
+        wallet.reference_budget = budget;
+        wallet.pending_reference_budget = None;
+
+        emit!(ReferenceBudgetUpdated {
+            wallet: wallet.key(),
+            reference_mint: budget.map(|budget| budget.reference_mint),
+            daily_limit: budget.map_or(0, |budget| budget.daily_limit),
+            effective_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Owner proof of life; vetoes a pending recovery when
+    /// `require_owner_inactivity` is set
+    pub fn heartbeat(ctx: Context<OwnerAction>) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+        wallet.last_owner_activity_at = now;
+
+        emit!(OwnerHeartbeat {
+            wallet: wallet.key(),
+            owner: wallet.owner,
+            at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Require owner inactivity since initiation for a recovery to execute
+    pub fn set_require_owner_inactivity(
+        ctx: Context<UpdateConfig>,
+        required: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.require_owner_inactivity = required;
+
+        emit!(OwnerInactivityRequirementUpdated {
+            wallet: wallet.key(),
+            required,
+        });
+
+        Ok(())
+    }
+
+    /// Cap the instruction data and signature bytes execute_transaction accepts
+    pub fn set_max_exec_payload_len(
+        ctx: Context<UpdateConfig>,
+        max_exec_payload_len: u16,
+    ) -> Result<()> {
+        require!(
+            (1..=MAX_EXEC_PAYLOAD_LEN).contains(&max_exec_payload_len),
+            WalletError::InvalidPayloadLimit
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.max_exec_payload_len = max_exec_payload_len;
+
+        emit!(ExecPayloadLimitUpdated {
+            wallet: wallet.key(),
+            max_exec_payload_len,
+        });
+
+        Ok(())
+    }
+
+    /// Set how many execute nonces ahead of `exec_nonce` may be used out of order
+    pub fn set_exec_nonce_window(
+        ctx: Context<UpdateConfig>,
+        window: u8,
+    ) -> Result<()> {
+        require!(window <= 64, WalletError::InvalidNonceWindow);
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.exec_nonce_window = window;
+
+        emit!(ExecNonceWindowUpdated {
+            wallet: wallet.key(),
+            window,
+        });
+
+        Ok(())
+    }
+
+    /// Set the cooldown after an executed recovery before another can be
+    /// initiated. The panic path is exempt.
+    pub fn set_post_recovery_cooldown(
+        ctx: Context<UpdateConfig>,
+        cooldown: i64,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_POST_RECOVERY_COOLDOWN).contains(&cooldown),
+            WalletError::InvalidRecoveryCooldown
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.post_recovery_cooldown = cooldown;
+
+        emit!(PostRecoveryCooldownUpdated {
+            wallet: wallet.key(),
+            cooldown,
+        });
+
+        Ok(())
+    }
+
+    /// Enforce the daily limit over a trailing window of `window_seconds` in
+    /// addition to calendar days (0 disables). Creates the SpendWindow PDA.
+    pub fn set_spend_window(
+        ctx: Context<SetSpendWindow>,
+        window_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            (0..=86400).contains(&window_seconds),
+            WalletError::InvalidSpendWindow
+        );
+
+        let window = &mut ctx.accounts.spend_window;
+        if window.wallet == Pubkey::default() {
+            window.wallet = ctx.accounts.wallet.key();
+            window.bump = ctx.bumps.spend_window;
+        }
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.spend_window_seconds = window_seconds;
+
+        emit!(SpendWindowUpdated {
+            wallet: wallet.key(),
+            window_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Emit one GuardianAlert per guardian PDA in remaining_accounts for a
+    /// frozen wallet (permissionless, safe to retry)
+    pub fn broadcast_freeze_alert(ctx: Context<ReadWallet>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        require!(wallet.is_frozen, WalletError::WalletNotFrozen);
+
+        let reason = wallet.freeze_reason.unwrap_or(FreezeReason::Manual);
+        for guardian_info in ctx.remaining_accounts.iter() {
+            require!(guardian_info.owner == &crate::ID, WalletError::InvalidGuardianAccounts);
+            let guardian = Guardian::try_deserialize(&mut &guardian_info.data.borrow()[..])?;
+            require!(guardian.wallet == wallet.key(), WalletError::InvalidGuardianAccounts);
+
+            emit!(GuardianAlert {
+                wallet: wallet.key(),
+                guardian: guardian.pubkey,
+                reason,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Quarantine a single mint: transfer_spl rejects it until unfrozen
+    pub fn freeze_mint(ctx: Context<FreezeMint>) -> Result<()> {
+        let frozen = &mut ctx.accounts.frozen_mint;
+        frozen.wallet = ctx.accounts.wallet.key();
+        frozen.mint = ctx.accounts.mint.key();
+        frozen.bump = ctx.bumps.frozen_mint;
+
+        emit!(MintFrozen {
+            wallet: frozen.wallet,
+            mint: frozen.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Lift a per-mint freeze
+    pub fn unfreeze_mint(ctx: Context<UnfreezeMint>) -> Result<()> {
+        emit!(MintUnfrozen {
+            wallet: ctx.accounts.wallet.key(),
+            mint: ctx.accounts.frozen_mint.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Cap guardian additions per day (0 = no cap)
+    ///
+    /// Raising or removing the cap needs guardian co-signers.
+    pub fn set_max_guardians_per_day(
+        ctx: Context<UpdateConfig>,
+        max_guardians_per_day: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let current = wallet.max_guardians_per_day;
+        let loosens = current > 0 && (max_guardians_per_day == 0 || max_guardians_per_day > current);
+        if loosens {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.max_guardians_per_day = max_guardians_per_day;
+
+        emit!(GuardianRateLimitUpdated {
+            wallet: wallet.key(),
+            max_guardians_per_day,
+        });
+
+        Ok(())
+    }
+
+    /// Evaluate each transfer_spl gate for `amount` of `mint` to `recipient`
+    /// and emit the results as one TransferEligibility event. Mutates nothing.
+    ///
+    /// Takes the same policy accounts as transfer_spl and evaluates the limits
+    /// through the same SpendChecks, fee included, so `eligible` matches what
+    /// transfer_spl would do. `in_address_book` is informational only.
+    pub fn check_transfer_eligibility(
+        ctx: Context<CheckTransferEligibility>,
+        amount: u64,
+        mint: Pubkey,
+        recipient: Pubkey,
+        _category: u8,
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let category_info = ctx.accounts.category_limit.to_account_info();
+        let checks = SpendChecks::evaluate(
+            wallet,
+            &ctx.accounts.global_config,
+            &ctx.accounts.token_account,
+            ctx.accounts.spend_window.as_deref(),
+            Some(&category_info),
+            ctx.accounts.price_quote.as_ref().map(|quote| quote.as_ref()),
+            amount,
+            now,
+        )?;
+        let total = checks.total;
+
+        let paused = ctx.accounts.global_config.paused;
+        let frozen = wallet.is_frozen;
+        let compliance_hold = wallet.compliance_hold;
+        let spending_disabled = wallet.spending_disabled();
+        let vacation_locked = wallet.is_vacation_locked(now);
+        let within_transfer_window = wallet.within_transfer_window(now);
+        let cooldown_elapsed = wallet.transfer_cooldown_elapsed(now);
+        let requires_review = wallet.requires_review(amount);
+        let within_lifetime_limit = checks.within_lifetime_limit && wallet.within_vested_amount(total, now);
+        let mint_frozen = !ctx.accounts.frozen_mint.data_is_empty();
+        let in_address_book = !ctx.accounts.address_book_entry.data_is_empty();
+
+        emit!(TransferEligibility {
+            wallet: wallet.key(),
+            mint,
+            recipient,
+            amount,
+            fee: checks.fee,
+            eligible: !paused
+                && wallet.check_transfer_gates(total, now).is_ok()
+                && !requires_review
+                && !mint_frozen
+                && checks.passes(wallet),
+            paused,
+            frozen,
+            compliance_hold,
+            spending_disabled,
+            vacation_locked,
+            within_transfer_window,
+            cooldown_elapsed,
+            requires_review,
+            within_daily_limit: checks.within_daily_limit,
+            within_lifetime_limit,
+            within_spend_window: checks.within_spend_window,
+            within_category_limit: checks.within_category_limit,
+            within_reference_budget: checks.within_reference_budget,
+            mint_frozen,
+            in_address_book,
+        });
+
+        Ok(())
+    }
+
+    /// Suspend a guardian without removing it; it stops counting toward
+    /// approvals. Needs guardian co-signers and no pending recovery.
+    pub fn deactivate_guardian(ctx: Context<ManageGuardian>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+
+        let guardian = &mut ctx.accounts.guardian;
+        guardian.is_active = false;
+        guardian.pending_reactivation = false;
+
+        emit!(GuardianDeactivated {
+            wallet: guardian.wallet,
+            guardian: guardian.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Ask an inactive guardian to re-consent; it stays inactive until the
+    /// guardian key confirms
+    pub fn request_reactivate_guardian(ctx: Context<ManageGuardian>) -> Result<()> {
+        let guardian = &mut ctx.accounts.guardian;
+        require!(!guardian.is_active, WalletError::GuardianAlreadyActive);
+        guardian.pending_reactivation = true;
+
+        emit!(GuardianReactivationRequested {
+            wallet: guardian.wallet,
+            guardian: guardian.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Guardian key confirms a requested reactivation
+    pub fn confirm_reactivation(ctx: Context<ConfirmReactivation>) -> Result<()> {
+        let guardian = &mut ctx.accounts.guardian;
+        require!(guardian.pending_reactivation, WalletError::NoReactivationPending);
+        guardian.is_active = true;
+        guardian.pending_reactivation = false;
+
+        emit!(GuardianReactivated {
+            wallet: guardian.wallet,
+            guardian: guardian.pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Allow or forbid a guardian to approve recovery, e.g. keeping an Email
+    /// guardian for alerts only. Needs guardian co-signers and no pending recovery.
+    pub fn set_guardian_recovery_permission(
+        ctx: Context<ManageGuardian>,
+        can_approve_recovery: bool,
+    ) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold.max(1),
+            WalletError::InsufficientApprovals
+        );
+
+        let guardian = &mut ctx.accounts.guardian;
+        guardian.can_approve_recovery = can_approve_recovery;
+
+        emit!(GuardianRecoveryPermissionUpdated {
+            wallet: guardian.wallet,
+            guardian: guardian.pubkey,
+            can_approve_recovery,
+        });
+
+        Ok(())
+    }
+
+    /// Set the guardian quorum for unfreezing, independent of recovery.
+    /// Can't be changed while frozen, and is at least 1 once the wallet has
+    /// guardians. Lowering it needs guardian co-signers.
+    pub fn set_unfreeze_threshold(
+        ctx: Context<UpdateConfig>,
+        unfreeze_threshold: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.is_frozen, WalletError::WalletFrozen);
+        require!(
+            unfreeze_threshold <= wallet.guardian_count
+                && (unfreeze_threshold > 0 || wallet.guardian_count == 0),
+            WalletError::InvalidThreshold
+        );
+        if unfreeze_threshold < wallet.unfreeze_threshold {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold.max(1),
+                WalletError::InsufficientApprovals
+            );
+        }
+        wallet.unfreeze_threshold = unfreeze_threshold;
+
+        emit!(UnfreezeThresholdUpdated {
+            wallet: wallet.key(),
+            unfreeze_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Create the program-level config with its admin. Only the program's
+    /// upgrade authority can call it, so the admin can't be front-run.
+    pub fn initialize_global_config(
+        ctx: Context<InitializeGlobalConfig>,
+        admin: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.global_config;
+        config.admin = admin;
+        config.paused = false;
+        config.fee_bps = 0;
+        config.fee_destination = Pubkey::default();
+        config.price_program = Pubkey::default();
+        config.bump = ctx.bumps.global_config;
+
+        emit!(GlobalConfigInitialized {
+            config: config.key(),
+            admin,
+        });
+
+        Ok(())
+    }
+
+    /// Pause or resume fund-moving and guardian instructions across all wallets.
+    /// freeze_wallet, panic and revoke_delegate stay available while paused.
+    pub fn set_program_paused(
+        ctx: Context<GlobalAdmin>,
+        paused: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.global_config;
+        config.paused = paused;
+
+        emit!(ProgramPausedUpdated {
+            paused,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Set the platform fee skimmed from every transfer_spl (0 bps disables).
+    /// `fee_destination` owns the token accounts that receive the fee.
+    pub fn set_platform_fee(
+        ctx: Context<GlobalAdmin>,
+        fee_bps: u16,
+        fee_destination: Pubkey,
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, WalletError::InvalidBasisPoints);
+        require!(
+            fee_bps == 0 || fee_destination != Pubkey::default(),
+            WalletError::InvalidFeeDestination
+        );
+
+        let config = &mut ctx.accounts.global_config;
+        config.fee_bps = fee_bps;
+        config.fee_destination = fee_destination;
+
+        emit!(PlatformFeeUpdated {
+            fee_bps,
+            fee_destination,
+        });
+
+        Ok(())
+    }
+
+    /// Set the program whose PriceQuote accounts every reference budget is
+    /// priced with
+    pub fn set_price_program(
+        ctx: Context<GlobalAdmin>,
+        price_program: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.global_config;
+        config.price_program = price_program;
+
+        emit!(PriceProgramUpdated {
+            price_program,
+        });
+
+        Ok(())
+    }
+
+    /// Hand the global admin role to a new key
+    pub fn transfer_global_admin(
+        ctx: Context<GlobalAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        require!(new_admin != Pubkey::default(), WalletError::InvalidAuthority);
+
+        let config = &mut ctx.accounts.global_config;
+        let previous_admin = config.admin;
+        config.admin = new_admin;
+
+        emit!(GlobalAdminTransferred {
+            previous_admin,
+            new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Owner rotates the authority immediately, e.g. after an MPC key refresh,
+    /// without the guardian recovery delay
+    pub fn rotate_authority(
+        ctx: Context<RotateAuthority>,
+        new_authority: Pubkey,
+        new_authority_is_group: bool,
+    ) -> Result<()> {
+        require!(new_authority != Pubkey::default(), WalletError::InvalidAuthority);
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.authority = new_authority;
+        wallet.authority_is_group = new_authority_is_group;
+        wallet.nonce += 1;
+        wallet.last_owner_activity_at = Clock::get()?.unix_timestamp;
+
+        emit!(RecoveryExecuted {
+            wallet: wallet.key(),
+            new_authority,
+            rotated_by_owner: true,
+            authority_is_group: new_authority_is_group,
+            group_pubkey: new_authority_is_group.then_some(new_authority),
+        });
+
+        Ok(())
+    }
+
+    /// Owner veto: cancel the pending recovery and refund the session rent
+    pub fn cancel_recovery(ctx: Context<CancelRecovery>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let wallet = &mut ctx.accounts.wallet;
+        let wallet_key = wallet.key();
+        wallet.last_owner_activity_at = now;
+        wallet.cancel_recovery(wallet_key, RecoveryCancelReason::OwnerVeto, now);
+
+        Ok(())
+    }
+
+    /// Cancel a recovery whose session deadline has passed, refunding the
+    /// session rent so a fresh initiate_recovery can start (permissionless)
+    pub fn expire_recovery(ctx: Context<ExpireRecovery>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now > ctx.accounts.recovery_session.expires_at,
+            WalletError::RecoverySessionNotExpired
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        let wallet_key = wallet.key();
+        wallet.cancel_recovery(wallet_key, RecoveryCancelReason::Expired, now);
+
+        Ok(())
+    }
+
+    /// Set how long past recovery_delay a recovery session stays executable.
+    /// Applies to sessions initiated afterwards.
+    pub fn set_recovery_session_grace(
+        ctx: Context<UpdateConfig>,
+        grace: i64,
+    ) -> Result<()> {
+        require!(
+            (MIN_RECOVERY_SESSION_GRACE..=MAX_RECOVERY_SESSION_GRACE).contains(&grace),
+            WalletError::InvalidRecoverySessionGrace
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        wallet.recovery_session_grace = grace;
+
+        emit!(RecoverySessionGraceUpdated {
+            wallet: wallet.key(),
+            grace,
+        });
+
+        Ok(())
+    }
+
+    /// Close a RecoverySession left behind after panic cleared the pending
+    /// recovery, refunding its payer (permissionless)
+    pub fn close_stale_recovery(ctx: Context<CloseStaleRecovery>) -> Result<()> {
+        emit!(RecoverySessionClosed {
+            wallet: ctx.accounts.wallet.key(),
+            payer: ctx.accounts.payer.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Register or clear the guardians' FROST group key for aggregated
+    /// recovery. `threshold` is the group's signing threshold from the key
+    /// ceremony, compared against recovery_threshold as if each guardian had
+    /// weight 1. Needs guardian co-signers.
+    pub fn set_guardian_group_key(
+        ctx: Context<UpdateConfig>,
+        group_key: Option<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold,
+            WalletError::InsufficientApprovals
+        );
+        if group_key.is_some() {
+            require!(
+                threshold > 0 && threshold <= wallet.guardian_count,
+                WalletError::InvalidThreshold
+            );
+        }
+
+        wallet.guardian_group_key = group_key;
+        wallet.guardian_group_threshold = if group_key.is_some() { threshold } else { 0 };
+
+        emit!(GuardianGroupKeyUpdated {
+            wallet: wallet.key(),
+            group_key,
+            threshold: wallet.guardian_group_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Cap how many guardians of a single GuardianType count toward the
+    /// recovery and unfreeze quorums (0 removes the cap). Approvals beyond
+    /// the cap are accepted but add nothing.
+    ///
+    /// A cap can make recovery unreachable, so it needs guardian-threshold
+    /// co-signers and can't change while a recovery is pending.
+    pub fn set_max_approvals_per_type(
+        ctx: Context<UpdateConfig>,
+        max_approvals_per_type: u8,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+        let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+        require!(
+            approvals >= wallet.guardian_threshold,
+            WalletError::InsufficientApprovals
+        );
+
+        wallet.max_approvals_per_type = max_approvals_per_type;
+
+        emit!(GuardianTypeCapUpdated {
+            wallet: wallet.key(),
+            max_approvals_per_type,
+        });
+
+        Ok(())
+    }
+
+    /// Toggle observation mode: transfer_spl evaluates the daily,
+    /// trailing-window, category and reference limits but only emits
+    /// LimitWouldBlock when one fails. The lifetime cap, freezes, holds,
+    /// locks and vesting stay enforced.
+    /// Turning it on needs guardian co-signers, so a wallet with guardians.
+    pub fn set_observe_only(
+        ctx: Context<UpdateConfig>,
+        observe_only: bool,
+    ) -> Result<()> {
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        if observe_only {
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                wallet.guardian_threshold > 0 && approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+        wallet.observe_only = observe_only;
+
+        emit!(ObserveOnlyUpdated {
+            wallet: wallet.key(),
+            observe_only,
+        });
+
+        Ok(())
+    }
+
+    /// Add a wallet to its current owner's OwnerIndex, creating the index if
+    /// needed (idempotent; the owner must sign). Covers wallets created
+    /// before the index existed, migrated wallets and owner changes.
+    pub fn index_wallet(ctx: Context<IndexWallet>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let index = &mut ctx.accounts.owner_index;
+        if index.owner == Pubkey::default() {
+            index.owner = wallet.owner;
+            index.bump = ctx.bumps.owner_index;
+        }
+        add_to_owner_index(index, &wallet.key(), wallet.wallet_id);
+
+        Ok(())
+    }
+
+    /// Drop a wallet from an OwnerIndex whose owner no longer owns it
+    /// (permissionless)
+    pub fn prune_owner_index(ctx: Context<PruneOwnerIndex>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let index = &mut ctx.accounts.owner_index;
+        require!(index.remove(&wallet.wallet_id), WalletError::WalletNotIndexed);
+
+        emit!(OwnerIndexEntryRemoved {
+            owner: index.owner,
+            wallet: wallet.key(),
+            wallet_id: wallet.wallet_id,
+        });
+
+        Ok(())
+    }
+
+    /// Drop any entry from the signer's own OwnerIndex, whoever the wallet
+    /// names as owner
+    pub fn remove_from_owner_index(
+        ctx: Context<RemoveFromOwnerIndex>,
+        wallet_id: [u8; 32],
+    ) -> Result<()> {
+        let index = &mut ctx.accounts.owner_index;
+        require!(index.remove(&wallet_id), WalletError::WalletNotIndexed);
+
+        let (wallet, _) = Pubkey::find_program_address(&[b"wallet", wallet_id.as_ref()], &crate::ID);
+        emit!(OwnerIndexEntryRemoved {
+            owner: index.owner,
+            wallet,
+            wallet_id,
+        });
+
+        Ok(())
+    }
+
+    /// Set the guardian types (GuardianType::mask() bits) a valid guardian set
+    /// must include, and whether execute_recovery enforces it
+    ///
+    /// Enforcing or adding a required type needs guardian co-signers and no pending recovery.
+    pub fn set_required_guardian_types(
+        ctx: Context<UpdateConfig>,
+        required_types_mask: u8,
+        enforce_on_recovery: bool,
+    ) -> Result<()> {
+        require!(
+            required_types_mask & !GuardianType::ALL_MASK == 0,
+            WalletError::InvalidGuardianTypeMask
+        );
+
+        let wallet = &mut ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let enforced_before = if wallet.enforce_guardian_types { wallet.required_types_mask } else { 0 };
+        let enforced_after = if enforce_on_recovery { required_types_mask } else { 0 };
+        if enforced_after & !enforced_before != 0 {
+            require!(!wallet.recovery_pending, WalletError::RecoveryAlreadyPending);
+            let approvals = count_guardian_signers(&wallet.key(), ctx.remaining_accounts, now)?;
+            require!(
+                approvals >= wallet.guardian_threshold,
+                WalletError::InsufficientApprovals
+            );
+        }
+
+        wallet.required_types_mask = required_types_mask;
+        wallet.enforce_guardian_types = enforce_on_recovery;
+
+        emit!(RequiredGuardianTypesUpdated {
+            wallet: wallet.key(),
+            required_types_mask,
+            enforce_on_recovery,
+        });
+
+        Ok(())
+    }
+
+    /// Check that the active guardian PDAs in remaining_accounts cover every
+    /// required type and emit the result as GuardianSetValidated (permissionless)
+    pub fn validate_guardian_set(ctx: Context<ReadWallet>) -> Result<()> {
+        let wallet = &ctx.accounts.wallet;
+        let now = Clock::get()?.unix_timestamp;
+
+        let covered_types = guardian_type_coverage(&wallet.key(), ctx.remaining_accounts, now)?;
+        let missing_types = wallet.required_types_mask & !covered_types;
+
+        emit!(GuardianSetValidated {
+            wallet: wallet.key(),
+            required_types_mask: wallet.required_types_mask,
+            covered_types,
+            missing_types,
+            valid: missing_types == 0,
+        });
+
+        Ok(())
+    }
+}
+
+// ============ Constants ============
+
+/// Program interface version, bumped whenever instructions or account
+/// layouts change
+pub const VERSION: u32 = 1;
+
+/// SmartWallet::feature_flags bits, one per opt-in feature. Append only:
+/// clients rely on a bit keeping its meaning across versions.
+pub const FEATURE_MULTI_OWNER: u64 = 1 << 0;
+pub const FEATURE_SAFE_ADDRESS: u64 = 1 << 1;
+pub const FEATURE_PANIC: u64 = 1 << 2;
+pub const FEATURE_GUARDIAN_DIVERSITY: u64 = 1 << 3;
+pub const FEATURE_GUARDIAN_TYPE_COVERAGE: u64 = 1 << 4;
+pub const FEATURE_GUARDIAN_GROUP_KEY: u64 = 1 << 5;
+pub const FEATURE_OWNER_INACTIVITY: u64 = 1 << 6;
+pub const FEATURE_LIFETIME_LIMIT: u64 = 1 << 7;
+pub const FEATURE_VESTING: u64 = 1 << 8;
+pub const FEATURE_SPEND_WINDOW: u64 = 1 << 9;
+pub const FEATURE_ANOMALY_FREEZE: u64 = 1 << 10;
+pub const FEATURE_LIMIT_PENALTY: u64 = 1 << 11;
+pub const FEATURE_WEEKDAY_LIMITS: u64 = 1 << 12;
+pub const FEATURE_REFERENCE_BUDGET: u64 = 1 << 13;
+pub const FEATURE_TRANSFER_REVIEW: u64 = 1 << 14;
+pub const FEATURE_OBSERVE_ONLY: u64 = 1 << 15;
+
+/// Delay before a newly set safe address can receive emergency withdrawals
+pub const SAFE_ADDRESS_DELAY: i64 = 2 * 86400;
+
+/// Maximum number of guardians per wallet
+pub const MAX_GUARDIANS: usize = 7;
+
+/// Domain prefix for every message the MPC service signs for this program
+pub const MESSAGE_DOMAIN: &[u8] = b"leet_wallet:v1";
+
+/// Maximum number of co-owners on a shared wallet
+pub const MAX_OWNERS: usize = 5;
+
+/// Number of entries retained by an AuditLog before the oldest is overwritten
+pub const AUDIT_LOG_CAPACITY: usize = 32;
+
+/// Delay before a daily-limit increase can be applied
+pub const LIMIT_INCREASE_DELAY: i64 = 86400;
+
+/// Default cap on open transfer requests and guardian invites per wallet
+pub const DEFAULT_MAX_PENDING_ITEMS: u8 = 16;
+
+/// Upper bound on post_recovery_cooldown so a compromised authority can't
+/// lock the guardians out of recovery indefinitely
+pub const MAX_POST_RECOVERY_COOLDOWN: i64 = 7 * 86400;
+
+/// Upper bound on recovery_delay so a misconfiguration can't make recovery
+/// effectively impossible
+pub const MAX_RECOVERY_DELAY: i64 = 90 * 86400;
+
+/// Distinct parties that can approve a freeze: authority, owners and guardians
+pub const MAX_FREEZE_APPROVALS: usize = 1 + MAX_OWNERS + MAX_GUARDIANS;
+
+/// Freeze approvals older than this no longer count
+pub const FREEZE_APPROVAL_WINDOW: i64 = 86400;
+
+/// Upper bound on a limit penalty so a guardian flag can't throttle the
+/// wallet indefinitely
+pub const MAX_LIMIT_PENALTY_DURATION: i64 = 30 * 86400;
+
+/// Upper bound on the guardian review window for held transfers
+pub const MAX_REVIEW_WINDOW: i64 = 7 * 86400;
+
+/// Window after recovery_delay in which a recovery session can still be
+/// executed before it must be restarted
+pub const DEFAULT_RECOVERY_SESSION_GRACE: i64 = 7 * 86400;
+pub const MIN_RECOVERY_SESSION_GRACE: i64 = 3600;
+pub const MAX_RECOVERY_SESSION_GRACE: i64 = 30 * 86400;
+
+/// Spend records kept by a SpendWindow ring buffer
+pub const SPEND_WINDOW_SLOTS: usize = 16;
+
+/// Upper bound on the platform transfer fee (10%)
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Wallet ids an OwnerIndex can hold; further wallets are only counted
+pub const OWNER_INDEX_CAPACITY: usize = 16;
+
+/// Fixed-point scale of PriceQuote.price
+pub const PRICE_SCALE: u64 = 1_000_000_000;
+
+/// Transfer idempotency keys remembered per wallet
+pub const IDEMPOTENCY_KEY_SLOTS: usize = 8;
+
+/// Default and upper bound for max_exec_payload_len: one transaction's size
+pub const MAX_EXEC_PAYLOAD_LEN: u16 = 1232;
+
+/// Seed of the PDA a budgeted program signs with when drawing its budget
+pub const BUDGET_CALLER_SEED: &[u8] = b"budget_caller";
+
+// ============ Helpers ============
+
+/// Count distinct active guardians of `wallet` that co-signed this instruction.
+///
+/// `accounts` is read as `[guardian_pda, guardian_signer]` pairs; each pair
+/// counts once when the PDA belongs to the wallet and its pubkey signed.
+pub fn count_guardian_signers(
+    wallet: &Pubkey,
+    accounts: &[AccountInfo],
+    now: i64,
+) -> Result<u8> {
+    count_guardian_signers_capped(wallet, accounts, now, 0)
+}
+
+/// count_guardian_signers, counting at most `max_per_type` guardians of any
+/// one GuardianType (0 = uncapped). Extra signers are still validated.
+///
+/// Guardians are distinct by pubkey, so one key registered under several
+/// guardian PDAs counts once.
+pub fn count_guardian_signers_capped(
+    wallet: &Pubkey,
+    accounts: &[AccountInfo],
+    now: i64,
+    max_per_type: u8,
+) -> Result<u8> {
+    require!(accounts.len().is_multiple_of(2), WalletError::InvalidGuardianAccounts);
+
+    let mut seen: Vec<Pubkey> = Vec::with_capacity(accounts.len() / 2);
+    let mut per_type = [0u8; GuardianType::COUNT];
+    for pair in accounts.chunks(2) {
+        let (guardian_info, signer_info) = (&pair[0], &pair[1]);
+
+        require!(guardian_info.owner == &crate::ID, WalletError::InvalidGuardianAccounts);
+        let guardian = Guardian::try_deserialize(&mut &guardian_info.data.borrow()[..])?;
+
+        require!(guardian.wallet == *wallet, WalletError::InvalidGuardianAccounts);
+        require!(guardian.is_usable(now), WalletError::GuardianInactive);
+        require!(
+            signer_info.is_signer && signer_info.key() == guardian.pubkey,
+            WalletError::InvalidSignature
+        );
+
+        if !seen.contains(&guardian.pubkey) {
+            seen.push(guardian.pubkey);
+            per_type[guardian.guardian_type as usize] += 1;
+        }
+    }
+
+    Ok(capped_count(&per_type, max_per_type))
+}
+
+/// Sum of per-type counts with each type limited to `max_per_type` (0 = uncapped)
+fn capped_count(per_type: &[u8; GuardianType::COUNT], max_per_type: u8) -> u8 {
+    per_type
+        .iter()
+        .map(|count| if max_per_type == 0 { *count } else { (*count).min(max_per_type) })
+        .sum()
+}
+
+/// Bitmask of the guardian types among `accounts`, read as guardian PDAs of
+/// `wallet`. Inactive or expired guardians don't count.
+///
+/// Omitting a guardian can only shrink the result, so callers may pass a
+/// subset of the set.
+pub fn guardian_type_coverage(wallet: &Pubkey, accounts: &[AccountInfo], now: i64) -> Result<u8> {
+    let mut covered = 0u8;
+    for guardian_info in accounts {
+        require!(guardian_info.owner == &crate::ID, WalletError::InvalidGuardianAccounts);
+        let guardian = Guardian::try_deserialize(&mut &guardian_info.data.borrow()[..])?;
+        require!(guardian.wallet == *wallet, WalletError::InvalidGuardianAccounts);
+
+        if guardian.is_usable(now) {
+            covered |= guardian.guardian_type.mask();
+        }
+    }
+    Ok(covered)
+}
+
+/// Count distinct active guardians of `wallet` that signed `message` off-chain.
+///
+/// `accounts` are guardian PDAs; each counts once when one of `signatures`
+/// verifies against its pubkey through an Ed25519 program instruction. At most
+/// `max_per_type` guardians of one GuardianType count (0 = uncapped).
+pub fn count_guardian_signatures(
+    wallet: &Pubkey,
+    accounts: &[AccountInfo],
+    instructions: &AccountInfo,
+    message: &[u8],
+    signatures: &[[u8; 64]],
+    now: i64,
+    max_per_type: u8,
+) -> Result<u8> {
+    let mut seen: Vec<Pubkey> = Vec::with_capacity(accounts.len());
+    let mut per_type = [0u8; GuardianType::COUNT];
+    for guardian_info in accounts {
+        require!(guardian_info.owner == &crate::ID, WalletError::InvalidGuardianAccounts);
+        let guardian = Guardian::try_deserialize(&mut &guardian_info.data.borrow()[..])?;
+
+        require!(guardian.wallet == *wallet, WalletError::InvalidGuardianAccounts);
+        if !guardian.is_usable(now) || seen.contains(&guardian.pubkey) {
+            continue;
+        }
+
+        let signed = signatures
+            .iter()
+            .any(|signature| has_ed25519_signature(instructions, &guardian.pubkey, message, signature));
+        if signed {
+            seen.push(guardian.pubkey);
+            per_type[guardian.guardian_type as usize] += 1;
+        }
+    }
+
+    Ok(capped_count(&per_type, max_per_type))
+}
+
+/// Domain-separated message an off-chain signer approves for one operation.
+///
+/// Format: `sha256(MESSAGE_DOMAIN || op_tag:u8 || wallet:32 || nonce:u64le || payload)`.
+/// Each operation type draws from its own nonce space on `SmartWallet`, and the
+/// tag keeps a signature for one operation from verifying as another.
+pub fn operation_message(
+    op: OperationType,
+    wallet: &Pubkey,
+    nonce: u64,
+    payload: &[u8],
+) -> [u8; 32] {
+    hash::hashv(&[
+        MESSAGE_DOMAIN,
+        &[op as u8],
+        wallet.as_ref(),
+        &nonce.to_le_bytes(),
+        payload,
+    ])
+    .to_bytes()
+}
+
+/// Whether the transaction carries an Ed25519 program instruction that
+/// verified `signature` by `pubkey` over `message`.
+///
+/// Only self-contained Ed25519 entries (all offsets in the same instruction)
+/// are considered, so data can't be borrowed from unrelated instructions.
+pub fn has_ed25519_signature(
+    instructions: &AccountInfo,
+    pubkey: &Pubkey,
+    message: &[u8],
+    signature: &[u8; 64],
+) -> bool {
+    let mut index = 0;
+    while let Ok(ix) = ix_sysvar::load_instruction_at_checked(index, instructions) {
+        index += 1;
+        if ix.program_id != ed25519_program::ID || ix.data.len() < 2 {
+            continue;
+        }
+
+        let data = &ix.data;
+        for entry in 0..data[0] as usize {
+            let start = 2 + entry * 14;
+            let Some(header) = data.get(start..start + 14) else {
+                break;
+            };
+            let read = |at: usize| u16::from_le_bytes([header[at], header[at + 1]]) as usize;
+
+            let this_ix = u16::MAX as usize;
+            if read(2) != this_ix || read(6) != this_ix || read(12) != this_ix {
+                continue;
+            }
+
+            let (sig_offset, key_offset, msg_offset, msg_len) = (read(0), read(4), read(8), read(10));
+            if data.get(sig_offset..sig_offset + 64) == Some(&signature[..])
+                && data.get(key_offset..key_offset + 32) == Some(pubkey.as_ref())
+                && data.get(msg_offset..msg_offset + msg_len) == Some(message)
+            {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Count distinct `owners` among the signing accounts
+pub fn count_owner_signers<'a, 'info: 'a>(
+    owners: &[Pubkey],
+    accounts: impl Iterator<Item = &'a AccountInfo<'info>>,
+) -> u8 {
+    let mut seen: Vec<Pubkey> = Vec::with_capacity(owners.len());
+    for info in accounts {
+        if info.is_signer && owners.contains(info.key) && !seen.contains(info.key) {
+            seen.push(info.key());
+        }
+    }
+    seen.len() as u8
+}
+
+/// Canonical wallet PDA and bump for `wallet_id`
+pub fn wallet_pda(wallet_id: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"wallet", wallet_id.as_ref()], &crate::ID)
+}
+
+/// Day of week for a unix timestamp, Monday = 0. Uses the same UTC day
+/// boundary as the daily limit rollover; 1970-01-01 was a Thursday.
+pub fn day_of_week(now: i64) -> usize {
+    (now.div_euclid(86400) + 3).rem_euclid(7) as usize
+}
+
+/// Commitment binding a guardian to an off-chain identifier (e.g. an email)
+pub fn identity_commitment(salt: &[u8; 32], identifier: &[u8]) -> [u8; 32] {
+    hash::hashv(&[salt, identifier]).to_bytes()
+}
+
+/// CPI the wallet's notify program after a successful transfer.
+///
+/// Callback ABI: data is `sha256("global:on_wallet_transfer")[..8] ||
+/// wallet:32 || amount:u64le || recipient:32`, with the wallet PDA as the
+/// only account (read-only). The wallet never signs the callback.
+pub fn notify_transfer<'info>(
+    program: &AccountInfo<'info>,
+    wallet: &AccountInfo<'info>,
+    amount: u64,
+    recipient: &Pubkey,
+) -> Result<()> {
+    let mut data = hash::hash(b"global:on_wallet_transfer").to_bytes()[..8].to_vec();
+    data.extend_from_slice(wallet.key.as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(recipient.as_ref());
+
+    let ix = Instruction {
+        program_id: program.key(),
+        accounts: vec![AccountMeta::new_readonly(wallet.key(), false)],
+        data,
+    };
+    invoke(&ix, &[wallet.clone(), program.clone()])?;
+
+    Ok(())
+}
+
+/// Pass a limit check, or fail with the rule's error. Under `observe_only`
+/// a failure emits LimitWouldBlock and passes, except for the lifetime cap,
+/// which is a fixed total and always enforced.
+pub fn enforce_limit(
+    wallet: &Pubkey,
+    observe_only: bool,
+    rule: LimitRule,
+    amount: u64,
+    passed: bool,
+) -> Result<()> {
+    if passed {
+        return Ok(());
+    }
+    if observe_only && rule != LimitRule::Lifetime {
+        emit!(LimitWouldBlock {
+            wallet: *wallet,
+            rule,
+            amount,
+        });
+        return Ok(());
+    }
+    Err(rule.error().into())
+}
+
+/// Add `wallet_id` to `index`, emitting OwnerIndexEntryAdded, or
+/// OwnerIndexFull when it doesn't fit. Never fails, so a full index can't
+/// block wallet creation.
+pub fn add_to_owner_index(index: &mut OwnerIndex, wallet: &Pubkey, wallet_id: [u8; 32]) {
+    if index.contains(&wallet_id) {
+        return;
+    }
+    if (index.len as usize) < OWNER_INDEX_CAPACITY {
+        index.wallet_ids[index.len as usize] = wallet_id;
+        index.len += 1;
+
+        emit!(OwnerIndexEntryAdded {
+            owner: index.owner,
+            wallet: *wallet,
+            wallet_id,
+            len: index.len,
+        });
+    } else {
+        index.unindexed_count = index.unindexed_count.saturating_add(1);
+
+        emit!(OwnerIndexFull {
+            owner: index.owner,
+            wallet: *wallet,
+            wallet_id,
+        });
+    }
+}
+
+/// Create a rent-exempt, program-owned PDA of `space` bytes paid by `payer`.
+///
+/// create_account fails on an address that already holds lamports, which
+/// anyone can arrange by pre-funding the PDA; in that case only the rent
+/// shortfall is transferred before allocating and assigning it.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let current = target.lamports();
+
+    if current == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                &[signer_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    if current < rent {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+            ),
+            rent - current,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+/// Fail unless `account` stays rent-exempt after `amount` lamports leave it.
+///
+/// Call before every direct lamport debit from a program-owned account.
+/// wrap_sol is currently the only one: token outflows move SPL balances,
+/// stake instructions never debit the wallet, and closes go through Anchor.
+pub fn assert_rent_exempt_after(account: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_floor = Rent::get()?.minimum_balance(account.data_len());
+    let remaining = account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(WalletError::WouldBreakRentExemption)?;
+    require!(remaining >= rent_floor, WalletError::WouldBreakRentExemption);
+    Ok(())
+}
+
+/// Result of each spend_from_wallet limit for one outflow, evaluated without
+/// side effects so simulate_transfer and check_transfer_eligibility report
+/// exactly what the spend would enforce
+pub struct SpendChecks {
+    pub fee: u64,
+    pub total: u64,                 // amount + fee, what every limit is charged
+    pub within_daily_limit: bool,
+    pub within_lifetime_limit: bool,
+    pub within_spend_window: bool,
+    pub within_category_limit: bool,
+    pub within_reference_budget: bool,
+    pub reference_value: u64,
+    pub category_limit: Option<CategoryLimit>,
+}
+
+impl SpendChecks {
+    /// Fails only when an account the wallet's policy needs is missing or
+    /// invalid (spend window, price quote, category limit)
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate(
+        wallet: &SmartWallet,
+        global_config: &GlobalConfig,
+        from_token_account: &TokenAccount,
+        spend_window: Option<&SpendWindow>,
+        category_info: Option<&AccountInfo>,
+        price_quote: Option<&AccountInfo>,
+        amount: u64,
+        now: i64,
+    ) -> Result<Self> {
+        let fee = global_config.fee_for(amount);
+        let total = amount.checked_add(fee).ok_or(WalletError::InvalidAmount)?;
+        let balance = from_token_account.amount;
+
+        // Trailing window applies on top of the calendar-day limit
+        let within_spend_window = match wallet.spend_window_seconds {
+            0 => true,
+            window_seconds => spend_window
+                .ok_or(WalletError::SpendWindowRequired)?
+                .check(total, now, window_seconds, wallet.effective_daily_limit(now, balance))
+                .is_ok(),
+        };
+
+        // Category budget applies on top of the wallet-wide limit
+        let category_limit = match category_info {
+            Some(info) => CategoryLimit::load(info)?,
+            None => None,
+        };
+        let within_category_limit = category_limit
+            .as_ref()
+            .is_none_or(|limit| limit.check(total, now).is_ok());
+
+        // Stable-value budget, priced through the pinned quote source
+        let (within_reference_budget, reference_value) = match wallet.reference_budget.as_ref() {
+            Some(budget) => {
+                let quote = price_quote.ok_or(WalletError::PriceQuoteRequired)?;
+                let value = budget.value_of(&global_config.price_program, quote, &from_token_account.mint, total, now)?;
+                (wallet.reference_spent_today(now).saturating_add(value) <= budget.daily_limit, value)
+            }
+            None => (true, 0),
+        };
+
+        Ok(Self {
+            fee,
+            total,
+            within_daily_limit: wallet.within_daily_limit(total, now, balance),
+            within_lifetime_limit: wallet.within_lifetime_limit(total),
+            within_spend_window,
+            within_category_limit,
+            within_reference_budget,
+            reference_value,
+            category_limit,
+        })
+    }
+
+    /// Apply each limit through enforce_limit, so observe-only mode relaxes
+    /// them the same way everywhere
+    pub fn enforce(&self, wallet: &SmartWallet, wallet_key: &Pubkey) -> Result<()> {
+        let observe_only = wallet.observe_only;
+        let total = self.total;
+        enforce_limit(wallet_key, observe_only, LimitRule::Daily, total, self.within_daily_limit)?;
+        enforce_limit(wallet_key, observe_only, LimitRule::Lifetime, total, self.within_lifetime_limit)?;
+        enforce_limit(wallet_key, observe_only, LimitRule::SpendWindow, total, self.within_spend_window)?;
+        enforce_limit(wallet_key, observe_only, LimitRule::Category, total, self.within_category_limit)?;
+        enforce_limit(wallet_key, observe_only, LimitRule::Reference, total, self.within_reference_budget)?;
+        Ok(())
+    }
+
+    /// Whether every limit passes, or would only be logged in observe-only mode
+    pub fn passes(&self, wallet: &SmartWallet) -> bool {
+        self.within_lifetime_limit
+            && (wallet.observe_only
+                || (self.within_daily_limit
+                    && self.within_spend_window
+                    && self.within_category_limit
+                    && self.within_reference_budget))
+    }
+}
+
+/// Accounts read and written by spend_from_wallet
+pub struct SpendAccounts<'a, 'info> {
+    pub wallet: &'a mut Account<'info, SmartWallet>,
+    pub global_config: &'a GlobalConfig,
+    pub from_token_account: &'a Account<'info, TokenAccount>,
+    pub to_token_account: AccountInfo<'info>,
+    pub fee_token_account: Option<&'a Account<'info, TokenAccount>>,
+    pub frozen_mint: AccountInfo<'info>,
+    pub spend_window: Option<&'a mut Account<'info, SpendWindow>>,
+    pub category_limit: Option<AccountInfo<'info>>, // None where categories aren't supported
+    pub price_quote: Option<AccountInfo<'info>>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Check, record and move a policy-checked token outflow of `amount` plus
+/// the platform fee: transfer gates, frozen mint, daily, lifetime, spend
+/// window, category and reference limits, then the anomaly breaker.
+///
+/// Authorization, review and idempotency-key checks stay with the caller.
+/// Returns the fee charged, or None when the anomaly breaker tripped instead
+/// of moving funds; the caller must then return Ok so the response is not
+/// rolled back.
+pub fn spend_from_wallet(
+    accounts: SpendAccounts,
+    amount: u64,
+    idempotency_key: Option<[u8; 16]>,
+    now: i64,
+) -> Result<Option<u64>> {
+    let SpendAccounts {
+        wallet,
+        global_config,
+        from_token_account,
+        to_token_account,
+        fee_token_account,
+        frozen_mint,
+        spend_window,
+        category_limit: category_info,
+        price_quote,
+        token_program,
+    } = accounts;
+
+    let checks = SpendChecks::evaluate(
+        wallet,
+        global_config,
+        from_token_account,
+        spend_window.as_deref().map(|window| &**window),
+        category_info.as_ref(),
+        price_quote.as_ref(),
+        amount,
+        now,
+    )?;
+    let mint = from_token_account.mint;
+
+    wallet.check_transfer_gates(checks.total, now)?;
+    require!(frozen_mint.data_is_empty(), WalletError::MintFrozen);
+    if checks.fee > 0 {
+        require!(fee_token_account.is_some(), WalletError::FeeAccountRequired);
+    }
+
+    // In observe-only mode a failing limit emits LimitWouldBlock instead
+    let wallet_key = wallet.key();
+    checks.enforce(wallet, &wallet_key)?;
+    let SpendChecks { fee, total, reference_value, mut category_limit, .. } = checks;
+
+    wallet.expire_limit_penalty(wallet_key, now);
+
+    // Circuit breaker: respond to an anomalous spend instead of moving
+    // funds. The caller returns Ok so the response is not rolled back.
+    if wallet.is_anomalous_spend(total, now) {
+        wallet.trip_anomaly_breaker(wallet_key, now);
+        return Ok(None);
+    }
+
+    // Reset daily limit if new day
+    wallet.reset_daily_spent(now);
+
+    // Checks-effects-interactions: commit the spend before any CPI and
+    // flush it to account data, so the notify program reads the updated
+    // wallet. Nothing can call back into us mid-spend: token_program is
+    // classic SPL (no transfer hooks) and the runtime rejects re-entry.
+    wallet.record_spend(total);
+    wallet.reference_spent = wallet.reference_spent.saturating_add(reference_value);
+    wallet.record_anomaly_window(total, now);
+    if let Some(key) = idempotency_key {
+        wallet.record_idempotency_key(key);
+    }
+    wallet.last_transfer_at = now;
+    wallet.transfer_nonce += 1;
+    wallet.nonce += 1;
+
+    if let (Some(limit), Some(info)) = (category_limit.as_mut(), category_info.as_ref()) {
+        limit.record(total, now);
+        limit.store(info)?;
+    }
+    let window_seconds = wallet.spend_window_seconds;
+    if window_seconds > 0 {
+        if let Some(window) = spend_window {
+            window.record(total, now, window_seconds);
+            window.exit(&crate::ID)?;
+        }
+    }
+    wallet.exit(&crate::ID)?;
+
+    // Perform transfer using PDA authority
+    let seeds = wallet.signer_seeds();
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: from_token_account.to_account_info(),
+        to: to_token_account,
+        authority: wallet.to_account_info(),
+    };
+    token::transfer(CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds), amount)?;
+
+    if fee > 0 {
+        let fee_account = fee_token_account.ok_or(WalletError::FeeAccountRequired)?;
+        let cpi_accounts = Transfer {
+            from: from_token_account.to_account_info(),
+            to: fee_account.to_account_info(),
+            authority: wallet.to_account_info(),
+        };
+        token::transfer(CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds), fee)?;
+
+        emit!(FeeCollected {
+            wallet: wallet_key,
+            mint,
+            fee_account: fee_account.key(),
+            amount,
+            fee,
+        });
+    }
+
+    Ok(Some(fee))
+}
+
+// ============ Account Structures ============
+
+#[account]
+#[derive(Default)]
+pub struct SmartWallet {
+    pub owner: Pubkey,              // Platform user identifier
+    pub owners: Vec<Pubkey>,        // Co-owners for shared wallets (max MAX_OWNERS)
+    pub owner_threshold: u8,        // Owner signatures required when shared
+    pub wallet_id: [u8; 32],        // Unique wallet ID
+    pub authority: Pubkey,          // MPC-derived signing authority
+    pub guardian_threshold: u8,     // Required guardian approvals
     pub guardian_count: u8,         // Total guardians
-    pub daily_limit: u64,           // Daily spending limit (lamports/tokens)
+    pub next_guardian_index: u8,    // Seed index for the next guardian PDA
+    pub daily_limit: u64,           // Daily spending limit (lamports/tokens); 0 = spending disabled
     pub daily_spent: u64,           // Amount spent today
     pub last_reset_day: i64,        // Unix day of last reset
-    pub recovery_delay: i64,        // Seconds to wait before recovery execution
-    pub pending_recovery: Option<PendingRecovery>,
-    pub nonce: u64,                 // Transaction nonce
-    pub is_frozen: bool,            // Emergency freeze flag
-    pub bump: u8,                   // PDA bump seed
+    pub created_at: i64,            // Wallet creation timestamp
+    pub recovery_delay: i64,        // Seconds to wait before recovery execution
+    pub recovery_pending: bool,     // A RecoverySession PDA is open
+    pub nonce: u64,                 // Transaction nonce
+    pub is_frozen: bool,            // Emergency freeze flag
+    pub freeze_reason: Option<FreezeReason>, // Why the wallet was frozen
+    pub require_diverse_guardians: bool, // Recovery needs approvals from 2+ guardian types
+    pub safe_address: Option<Pubkey>,   // Active emergency withdrawal destination
+    pub pending_safe_address: Option<Pubkey>, // Proposed safe address awaiting timelock
+    pub safe_address_effective_at: i64, // When pending_safe_address becomes active
+    pub allowed_start_second: u32,  // Transfer window start (UTC seconds of day)
+    pub allowed_end_second: u32,    // Transfer window end (exclusive)
+    pub min_transfer_interval: i64, // Minimum seconds between transfers (0 = off)
+    pub last_transfer_at: i64,      // Timestamp of the last successful transfer
+    pub next_transfer_request_id: u64, // Seed id for the next TransferRequest PDA
+    pub lock_from: i64,             // Vacation lock start (inclusive)
+    pub lock_until: i64,            // Vacation lock end (exclusive); equal bounds = off
+    pub daily_limit_bps: u16,       // Daily cap as basis points of balance (0 = off)
+    pub transfer_nonce: u64,        // Nonce space for OperationType::Transfer
+    pub exec_nonce: u64,            // Nonce space for OperationType::Execute
+    pub emit_events: bool,          // Emit events from transfer_spl/execute_transaction
+    pub next_deposit_id: u64,       // Seed id for the next PendingDeposit PDA
+    pub recovery_threshold: u8,     // Approval weight required for recovery
+    pub total_guardian_weight: u16, // Sum of weights of registered guardians
+    pub anomaly_threshold: u64,     // Spend that trips the auto-freeze (0 = off)
+    pub anomaly_window: i64,        // Seconds of cumulative spend considered
+    pub anomaly_window_start: i64,  // Start of the current anomaly window
+    pub anomaly_window_spent: u64,  // Spend within the current anomaly window
+    pub pending_limit_change: Option<PendingLimitChange>, // Queued limit increase
+    pub limit_ramp_per_day: u64,    // Daily-limit growth per day of wallet age (0 = off)
+    pub limit_ramp_target: u64,     // Ceiling for the ramped daily limit
+    pub panic_enabled: bool,        // Allow the guardian freeze-and-rotate shortcut
+    pub days_skipped: u64,          // Idle days skipped at the last daily rollover
+    pub compliance_hold: bool,      // Legal hold; only compliance_admin can release
+    pub compliance_admin: Option<Pubkey>,
+    pub mpc_mode: bool,             // execute_transaction takes one FROST aggregate signature
+    pub pending_item_count: u8,     // Open transfer requests + guardian invites
+    pub max_pending_items: u8,
+    pub is_initialized: bool,       // Set last by initialize_wallet
+    pub lifetime_limit: Option<u64>, // Total ever spendable; can only be lowered once set
+    pub lifetime_spent: u64,
+    pub limit_change_requires_guardians: bool, // Limit increases need guardian co-signers
+    pub notify_program: Option<Pubkey>, // Receives a callback after each transfer_spl
+    pub strict_hook: bool,          // Require the callback on every transfer
+    pub vesting: Option<VestingSchedule>, // Caps cumulative spend; immutable once set
+    pub migrated_to: Option<Pubkey>, // Successor wallet; this wallet can no longer spend
+    pub exec_daily_count: u32,      // execute_transaction calls today
+    pub max_exec_daily_count: u32,  // Daily cap on execute_transaction (0 = off)
+    pub exec_count_day: i64,        // Unix day exec_daily_count belongs to
+    pub exec_nonce_window: u8,      // Out-of-order execute nonces accepted (0/1 = strict, max 64)
+    pub exec_nonce_used: u64,       // Bit i set = exec_nonce + i already consumed
+    pub last_recovery_executed_at: i64, // When execute_recovery last rotated the authority
+    pub post_recovery_cooldown: i64, // Seconds after a recovery before another can start
+    pub spend_window_seconds: i64,  // Trailing-window limit via SpendWindow (0 = calendar day only)
+    pub guardians_added_today: u8,  // Guardians registered today
+    pub max_guardians_per_day: u8,  // Daily cap on guardian additions (0 = off)
+    pub guardian_add_day: i64,      // Unix day guardians_added_today belongs to
+    pub unfreeze_threshold: u8,     // Guardian co-signers required to unfreeze
+    pub required_types_mask: u8,    // GuardianType::mask() bits the active set must cover
+    pub enforce_guardian_types: bool, // execute_recovery requires required_types_mask coverage
+    pub observe_only: bool,         // transfer_spl reports limit violations instead of enforcing them
+    pub guardian_group_key: Option<Pubkey>, // FROST group key of the guardians, for aggregated recovery
+    pub guardian_group_threshold: u8, // Signers the group key needs; compared to recovery_threshold
+    pub recent_idempotency_keys: [[u8; 16]; IDEMPOTENCY_KEY_SLOTS], // Ring of processed transfer keys
+    pub idempotency_head: u8,       // Slot the next key is written to
+    pub max_exec_payload_len: u16,  // Byte cap on execute_transaction data and signatures
+    pub require_owner_inactivity: bool, // Owner activity after initiation cancels a recovery
+    pub last_owner_activity_at: i64, // Last heartbeat or other owner-signed action
+    pub pending_recovery_delay: Option<PendingDelayChange>, // Queued recovery_delay shortening
+    pub reference_budget: Option<ReferenceBudget>, // Daily cap valued in a reference token
+    pub reference_spent: u64,       // Reference value spent today (shares last_reset_day)
+    pub last_recovery_cancel_reason: Option<RecoveryCancelReason>, // Why the last recovery ended unexecuted
+    pub recovery_session_grace: i64, // Seconds past recovery_delay a session stays executable
+    pub weekday_limits: Option<[u64; 7]>, // Per-weekday caps on daily_limit, Monday = 0
+    pub review_window: i64,         // Guardian veto window for held transfers (0 = review off)
+    pub review_threshold: u64,      // Transfers at or above this must be held for review
+    pub next_held_transfer_id: u64, // Seed id for the next HeldTransfer PDA
+    pub limit_penalty_bps: u16,     // Share of the daily limit left while penalized
+    pub limit_penalty_duration: i64, // Penalty length; 0 = anomalies freeze instead
+    pub limit_penalty_until: i64,   // Penalty end (0 = none running)
+    pub max_approvals_per_type: u8, // Guardians of one type counted toward a quorum (0 = uncapped)
+    pub authority_is_group: bool,   // `authority` is an MPC threshold group key
+    pub limit_decrease_floor: u64,  // Decreases below this need guardian approval (0 = off)
+    pub limit_drop_alert_bps: u16,  // Decreases of at least this share are flagged (0 = off)
+    pub freeze_threshold: u8,       // Approvals needed to freeze (1 = single-signer freeze_wallet)
+    pub pending_freeze: Option<PendingFreeze>, // Freeze approvals collected so far
+    pub reconcile_account: Pubkey,  // Token account reconcile_spend is pinned to
+    pub last_reconciled_balance: u64, // Token balance at the last reconcile_spend
+    pub last_reconciled_spent: u64, // lifetime_spent at the last reconcile_spend
+    pub reconcile_corrects: bool,   // reconcile_spend charges discrepancies instead of only reporting
+    pub pending_reference_budget: Option<PendingReferenceBudget>, // Queued reference budget loosening
+    pub bump: u8,                   // PDA bump seed
+}
+
+impl SmartWallet {
+    /// Seeds for signing CPIs as the wallet PDA
+    pub fn signer_seeds(&self) -> [&[u8]; 3] {
+        [b"wallet", self.wallet_id.as_ref(), std::slice::from_ref(&self.bump)]
+    }
+
+    /// Amount spent in the current day, applying the lazy rollover in memory
+    pub fn spent_today(&self, now: i64) -> u64 {
+        if now / 86400 > self.last_reset_day {
+            0
+        } else {
+            self.daily_spent
+        }
+    }
+
+    /// Spending room left today; zero while frozen or under compliance hold
+    pub fn remaining_today(&self, now: i64) -> u64 {
+        if self.is_frozen || self.compliance_hold {
+            return 0;
+        }
+        self.daily_limit.saturating_sub(self.spent_today(now))
+    }
+
+    /// Fail unless the wallet is initialized, not migrated, unfrozen and not
+    /// under compliance hold
+    pub fn require_spendable(&self) -> Result<()> {
+        require!(self.is_initialized, WalletError::WalletNotInitialized);
+        require!(self.migrated_to.is_none(), WalletError::WalletMigrated);
+        require!(!self.is_frozen, WalletError::WalletFrozen);
+        require!(!self.compliance_hold, WalletError::ComplianceHold);
+        Ok(())
+    }
+
+    /// Validate a transfer of `amount` at `now` against every spending policy
+    pub fn check_transfer(&self, amount: u64, now: i64, balance: u64) -> Result<()> {
+        self.check_transfer_gates(amount, now)?;
+        require!(
+            self.within_daily_limit(amount, now, balance),
+            WalletError::DailyLimitExceeded
+        );
+        require!(self.within_lifetime_limit(amount), WalletError::LifetimeLimitExceeded);
+
+        Ok(())
+    }
+
+    /// check_transfer without the daily limit, for guardian co-signed sweeps
+    /// which are exempt from it
+    pub fn check_transfer_except_daily(&self, amount: u64, now: i64) -> Result<()> {
+        self.check_transfer_gates(amount, now)?;
+        require!(self.within_lifetime_limit(amount), WalletError::LifetimeLimitExceeded);
+
+        Ok(())
+    }
+
+    /// Whether `amount` must go through hold_transfer instead of transfer_spl
+    pub fn requires_review(&self, amount: u64) -> bool {
+        self.review_window > 0 && amount >= self.review_threshold
+    }
+
+    /// Every check_transfer condition except the spending limits, which
+    /// `observe_only` may relax
+    ///
+    /// Shared by transfer_spl and simulate_transfer so both report the same error.
+    pub fn check_transfer_gates(&self, amount: u64, now: i64) -> Result<()> {
+        self.require_spendable()?;
+        require!(!self.spending_disabled(), WalletError::SpendingDisabled);
+        require!(!self.is_vacation_locked(now), WalletError::VacationLock);
+        require!(self.within_transfer_window(now), WalletError::OutsideTransferWindow);
+        require!(self.transfer_cooldown_elapsed(now), WalletError::TransferTooSoon);
+        require!(self.within_vested_amount(amount, now), WalletError::AmountStillLocked);
+
+        Ok(())
+    }
+
+    /// Clear the pending recovery without executing it. Every such path goes
+    /// through here so RecoveryCancelled is emitted exactly once.
+    pub fn cancel_recovery(&mut self, wallet_key: Pubkey, reason: RecoveryCancelReason, now: i64) {
+        self.recovery_pending = false;
+        self.last_recovery_cancel_reason = Some(reason);
+
+        emit!(RecoveryCancelled {
+            wallet: wallet_key,
+            reason,
+            cancelled_at: now,
+        });
+    }
+
+    /// Reference-token value spent in the current day, applying the lazy rollover
+    pub fn reference_spent_today(&self, now: i64) -> u64 {
+        if now / 86400 > self.last_reset_day {
+            0
+        } else {
+            self.reference_spent
+        }
+    }
+
+    /// A daily limit of 0 with no ramp deliberately disables spending; such
+    /// transfers fail with SpendingDisabled rather than DailyLimitExceeded
+    pub fn spending_disabled(&self) -> bool {
+        self.daily_limit == 0 && self.limit_ramp_per_day == 0
+    }
+
+    /// Whether `amount` fits in today's effective limit
+    pub fn within_daily_limit(&self, amount: u64, now: i64, balance: u64) -> bool {
+        self.spent_today(now).saturating_add(amount) <= self.effective_daily_limit(now, balance)
+    }
+
+    /// Whether `amount` keeps cumulative spend within `lifetime_limit`
+    pub fn within_lifetime_limit(&self, amount: u64) -> bool {
+        match self.lifetime_limit {
+            Some(limit) => self.lifetime_spent.saturating_add(amount) <= limit,
+            None => true,
+        }
+    }
+
+    /// Whether `amount` keeps cumulative spend within the vested amount
+    pub fn within_vested_amount(&self, amount: u64, now: i64) -> bool {
+        match self.vesting {
+            Some(vesting) => self.lifetime_spent.saturating_add(amount) <= vesting.unlocked(now),
+            None => true,
+        }
+    }
+
+    /// Count a completed spend toward the daily and lifetime totals
+    pub fn record_spend(&mut self, amount: u64) {
+        self.daily_spent += amount;
+        self.lifetime_spent = self.lifetime_spent.saturating_add(amount);
+    }
+
+    /// Lazily zero `daily_spent` when the clock has moved into a new day,
+    /// recording how many whole days passed with no rollover at all
+    pub fn reset_daily_spent(&mut self, now: i64) {
+        let current_day = now / 86400;
+        if current_day > self.last_reset_day {
+            self.days_skipped = current_day.saturating_sub(self.last_reset_day).saturating_sub(1) as u64;
+            self.daily_spent = 0;
+            self.reference_spent = 0;
+            self.last_reset_day = current_day;
+        }
+    }
+
+    /// Whether `now` falls inside the configured transfer window
+    pub fn within_transfer_window(&self, now: i64) -> bool {
+        let start = self.allowed_start_second as i64;
+        let end = self.allowed_end_second as i64;
+        if start == 0 && end == 0 {
+            return true;
+        }
+
+        let second_of_day = now.rem_euclid(86400);
+        if start <= end {
+            second_of_day >= start && second_of_day < end
+        } else {
+            // Window wraps past midnight, e.g. 22:00-06:00
+            second_of_day >= start || second_of_day < end
+        }
+    }
+
+    /// Whether enough time has passed since the last transfer
+    pub fn transfer_cooldown_elapsed(&self, now: i64) -> bool {
+        self.min_transfer_interval == 0
+            || now - self.last_transfer_at >= self.min_transfer_interval
+    }
+
+    /// Activate a pending safe address once its timelock has elapsed
+    pub fn promote_safe_address(&mut self, now: i64) {
+        if let Some(pending) = self.pending_safe_address {
+            if now >= self.safe_address_effective_at {
+                self.safe_address = Some(pending);
+                self.pending_safe_address = None;
+            }
+        }
+    }
+
+    /// Whether a scheduled vacation lock covers `now`; clears itself once passed
+    pub fn is_vacation_locked(&self, now: i64) -> bool {
+        now >= self.lock_from && now < self.lock_until
+    }
+
+    /// Daily cap after applying the limit ramp, weekday caps, any running
+    /// limit penalty and percentage-of-balance limit
+    ///
+    /// When several caps are configured the lowest one wins.
+    pub fn effective_daily_limit(&self, now: i64, balance: u64) -> u64 {
+        let mut limit = self.ramped_daily_limit(now);
+        if let Some(weekday_limits) = self.weekday_limits {
+            limit = limit.min(weekday_limits[day_of_week(now)]);
+        }
+        if self.limit_penalty_active(now) {
+            limit = (limit as u128 * self.limit_penalty_bps as u128 / 10_000) as u64;
+        }
+        if self.daily_limit_bps == 0 {
+            return limit;
+        }
+        let pct_cap = (balance as u128 * self.daily_limit_bps as u128 / 10_000) as u64;
+        limit.min(pct_cap)
+    }
+
+    /// `min(daily_limit + per_day * days_since_created, target)` when a ramp is set
+    pub fn ramped_daily_limit(&self, now: i64) -> u64 {
+        if self.limit_ramp_per_day == 0 || self.limit_ramp_target <= self.daily_limit {
+            return self.daily_limit;
+        }
+        let days = (now.saturating_sub(self.created_at) / 86400).max(0) as u64;
+        self.daily_limit
+            .saturating_add(self.limit_ramp_per_day.saturating_mul(days))
+            .min(self.limit_ramp_target)
+    }
+
+    /// Check and record `amount` against the trailing spend window when one is
+    /// configured, for outflows that don't go through spend_from_wallet
+    pub fn charge_spend_window(
+        &self,
+        spend_window: Option<&mut SpendWindow>,
+        amount: u64,
+        now: i64,
+        balance: u64,
+    ) -> Result<()> {
+        let window_seconds = self.spend_window_seconds;
+        if window_seconds == 0 {
+            return Ok(());
+        }
+        let window = spend_window.ok_or(WalletError::SpendWindowRequired)?;
+        window.check(amount, now, window_seconds, self.effective_daily_limit(now, balance))?;
+        window.record(amount, now, window_seconds);
+        Ok(())
+    }
+
+    /// Guardian-threshold co-signers for a cap that could push the effective
+    /// limit under `limit_decrease_floor`; as in update_daily_limit, a wallet
+    /// without guardians can't approve one
+    pub fn require_floor_approval(&self, wallet_key: &Pubkey, accounts: &[AccountInfo], now: i64) -> Result<()> {
+        let approvals = count_guardian_signers(wallet_key, accounts, now)?;
+        require!(
+            self.guardian_threshold > 0 && approvals >= self.guardian_threshold,
+            WalletError::InsufficientApprovals
+        );
+        Ok(())
+    }
+
+    /// Authorize a spend: the authority for single-owner wallets, or
+    /// `owner_threshold` distinct owner signatures for shared wallets.
+    ///
+    /// Owner signers beyond `authority` are passed in remaining_accounts.
+    pub fn authorize_spend(&self, authority: &AccountInfo, accounts: &[AccountInfo]) -> Result<()> {
+        if self.owners.len() <= 1 {
+            require!(
+                authority.is_signer && authority.key() == self.authority,
+                WalletError::Unauthorized
+            );
+            return Ok(());
+        }
+
+        let authority_signed = authority.is_signer && self.owners.contains(authority.key);
+        let signers = count_owner_signers(&self.owners, accounts.iter().filter(|info| info.key != authority.key))
+            + authority_signed as u8;
+        require!(
+            signers >= self.owner_threshold,
+            WalletError::InsufficientOwnerSignatures
+        );
+
+        Ok(())
+    }
+
+    /// Require `owner_threshold` distinct owner signers among `accounts`
+    pub fn require_owner_quorum(&self, accounts: &[AccountInfo]) -> Result<()> {
+        require!(
+            count_owner_signers(&self.owners, accounts.iter()) >= self.owner_threshold,
+            WalletError::InsufficientOwnerSignatures
+        );
+        Ok(())
+    }
+
+    /// Spend already recorded in the anomaly window that contains `now`
+    pub fn anomaly_spent(&self, now: i64) -> u64 {
+        if now - self.anomaly_window_start >= self.anomaly_window {
+            0
+        } else {
+            self.anomaly_window_spent
+        }
+    }
+
+    /// Whether `amount` alone, or with recent spend, exceeds the anomaly threshold
+    pub fn is_anomalous_spend(&self, amount: u64, now: i64) -> bool {
+        self.anomaly_threshold != 0
+            && self.anomaly_spent(now).saturating_add(amount) > self.anomaly_threshold
+    }
+
+    /// FEATURE_* bits for the opt-in features currently enabled. Derived from
+    /// the settings that gate each feature, so it can never drift from what
+    /// the instructions actually enforce.
+    pub fn feature_flags(&self) -> u64 {
+        [
+            (self.owners.len() > 1, FEATURE_MULTI_OWNER),
+            (self.safe_address.is_some(), FEATURE_SAFE_ADDRESS),
+            (self.panic_enabled, FEATURE_PANIC),
+            (self.require_diverse_guardians, FEATURE_GUARDIAN_DIVERSITY),
+            (self.enforce_guardian_types, FEATURE_GUARDIAN_TYPE_COVERAGE),
+            (self.guardian_group_key.is_some(), FEATURE_GUARDIAN_GROUP_KEY),
+            (self.require_owner_inactivity, FEATURE_OWNER_INACTIVITY),
+            (self.lifetime_limit.is_some(), FEATURE_LIFETIME_LIMIT),
+            (self.vesting.is_some(), FEATURE_VESTING),
+            (self.spend_window_seconds > 0, FEATURE_SPEND_WINDOW),
+            (self.anomaly_threshold != 0, FEATURE_ANOMALY_FREEZE),
+            (self.limit_penalty_duration > 0, FEATURE_LIMIT_PENALTY),
+            (self.weekday_limits.is_some(), FEATURE_WEEKDAY_LIMITS),
+            (self.reference_budget.is_some(), FEATURE_REFERENCE_BUDGET),
+            (self.review_window > 0, FEATURE_TRANSFER_REVIEW),
+            (self.observe_only, FEATURE_OBSERVE_ONLY),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(0, |flags, (_, bit)| flags | bit)
+    }
+
+    /// Whether a limit penalty covers `now`
+    pub fn limit_penalty_active(&self, now: i64) -> bool {
+        now < self.limit_penalty_until
+    }
+
+    /// Respond to an anomalous spend: start the limit penalty if one is
+    /// configured and not already running, otherwise freeze the wallet
+    pub fn trip_anomaly_breaker(&mut self, wallet_key: Pubkey, now: i64) {
+        if self.limit_penalty_duration > 0 && !self.limit_penalty_active(now) {
+            self.apply_limit_penalty(wallet_key, crate::ID, now);
+            return;
+        }
+
+        self.is_frozen = true;
+        self.freeze_reason = Some(FreezeReason::SuspectedCompromise);
+
+        emit!(WalletFrozen {
+            wallet: wallet_key,
+            frozen_by: crate::ID,
+            reason: FreezeReason::SuspectedCompromise,
+        });
+    }
+
+    /// Start the configured limit penalty from `now`
+    pub fn apply_limit_penalty(&mut self, wallet_key: Pubkey, applied_by: Pubkey, now: i64) {
+        self.limit_penalty_until = now.saturating_add(self.limit_penalty_duration);
+
+        emit!(LimitPenaltyApplied {
+            wallet: wallet_key,
+            applied_by,
+            penalty_bps: self.limit_penalty_bps,
+            until: self.limit_penalty_until,
+        });
+    }
+
+    /// Clear a penalty that has run out, reporting it once
+    pub fn expire_limit_penalty(&mut self, wallet_key: Pubkey, now: i64) {
+        if self.limit_penalty_until != 0 && !self.limit_penalty_active(now) {
+            emit!(LimitPenaltyExpired {
+                wallet: wallet_key,
+                expired_at: self.limit_penalty_until,
+            });
+            self.limit_penalty_until = 0;
+        }
+    }
+
+    /// Add a completed transfer to the anomaly window, starting a new one if expired
+    pub fn record_anomaly_window(&mut self, amount: u64, now: i64) {
+        if now - self.anomaly_window_start >= self.anomaly_window {
+            self.anomaly_window_start = now;
+            self.anomaly_window_spent = 0;
+        }
+        self.anomaly_window_spent += amount;
+    }
+
+    /// Account for a newly activated guardian and advance the PDA seed index,
+    /// enforcing `max_guardians_per_day`
+    pub fn register_guardian(&mut self, weight: u8, now: i64) -> Result<()> {
+        require!(self.is_initialized, WalletError::WalletNotInitialized);
+        require!(
+            (self.guardian_count as usize) < MAX_GUARDIANS,
+            WalletError::TooManyGuardians
+        );
+
+        let current_day = now / 86400;
+        if current_day > self.guardian_add_day {
+            self.guardians_added_today = 0;
+            self.guardian_add_day = current_day;
+        }
+        if self.max_guardians_per_day != 0 {
+            require!(
+                self.guardians_added_today < self.max_guardians_per_day,
+                WalletError::GuardianRateLimited
+            );
+        }
+        self.guardians_added_today += 1;
+
+        self.guardian_count += 1;
+        self.total_guardian_weight += weight as u16;
+        self.next_guardian_index = self
+            .next_guardian_index
+            .checked_add(1)
+            .ok_or(WalletError::TooManyGuardians)?;
+
+        Ok(())
+    }
+
+    /// Account for a removed guardian; errors instead of wrapping on an
+    /// inconsistent count
+    pub fn unregister_guardian(&mut self, weight: u8) -> Result<()> {
+        self.guardian_count = self
+            .guardian_count
+            .checked_sub(1)
+            .ok_or(WalletError::NoGuardiansToRemove)?;
+        self.total_guardian_weight = self
+            .total_guardian_weight
+            .checked_sub(weight as u16)
+            .ok_or(WalletError::NoGuardiansToRemove)?;
+
+        Ok(())
+    }
+
+    /// Fail if `key` is reserved or among the recently processed transfer keys
+    pub fn check_idempotency_key(&self, key: &[u8; 16]) -> Result<()> {
+        require!(*key != [0; 16], WalletError::InvalidIdempotencyKey);
+        require!(
+            !self.recent_idempotency_keys.contains(key),
+            WalletError::DuplicateRequest
+        );
+        Ok(())
+    }
+
+    /// Remember a processed transfer key, overwriting the oldest
+    pub fn record_idempotency_key(&mut self, key: [u8; 16]) {
+        self.recent_idempotency_keys[self.idempotency_head as usize] = key;
+        self.idempotency_head = ((self.idempotency_head as usize + 1) % IDEMPOTENCY_KEY_SLOTS) as u8;
+    }
+
+    /// Account for a newly created pending PDA, enforcing `max_pending_items`
+    pub fn open_pending_item(&mut self) -> Result<()> {
+        require!(
+            self.pending_item_count < self.max_pending_items,
+            WalletError::TooManyPendingItems
+        );
+        self.pending_item_count += 1;
+        Ok(())
+    }
+
+    /// Account for a pending PDA that was executed, cancelled or declined
+    pub fn close_pending_item(&mut self) {
+        self.pending_item_count = self.pending_item_count.saturating_sub(1);
+    }
+
+    /// Count an execute_transaction call, enforcing `max_exec_daily_count`
+    pub fn record_execution(&mut self, now: i64) -> Result<()> {
+        let current_day = now / 86400;
+        if current_day > self.exec_count_day {
+            self.exec_daily_count = 0;
+            self.exec_count_day = current_day;
+        }
+        if self.max_exec_daily_count != 0 {
+            require!(
+                self.exec_daily_count < self.max_exec_daily_count,
+                WalletError::ExecRateLimited
+            );
+        }
+        self.exec_daily_count += 1;
+        Ok(())
+    }
+
+    /// Consume an execute nonce.
+    ///
+    /// Strict mode (window 0 or 1) accepts only `exec_nonce` itself, so nonces
+    /// are consumed gaplessly in order. With a window of `w`, any unused nonce
+    /// in `exec_nonce..exec_nonce + w` is accepted, letting concurrent relayers
+    /// land out of order; `exec_nonce` then advances past every consumed slot.
+    /// Nonces below the window, reused, or beyond it fail with
+    /// `ExpiredOrReusedNonce`.
+    pub fn consume_exec_nonce(&mut self, nonce: u64) -> Result<()> {
+        let window = self.exec_nonce_window.max(1) as u64;
+        let offset = nonce
+            .checked_sub(self.exec_nonce)
+            .filter(|offset| *offset < window)
+            .ok_or(WalletError::ExpiredOrReusedNonce)?;
+        require!(
+            self.exec_nonce_used & (1 << offset) == 0,
+            WalletError::ExpiredOrReusedNonce
+        );
+
+        self.exec_nonce_used |= 1 << offset;
+        while self.exec_nonce_used & 1 == 1 {
+            self.exec_nonce_used >>= 1;
+            self.exec_nonce += 1;
+        }
+        Ok(())
+    }
+}
+
+/// In-flight social recovery, keyed on `[b"recovery", wallet]`
+#[account]
+pub struct RecoverySession {
+    pub wallet: Pubkey,
+    pub new_authority: Pubkey,
+    pub new_authority_is_group: bool, // new_authority is an MPC threshold group key
+    pub new_owner: Option<Pubkey>,  // Also rotate `owner` when set
+    pub challenge: [u8; 32],        // Each approval must sign this with the guardian key
+    pub initiated_at: i64,
+    pub expires_at: i64,            // initiated_at + recovery_delay + grace; restart after this
+    pub approvals: u8,
+    pub approvers: [Pubkey; MAX_GUARDIANS], // Guardian pubkeys that approved, first `approvals` used
+    pub approved_weight: u16,       // Sum of approving guardians' weights
+    pub approved_types: u8,         // Bitmask of GuardianType::mask() values that approved
+    pub type_approvals: [u8; GuardianType::COUNT], // Distinct approving guardians per GuardianType, counted or not
+    pub payer: Pubkey,              // Receives rent back on close
+    pub bump: u8,
+}
+
+/// Cliff + linear unlock of the wallet's cumulative spend
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct VestingSchedule {
+    pub start: i64,
+    pub cliff: i64,                 // Seconds after start before anything unlocks
+    pub duration: i64,              // Seconds after start until fully unlocked
+    pub total: u64,
+}
+
+impl VestingSchedule {
+    /// Amount unlocked at `now`, rounded down
+    pub fn unlocked(&self, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.start);
+        if elapsed < self.cliff {
+            return 0;
+        }
+        if elapsed >= self.duration {
+            return self.total;
+        }
+        (self.total as u128 * elapsed as u128 / self.duration as u128) as u64
+    }
+}
+
+/// Freeze approvals gathered by approve_freeze
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PendingFreeze {
+    pub approvers: [Pubkey; MAX_FREEZE_APPROVALS],
+    pub approvals: u8,
+    pub started_at: i64,            // Round resets FREEZE_APPROVAL_WINDOW after this
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PendingLimitChange {
+    pub new_limit: u64,             // New daily_limit, or the ramp's target when `ramp` is set
+    pub effective_at: i64,
+    pub ramp: Option<LimitRamp>,    // Installs this ramp instead of changing daily_limit
+}
+
+/// Age-based daily-limit ramp queued by set_limit_ramp
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct LimitRamp {
+    pub per_day: u64,
+    pub target: u64,
+}
+
+/// Daily spending cap valued in `reference_mint` base units
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ReferenceBudget {
+    pub reference_mint: Pubkey,
+    pub daily_limit: u64,
+    pub max_price_age: i64,         // Seconds before a quote is considered stale
+}
+
+impl ReferenceBudget {
+    /// Whether `new` (None = no budget) could allow more spend than `self`
+    pub fn loosened_by(&self, new: Option<&ReferenceBudget>) -> bool {
+        match new {
+            Some(new) => {
+                new.reference_mint != self.reference_mint
+                    || new.daily_limit > self.daily_limit
+                    || new.max_price_age > self.max_price_age
+            }
+            None => true,
+        }
+    }
+
+    /// Value of `amount` base units of `mint` in reference base units, rounded
+    /// up so imprecision only ever tightens the budget. `price_program` is
+    /// GlobalConfig's pinned price source; quotes price whole tokens, so mints
+    /// of any decimals compare on the same scale.
+    pub fn value_of(
+        &self,
+        price_program: &Pubkey,
+        quote_info: &AccountInfo,
+        mint: &Pubkey,
+        amount: u64,
+        now: i64,
+    ) -> Result<u64> {
+        require!(
+            *price_program != Pubkey::default() && quote_info.owner == price_program,
+            WalletError::InvalidPriceQuote
+        );
+        let quote = PriceQuote::deserialize(&mut &quote_info.data.borrow()[..])?;
+        require!(
+            quote.mint == *mint && quote.reference_mint == self.reference_mint,
+            WalletError::InvalidPriceQuote
+        );
+        require!(quote.price > 0 && quote.published_at <= now, WalletError::InvalidPriceQuote);
+        require!(
+            now - quote.published_at <= self.max_price_age,
+            WalletError::StalePriceQuote
+        );
+
+        let unit = 10u128
+            .checked_pow(quote.decimals as u32)
+            .and_then(|unit| unit.checked_mul(PRICE_SCALE as u128))
+            .ok_or(WalletError::InvalidPriceQuote)?;
+        let value = (amount as u128 * quote.price as u128).div_ceil(unit);
+        Ok(u64::try_from(value).unwrap_or(u64::MAX))
+    }
+}
+
+/// Price source ABI: GlobalConfig's `price_program` owns accounts whose data
+/// starts with this struct, Borsh-encoded with no discriminator
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PriceQuote {
+    pub mint: Pubkey,
+    pub reference_mint: Pubkey,
+    pub decimals: u8,               // Decimals of `mint`
+    pub price: u64,                 // Reference base units per whole token, scaled by PRICE_SCALE
+    pub published_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PendingDelayChange {
+    pub new_delay: i64,
+    pub effective_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PendingReferenceBudget {
+    pub budget: Option<ReferenceBudget>,
+    pub effective_at: i64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct Guardian {
+    pub wallet: Pubkey,
+    pub pubkey: Pubkey,
+    pub guardian_type: GuardianType,
+    pub weight: u8,                 // Approval weight counted toward recovery
+    pub added_at: i64,
+    pub expires_at: i64,            // Guardian lapses at this time (0 = never)
+    pub last_action_at: i64,        // Timestamp of this guardian's last action
+    pub action_count: u32,          // Number of actions taken by this guardian
+    pub identity_commitment: Option<[u8; 32]>, // hash(salt || identifier) for Email/Phone guardians
+    pub is_active: bool,
+    pub pending_reactivation: bool, // Authority asked to reactivate; awaiting the guardian's consent
+    pub can_approve_recovery: bool, // False for alert-only guardians that don't count toward quorum
+    pub bump: u8,
+}
+
+impl Guardian {
+    /// Whether the guardian has passed its expiry
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    /// Active and not expired; expired guardians are treated as inactive
+    pub fn is_usable(&self, now: i64) -> bool {
+        self.is_active && !self.is_expired(now)
+    }
+
+    /// Populate a freshly created guardian PDA
+    #[allow(clippy::too_many_arguments)]
+    pub fn activate(
+        &mut self,
+        wallet: Pubkey,
+        pubkey: Pubkey,
+        guardian_type: GuardianType,
+        weight: u8,
+        expires_at: i64,
+        now: i64,
+        bump: u8,
+    ) {
+        self.wallet = wallet;
+        self.pubkey = pubkey;
+        self.guardian_type = guardian_type;
+        self.weight = weight;
+        self.added_at = now;
+        self.expires_at = expires_at;
+        self.last_action_at = 0;
+        self.action_count = 0;
+        self.identity_commitment = None;
+        self.is_active = true;
+        self.pending_reactivation = false;
+        self.can_approve_recovery = true;
+        self.bump = bump;
+    }
+
+    /// Record that this guardian acted at `now`
+    pub fn record_action(&mut self, now: i64) {
+        self.last_action_at = now;
+        self.action_count = self.action_count.saturating_add(1);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuardianType {
+    Email,
+    Phone,
+    #[default]
+    Wallet,
+    Hardware,
+    Institution,
+}
+
+impl GuardianType {
+    /// Union of every type's mask
+    pub const ALL_MASK: u8 = 0b1_1111;
+
+    /// Number of guardian types
+    pub const COUNT: usize = 5;
+
+    /// Single-bit mask used to track which guardian types have acted
+    pub fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FreezeReason {
+    Manual,
+    SuspectedCompromise,
+    Compliance,
+    UserRequest,
+}
+
+#[account]
+pub struct TransferRequest {
+    pub wallet: Pubkey,
+    pub id: u64,
+    pub destination: Pubkey,        // Destination token account
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub approvals: u8,
+    pub approvers: [Pubkey; MAX_GUARDIANS], // Guardian pubkeys that approved
+    pub payer: Pubkey,              // Receives rent back on close
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+/// Transfer held for guardian review; any active guardian can veto it until
+/// `releasable_at`
+#[account]
+pub struct HeldTransfer {
+    pub wallet: Pubkey,
+    pub id: u64,
+    pub destination: Pubkey,        // Destination token account
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub held_at: i64,
+    pub releasable_at: i64,
+    pub payer: Pubkey,              // Receives rent back on close
+    pub bump: u8,
+}
+
+#[account]
+#[derive(Default)]
+pub struct CategoryLimit {
+    pub wallet: Pubkey,
+    pub category: u8,
+    pub daily_limit: u64,           // Category budget per day
+    pub daily_spent: u64,           // Category spend today
+    pub last_reset_day: i64,        // Unix day of last reset
+    pub bump: u8,
+}
+
+impl CategoryLimit {
+    /// Load the category PDA if it has been created; unset categories have no budget
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(&mut &info.data.borrow()[..])?))
+    }
+
+    /// Write the updated counters back to the PDA
+    pub fn store(&self, info: &AccountInfo) -> Result<()> {
+        self.try_serialize(&mut &mut info.data.borrow_mut()[..])
+    }
+
+    /// Validate `amount` against the category budget at `now`
+    pub fn check(&self, amount: u64, now: i64) -> Result<()> {
+        let spent = if now / 86400 > self.last_reset_day { 0 } else { self.daily_spent };
+        require!(
+            spent + amount <= self.daily_limit,
+            WalletError::CategoryLimitExceeded
+        );
+        Ok(())
+    }
+
+    /// Apply the day rollover and add `amount` to today's category spend
+    pub fn record(&mut self, amount: u64, now: i64) {
+        let current_day = now / 86400;
+        if current_day > self.last_reset_day {
+            self.daily_spent = 0;
+            self.last_reset_day = current_day;
+        }
+        self.daily_spent += amount;
+    }
+}
+
+#[account]
+pub struct FrozenMint {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct SpendEntry {
+    pub at: i64,
+    pub amount: u64,
+}
+
+/// Recent spends for the opt-in trailing-window limit
+#[account]
+#[derive(Default)]
+pub struct SpendWindow {
+    pub wallet: Pubkey,
+    pub entries: [SpendEntry; SPEND_WINDOW_SLOTS],
+    pub bump: u8,
+}
+
+impl SpendWindow {
+    /// Sum of spends within the trailing `window` seconds before `now`
+    pub fn spent(&self, now: i64, window: i64) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.amount > 0 && entry.at > now - window)
+            .fold(0u64, |sum, entry| sum.saturating_add(entry.amount))
+    }
+
+    /// Validate `amount` against `limit` over the trailing window
+    pub fn check(&self, amount: u64, now: i64, window: i64, limit: u64) -> Result<()> {
+        require!(
+            self.spent(now, window).saturating_add(amount) <= limit,
+            WalletError::DailyLimitExceeded
+        );
+        Ok(())
+    }
+
+    /// Record a spend, reusing an expired slot. When every slot is still in
+    /// the window the spend is merged into the newest slot and restamped,
+    /// which can only overcount.
+    pub fn record(&mut self, amount: u64, now: i64, window: i64) {
+        let slot = match self.entries.iter().position(|entry| entry.amount == 0 || entry.at <= now - window) {
+            Some(index) => &mut self.entries[index],
+            None => self.entries.iter_mut().max_by_key(|entry| entry.at).unwrap(),
+        };
+        slot.amount = if slot.at > now - window { slot.amount.saturating_add(amount) } else { amount };
+        slot.at = now;
+    }
+}
+
+#[account]
+pub struct SoulboundMint {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+/// Program-wide operational switches, keyed on `[b"config"]`
+#[account]
+pub struct GlobalConfig {
+    pub admin: Pubkey,
+    pub paused: bool,               // Kill switch for fund-moving and guardian instructions
+    pub fee_bps: u16,               // Platform fee on transfer_spl (0 = off)
+    pub fee_destination: Pubkey,    // Owner of the token accounts that receive fees
+    pub price_program: Pubkey,      // Owner of the PriceQuote accounts trusted for reference budgets
+    pub bump: u8,
+}
+
+impl GlobalConfig {
+    /// Platform fee on a transfer of `amount`, rounded down
+    pub fn fee_for(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128 / 10_000) as u64
+    }
+}
+
+/// Wallet ids owned by `owner`, keyed on `[b"owner_index", owner]`
+#[account]
+pub struct OwnerIndex {
+    pub owner: Pubkey,
+    pub len: u8,                    // Number of valid entries in wallet_ids
+    pub wallet_ids: [[u8; 32]; OWNER_INDEX_CAPACITY],
+    pub unindexed_count: u32,       // Wallets that didn't fit once the index was full
+    pub bump: u8,
+}
+
+impl OwnerIndex {
+    /// Whether `wallet_id` is listed
+    pub fn contains(&self, wallet_id: &[u8; 32]) -> bool {
+        self.wallet_ids[..self.len as usize].contains(wallet_id)
+    }
+
+    /// Remove `wallet_id`, moving the last entry into its slot
+    pub fn remove(&mut self, wallet_id: &[u8; 32]) -> bool {
+        let len = self.len as usize;
+        match self.wallet_ids[..len].iter().position(|id| id == wallet_id) {
+            Some(position) => {
+                self.wallet_ids[position] = self.wallet_ids[len - 1];
+                self.wallet_ids[len - 1] = [0; 32];
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[account]
+pub struct Blocklist {
+    pub admin: Pubkey,              // Platform key allowed to edit the blocklist
+    pub blocked_count: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct BlockedKey {
+    pub pubkey: Pubkey,
+    pub blocked_at: i64,
+    pub bump: u8,
+}
+
+/// Operation tag mixed into signed messages for domain separation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OperationType {
+    Transfer,
+    Execute,
+    Unfreeze,
+}
+
+/// Signatures accepted by execute_transaction, selected by `mpc_mode`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum ExecSignatures {
+    Aggregated([u8; 64]),
+    Individual(Vec<[u8; 64]>),
+}
+
+#[account]
+pub struct PendingDeposit {
+    pub wallet: Pubkey,
+    pub id: u64,
+    pub sender: Pubkey,             // Signer that funded the deposit
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub deposited_at: i64,
+    pub bump: u8,
+}
+
+/// Spending limits that observation mode can relax
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LimitRule {
+    Daily,
+    Lifetime,
+    SpendWindow,
+    Category,
+    Reference,
+}
+
+impl LimitRule {
+    /// Error reported when the rule is enforced
+    pub fn error(self) -> WalletError {
+        match self {
+            LimitRule::Daily | LimitRule::SpendWindow => WalletError::DailyLimitExceeded,
+            LimitRule::Lifetime => WalletError::LifetimeLimitExceeded,
+            LimitRule::Category => WalletError::CategoryLimitExceeded,
+            LimitRule::Reference => WalletError::ReferenceLimitExceeded,
+        }
+    }
+}
+
+/// Sensitive actions recorded in the audit log
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuditAction {
+    #[default]
+    Freeze,
+    Unfreeze,
+    LimitUpdate,
+    GuardianAdded,
+    GuardianRemoved,
+    RecoveryInitiated,
+    RecoveryApproved,
+    RecoveryExecuted,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub actor: Pubkey,              // Signer that triggered the action (default = permissionless)
+    pub timestamp: i64,
+}
+
+#[account]
+pub struct AuditLog {
+    pub wallet: Pubkey,
+    pub head: u16,                  // Index the next entry is written to
+    pub len: u16,                   // Number of valid entries (<= AUDIT_LOG_CAPACITY)
+    pub entries: [AuditEntry; AUDIT_LOG_CAPACITY],
+    pub bump: u8,
+}
+
+impl AuditLog {
+    /// Append an entry, overwriting the oldest once the buffer is full
+    pub fn record(&mut self, action: AuditAction, actor: Pubkey, timestamp: i64) {
+        self.entries[self.head as usize] = AuditEntry {
+            action,
+            actor,
+            timestamp,
+        };
+        self.head = ((self.head as usize + 1) % AUDIT_LOG_CAPACITY) as u16;
+        if (self.len as usize) < AUDIT_LOG_CAPACITY {
+            self.len += 1;
+        }
+    }
+}
+
+/// Why a pending recovery was cleared without being executed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecoveryCancelReason {
+    OwnerVeto,                      // Owner called cancel_recovery
+    OwnerActivity,                  // Owner was active after initiation (require_owner_inactivity)
+    Superseded,                     // Guardian panic rotated the authority
+    Expired,                        // Session outlived its approval window
+}
+
+/// Guardian actions reported through GuardianActed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GuardianAction {
+    InitiateRecovery,
+    ApproveRecovery,
+    ApproveTransfer,
+    CancelLimitChange,
+    VetoTransfer,
+    ApplyLimitPenalty,
+}
+
+#[account]
+pub struct AddressBookEntry {
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+    pub label: [u8; 32],            // UTF-8 label, zero padded
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct AllowedValidator {
+    pub wallet: Pubkey,
+    pub vote_account: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct GuardianInvite {
+    pub wallet: Pubkey,
+    pub pubkey: Pubkey,             // Key that must sign to accept
+    pub guardian_type: GuardianType,
+    pub weight: u8,
+    pub expires_at: i64,            // Guardian expiry once accepted (0 = never)
+    pub payer: Pubkey,              // Receives rent back on close
+    pub invited_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct GuardianEntry {
+    pub pubkey: Pubkey,
+    pub guardian_type: GuardianType,
+}
+
+#[account]
+pub struct ProgramBudget {
+    pub wallet: Pubkey,
+    pub program_id: Pubkey,
+    pub destination: Pubkey,
+    pub remaining: u64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+// ============ Context Structures ============
+
+#[derive(Accounts)]
+#[instruction(wallet_id: [u8; 32])]
+pub struct InitializeWallet<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<SmartWallet>() + 100 + 32 * MAX_OWNERS,
+        seeds = [b"wallet", wallet_id.as_ref()],
+        bump
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    /// CHECK: Owner identifier (can be any pubkey representing the user)
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: MPC-derived authority
+    pub authority: UncheckedAccount<'info>,
+
+    /// Owner's index; only passed when the owner signs
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<OwnerIndex>(),
+        seeds = [b"owner_index", owner.key().as_ref()],
+        bump,
+        constraint = owner.is_signer @ WalletError::Unauthorized,
+    )]
+    pub owner_index: Option<Account<'info, OwnerIndex>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(guardian_pubkey: Pubkey)]
+pub struct AddGuardian<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<Guardian>(),
+        seeds = [b"guardian", wallet.key().as_ref(), &[wallet.next_guardian_index]],
+        bump
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    /// CHECK: BlockedKey PDA for guardian_pubkey; must be uninitialized
+    #[account(
+        seeds = [b"blocked", guardian_pubkey.as_ref()],
+        bump,
+    )]
+    pub blocked_key: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct PruneExpiredGuardian<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        close = wallet,
+        constraint = guardian.wallet == wallet.key(),
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, category: u8)]
+pub struct TransferSPL<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount,
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: CategoryLimit PDA for `category`; may be uninitialized when the
+    /// category has no budget. Seeds are enforced so it can't be swapped out.
+    #[account(
+        mut,
+        seeds = [b"cat", wallet.key().as_ref(), &[category]],
+        bump,
+    )]
+    pub category_limit: UncheckedAccount<'info>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), from_token_account.mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: The wallet's configured notify program; receives the transfer callback
+    #[account(
+        executable,
+        constraint = wallet.notify_program == Some(notify_program.key()) @ WalletError::InvalidNotifyProgram,
+    )]
+    pub notify_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: PriceQuote for the transferred mint; required with a reference
+    /// budget and validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// Transferred mint, read only for TransferExecuted.decimals
+    #[account(address = from_token_account.mint)]
+    pub mint: Option<Account<'info, Mint>>,
+
+    /// Platform treasury token account; required while the fee is on
+    #[account(
+        mut,
+        constraint = fee_token_account.owner == global_config.fee_destination @ WalletError::InvalidFeeDestination,
+        constraint = fee_token_account.mint == from_token_account.mint @ WalletError::InvalidFeeDestination,
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TransferSPLCreateAta<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount,
+        constraint = from_token_account.mint == mint.key(),
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Owner of the destination ATA; any address can receive
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: recipient's ATA for `mint`, pinned by address; created by the
+    /// associated token program when empty
+    #[account(
+        mut,
+        address = get_associated_token_address(&recipient.key(), &mint.key()),
+    )]
+    pub to_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for the transferred mint; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The wallet's configured notify program; receives the transfer callback
+    #[account(
+        executable,
+        constraint = wallet.notify_program == Some(notify_program.key()) @ WalletError::InvalidNotifyProgram,
+    )]
+    pub notify_program: Option<UncheckedAccount<'info>>,
+
+    /// Platform treasury token account; required while the fee is on
+    #[account(
+        mut,
+        constraint = fee_token_account.owner == global_config.fee_destination @ WalletError::InvalidFeeDestination,
+        constraint = fee_token_account.mint == mint.key() @ WalletError::InvalidFeeDestination,
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, category: u8)]
+pub struct SimulateTransfer<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(constraint = from_token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount)]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: CategoryLimit PDA for `category`; may be uninitialized
+    #[account(
+        seeds = [b"cat", wallet.key().as_ref(), &[category]],
+        bump,
+    )]
+    pub category_limit: UncheckedAccount<'info>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), from_token_account.mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for the transferred mint; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWallet<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+}
+
+#[derive(Accounts)]
+pub struct SweepAll<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key(),
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.mint == from_token_account.mint,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key(),
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.mint == from_token_account.mint,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveDelegate<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = token_account.owner == wallet.key(),
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Delegate receiving the allowance
+    pub delegate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// No pause constraint: revoking an allowance only reduces exposure, so it
+/// stays available while the program is paused
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        constraint = token_account.owner == wallet.key(),
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTransaction<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for Ed25519 verification instructions
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitiateRecovery<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = guardian.wallet == wallet.key(),
+        constraint = guardian.is_active,
+        constraint = guardian.pubkey == initiator.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<RecoverySession>(),
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    pub initiator: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = guardian.wallet == wallet.key(),
+        constraint = guardian.is_active,
+        constraint = guardian.pubkey == approver.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump = recovery_session.bump,
+        constraint = wallet.recovery_pending @ WalletError::NoRecoveryPending,
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    pub approver: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for the challenge signature
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump = recovery_session.bump,
+        constraint = wallet.recovery_pending @ WalletError::NoRecoveryPending,
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    /// CHECK: Original rent payer, validated against the session
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    /// Current owner's index; the wallet is removed when the owner rotates
+    #[account(
+        mut,
+        seeds = [b"owner_index", wallet.owner.as_ref()],
+        bump = previous_owner_index.bump,
+    )]
+    pub previous_owner_index: Option<Account<'info, OwnerIndex>>,
+
+    /// CHECK: Instructions sysvar, read for an aggregated guardian signature
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeWallet<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileSpend<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(constraint = token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveFreeze<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub guardian: Option<Account<'info, Guardian>>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnfreezeWallet<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for inline guardian signatures
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLimit<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump = audit_log.bump,
+    )]
+    pub audit_log: Option<Account<'info, AuditLog>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestTransfer<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<TransferRequest>(),
+        seeds = [
+            b"transfer_request",
+            wallet.key().as_ref(),
+            wallet.next_transfer_request_id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub request: Account<'info, TransferRequest>,
+
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveTransferRequest<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+    )]
+    pub request: Account<'info, TransferRequest>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        constraint = guardian.pubkey == approver.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTransferRequest<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        close = payer,
+    )]
+    pub request: Account<'info, TransferRequest>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key(),
+        constraint = from_token_account.mint == request.mint,
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.key() == request.destination,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), request.mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for the transferred mint; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// Platform treasury token account; required while the fee is on
+    #[account(
+        mut,
+        constraint = fee_token_account.owner == global_config.fee_destination @ WalletError::InvalidFeeDestination,
+        constraint = fee_token_account.mint == request.mint @ WalletError::InvalidFeeDestination,
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Original rent payer, validated against the request
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct HoldTransfer<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<HeldTransfer>(),
+        seeds = [
+            b"held_transfer",
+            wallet.key().as_ref(),
+            wallet.next_held_transfer_id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub held_transfer: Account<'info, HeldTransfer>,
+
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GuardianVeto<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        close = payer,
+    )]
+    pub held_transfer: Account<'info, HeldTransfer>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        constraint = guardian.pubkey == approver.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub approver: Signer<'info>,
+
+    /// CHECK: Original rent payer, validated against the held transfer
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GuardianFlag<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        constraint = guardian.pubkey == approver.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseHeldTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        close = payer,
+    )]
+    pub held_transfer: Account<'info, HeldTransfer>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount,
+        constraint = from_token_account.mint == held_transfer.mint,
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.key() == held_transfer.destination,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), held_transfer.mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for the transferred mint; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// Platform treasury token account; required while the fee is on
+    #[account(
+        mut,
+        constraint = fee_token_account.owner == global_config.fee_destination @ WalletError::InvalidFeeDestination,
+        constraint = fee_token_account.mint == held_transfer.mint @ WalletError::InvalidFeeDestination,
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Original rent payer, validated against the held transfer
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTransferRequest<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        close = payer,
+    )]
+    pub request: Account<'info, TransferRequest>,
+
+    /// CHECK: Original rent payer, validated against the request
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(category: u8)]
+pub struct SetCategoryLimit<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<CategoryLimit>(),
+        seeds = [b"cat", wallet.key().as_ref(), &[category]],
+        bump
+    )]
+    pub category_limit: Account<'info, CategoryLimit>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferNft<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key(),
+        constraint = from_token_account.mint == mint.key(),
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = to_token_account.mint == mint.key(),
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: SoulboundMint PDA for this mint; must be uninitialized
+    #[account(
+        seeds = [b"soulbound", wallet.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub soulbound: UncheckedAccount<'info>,
+
+    /// CHECK: FrozenMint PDA for this mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MarkSoulbound<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<SoulboundMint>(),
+        seeds = [b"soulbound", wallet.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub soulbound: Account<'info, SoulboundMint>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBlocklist<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<Blocklist>(),
+        seeds = [b"blocklist"],
+        bump
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ WalletError::Unauthorized)]
+    pub program: Program<'info, crate::program::LeetWallet>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ WalletError::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub upgrade_authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(pubkey: Pubkey)]
+pub struct BlockPubkey<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"blocklist"],
+        bump = blocklist.bump,
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<BlockedKey>(),
+        seeds = [b"blocked", pubkey.as_ref()],
+        bump
+    )]
+    pub blocked_key: Account<'info, BlockedKey>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnblockPubkey<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"blocklist"],
+        bump = blocklist.bump,
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blocked", blocked_key.pubkey.as_ref()],
+        bump = blocked_key.bump,
+    )]
+    pub blocked_key: Account<'info, BlockedKey>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(sender: Pubkey)]
+pub struct BlockSender<'info> {
+    #[account(
+        has_one = admin,
+        seeds = [b"blocklist"],
+        bump = blocklist.bump,
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<BlockedKey>(),
+        seeds = [b"blocked_sender", sender.as_ref()],
+        bump
+    )]
+    pub blocked_sender: Account<'info, BlockedKey>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnblockSender<'info> {
+    #[account(
+        has_one = admin,
+        seeds = [b"blocklist"],
+        bump = blocklist.bump,
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blocked_sender", blocked_sender.pubkey.as_ref()],
+        bump = blocked_sender.bump,
+    )]
+    pub blocked_sender: Account<'info, BlockedKey>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + std::mem::size_of::<PendingDeposit>(),
+        seeds = [
+            b"deposit",
+            wallet.key().as_ref(),
+            wallet.next_deposit_id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub pending_deposit: Account<'info, PendingDeposit>,
+
+    /// CHECK: Escrow PDA that owns deposited tokens until they are claimed
+    #[account(
+        seeds = [b"escrow", wallet.key().as_ref()],
+        bump,
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == escrow_authority.key(),
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.mint == escrow_token_account.mint,
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDeposit<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        constraint = pending_deposit.sender == sender.key(),
+        close = sender,
+    )]
+    pub pending_deposit: Account<'info, PendingDeposit>,
+
+    /// CHECK: Escrow PDA that owns deposited tokens until they are claimed
+    #[account(
+        seeds = [b"escrow", wallet.key().as_ref()],
+        bump,
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == escrow_authority.key(),
+        constraint = escrow_token_account.mint == pending_deposit.mint,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = wallet_token_account.owner == wallet.key(),
+        constraint = wallet_token_account.mint == pending_deposit.mint,
+    )]
+    pub wallet_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Sender-blocklist PDA for the sender; must be uninitialized
+    #[account(
+        seeds = [b"blocked_sender", pending_deposit.sender.as_ref()],
+        bump,
+    )]
+    pub blocked_sender: UncheckedAccount<'info>,
+
+    /// CHECK: Original depositor, receives the deposit record's rent
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundDeposit<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        constraint = pending_deposit.sender == sender.key(),
+        close = sender,
+    )]
+    pub pending_deposit: Account<'info, PendingDeposit>,
+
+    /// CHECK: Escrow PDA that owns deposited tokens until they are claimed
+    #[account(
+        seeds = [b"escrow", wallet.key().as_ref()],
+        bump,
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == escrow_authority.key(),
+        constraint = escrow_token_account.mint == pending_deposit.mint,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == pending_deposit.sender,
+        constraint = sender_token_account.mint == pending_deposit.mint,
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Original depositor, receives the deposit record's rent
+    #[account(mut)]
+    pub sender: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ManageOwners<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAuditLog<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<AuditLog>(),
+        seeds = [b"audit", wallet.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WrapSol<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = wallet,
+    )]
+    pub wsol_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnwrapSol<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(address = token::spl_token::native_mint::ID)]
+    pub wsol_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = wsol_mint,
+        associated_token::authority = wallet,
+    )]
+    pub wsol_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyLimitChange<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyRecoveryDelay<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyReferenceBudget<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+}
+
+#[derive(Accounts)]
+pub struct CancelLimitChange<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(mut)]
+    pub guardian: Option<Account<'info, Guardian>>,
+
+    pub canceller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct AddAddress<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<AddressBookEntry>(),
+        seeds = [b"addr", wallet.key().as_ref(), recipient.as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, AddressBookEntry>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveAddress<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        close = rent_receiver,
+    )]
+    pub entry: Account<'info, AddressBookEntry>,
+
+    /// CHECK: Receives the closed entry's rent
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Panic<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateStake<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [b"validator", wallet.key().as_ref(), vote_account.key().as_ref()],
+        bump = allowed_validator.bump,
+    )]
+    pub allowed_validator: Account<'info, AllowedValidator>,
+
+    /// CHECK: Stake account with the wallet PDA as staker; validated by the stake program
+    #[account(mut, owner = stake::program::ID)]
+    pub stake_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validator vote account, checked against allowed_validator seeds
+    pub vote_account: UncheckedAccount<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+
+    /// CHECK: Stake history sysvar
+    #[account(address = sysvar::stake_history::ID)]
+    pub stake_history: UncheckedAccount<'info>,
+
+    /// CHECK: Stake config account
+    #[account(address = stake::config::ID)]
+    pub stake_config: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Native stake program
+    #[account(address = stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeactivateStake<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    /// CHECK: Stake account with the wallet PDA as staker; validated by the stake program
+    #[account(mut, owner = stake::program::ID)]
+    pub stake_account: UncheckedAccount<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Native stake program
+    #[account(address = stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AllowValidator<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    /// CHECK: Validator vote account being allowlisted
+    pub vote_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<AllowedValidator>(),
+        seeds = [b"validator", wallet.key().as_ref(), vote_account.key().as_ref()],
+        bump
+    )]
+    pub allowed_validator: Account<'info, AllowedValidator>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(guardian_pubkey: Pubkey)]
+pub struct InviteGuardian<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<GuardianInvite>(),
+        seeds = [b"invite", wallet.key().as_ref(), guardian_pubkey.as_ref()],
+        bump
+    )]
+    pub invite: Account<'info, GuardianInvite>,
+
+    /// CHECK: BlockedKey PDA for guardian_pubkey; must be uninitialized
+    #[account(
+        seeds = [b"blocked", guardian_pubkey.as_ref()],
+        bump,
+    )]
+    pub blocked_key: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptGuardian<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        constraint = invite.pubkey == invitee.key() @ WalletError::Unauthorized,
+        close = payer,
+    )]
+    pub invite: Account<'info, GuardianInvite>,
+
+    #[account(
+        init,
+        payer = invitee,
+        space = 8 + std::mem::size_of::<Guardian>(),
+        seeds = [b"guardian", wallet.key().as_ref(), &[wallet.next_guardian_index]],
+        bump
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    #[account(mut)]
+    pub invitee: Signer<'info>,
+
+    /// CHECK: Original invite payer, validated against the invite
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelInvite<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        close = payer,
+    )]
+    pub invite: Account<'info, GuardianInvite>,
+
+    /// CHECK: Original invite payer, validated against the invite
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeclineInvite<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        has_one = payer,
+        constraint = invite.pubkey == invitee.key() @ WalletError::Unauthorized,
+        close = payer,
+    )]
+    pub invite: Account<'info, GuardianInvite>,
+
+    pub invitee: Signer<'info>,
+
+    /// CHECK: Original invite payer, validated against the invite
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddGuardiansBatch<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey)]
+pub struct GrantProgramBudget<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<ProgramBudget>(),
+        seeds = [b"pbudget", wallet.key().as_ref(), program_id.as_ref()],
+        bump
+    )]
+    pub program_budget: Account<'info, ProgramBudget>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeProgramBudget<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        close = authority,
+    )]
+    pub program_budget: Account<'info, ProgramBudget>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DrawProgramBudget<'info> {
+    #[account(
+        mut,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        seeds = [b"pbudget", wallet.key().as_ref(), program_budget.program_id.as_ref()],
+        bump = program_budget.bump,
+    )]
+    pub program_budget: Account<'info, ProgramBudget>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount,
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = program_budget.destination @ WalletError::InvalidDestination,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: FrozenMint PDA for the transferred mint; must be uninitialized
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), from_token_account.mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for the transferred mint; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// Platform treasury token account; required while the fee is on
+    #[account(
+        mut,
+        constraint = fee_token_account.owner == global_config.fee_destination @ WalletError::InvalidFeeDestination,
+        constraint = fee_token_account.mint == from_token_account.mint @ WalletError::InvalidFeeDestination,
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// PDA of the budgeted program; only that program can sign for it
+    #[account(
+        address = Pubkey::find_program_address(&[BUDGET_CALLER_SEED], &program_budget.program_id).0
+            @ WalletError::Unauthorized,
+    )]
+    pub budget_caller: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetComplianceAdmin<'info> {
+    #[account(mut)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageComplianceHold<'info> {
+    #[account(
+        mut,
+        constraint = wallet.compliance_admin == Some(compliance_admin.key()) @ WalletError::Unauthorized,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub compliance_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardianCommitment<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyGuardianCommitment<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(has_one = wallet)]
+    pub guardian: Account<'info, Guardian>,
+}
+
+#[derive(Accounts)]
+pub struct GetPolicySnapshot<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(constraint = token_account.owner == wallet.key())]
+    pub token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_wallet_id: [u8; 32])]
+pub struct MigrateWallet<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<SmartWallet>() + 100 + 32 * MAX_OWNERS,
+        seeds = [b"wallet", new_wallet_id.as_ref()],
+        bump
+    )]
+    pub new_wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        seeds = [b"owner_index", wallet.owner.as_ref()],
+        bump = owner_index.bump,
+    )]
+    pub owner_index: Option<Account<'info, OwnerIndex>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepToMigrated<'info> {
+    #[account(
+        seeds = [b"wallet", wallet.wallet_id.as_ref()],
+        bump = wallet.bump,
+        constraint = wallet.migrated_to.is_some() @ WalletError::WalletNotMigrated,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = from_token_account.owner == wallet.key(),
+    )]
+    pub from_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = Some(to_token_account.owner) == wallet.migrated_to @ WalletError::WalletNotMigrated,
+        constraint = to_token_account.mint == from_token_account.mint,
+    )]
+    pub to_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetSpendWindow<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<SpendWindow>(),
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump
+    )]
+    pub spend_window: Account<'info, SpendWindow>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeMint<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<FrozenMint>(),
+        seeds = [b"fmint", wallet.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub frozen_mint: Account<'info, FrozenMint>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnfreezeMint<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+        close = authority,
+        seeds = [b"fmint", wallet.key().as_ref(), frozen_mint.mint.as_ref()],
+        bump = frozen_mint.bump,
+    )]
+    pub frozen_mint: Account<'info, FrozenMint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, mint: Pubkey, recipient: Pubkey, category: u8)]
+pub struct CheckTransferEligibility<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        constraint = token_account.owner == wallet.key() @ WalletError::InvalidSourceAccount,
+        constraint = token_account.mint == mint,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: FrozenMint PDA for `mint`; initialized only when the mint is frozen
+    #[account(
+        seeds = [b"fmint", wallet.key().as_ref(), mint.as_ref()],
+        bump,
+    )]
+    pub frozen_mint: UncheckedAccount<'info>,
+
+    /// CHECK: CategoryLimit PDA for `category`; may be uninitialized
+    #[account(
+        seeds = [b"cat", wallet.key().as_ref(), &[category]],
+        bump,
+    )]
+    pub category_limit: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"spend_window", wallet.key().as_ref()],
+        bump = spend_window.bump,
+    )]
+    pub spend_window: Option<Account<'info, SpendWindow>>,
+
+    /// CHECK: PriceQuote for `mint`; validated by ReferenceBudget::value_of
+    pub price_quote: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: AddressBookEntry PDA for `recipient`; may be uninitialized
+    #[account(
+        seeds = [b"addr", wallet.key().as_ref(), recipient.as_ref()],
+        bump,
+    )]
+    pub address_book_entry: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageGuardian<'info> {
+    #[account(has_one = authority)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = wallet,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmReactivation<'info> {
+    #[account(
+        mut,
+        constraint = guardian.pubkey == guardian_signer.key() @ WalletError::Unauthorized,
+    )]
+    pub guardian: Account<'info, Guardian>,
+
+    pub guardian_signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<GlobalConfig>(),
+        seeds = [b"config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ WalletError::Unauthorized)]
+    pub program: Program<'info, crate::program::LeetWallet>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ WalletError::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub upgrade_authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GlobalAdmin<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = global_config.bump,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = wallet.recovery_pending @ WalletError::NoRecoveryPending,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump = recovery_session.bump,
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    /// CHECK: Original rent payer, validated against the session
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireRecovery<'info> {
+    #[account(
+        mut,
+        constraint = wallet.recovery_pending @ WalletError::NoRecoveryPending,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump = recovery_session.bump,
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    /// CHECK: Original rent payer, validated against the session
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStaleRecovery<'info> {
+    #[account(constraint = !wallet.recovery_pending @ WalletError::RecoveryAlreadyPending)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        seeds = [b"recovery", wallet.key().as_ref()],
+        bump = recovery_session.bump,
+    )]
+    pub recovery_session: Account<'info, RecoverySession>,
+
+    /// CHECK: Original rent payer, validated against the session
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IndexWallet<'info> {
+    #[account(has_one = owner)]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<OwnerIndex>(),
+        seeds = [b"owner_index", wallet.owner.as_ref()],
+        bump
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PruneOwnerIndex<'info> {
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        mut,
+        seeds = [b"owner_index", owner_index.owner.as_ref()],
+        bump = owner_index.bump,
+        constraint = owner_index.owner != wallet.owner @ WalletError::InvalidOwnerIndex,
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFromOwnerIndex<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"owner_index", owner.key().as_ref()],
+        bump = owner_index.bump,
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OwnerAction<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RotateAuthority<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+    )]
+    pub wallet: Account<'info, SmartWallet>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = global_config.bump,
+        constraint = !global_config.paused @ WalletError::ProgramPaused,
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub owner: Signer<'info>,
+}
+
+// ============ Events ============
+
+#[event]
+pub struct WalletInitialized {
+    pub wallet: Pubkey,
+    pub owner: Pubkey,
+    pub wallet_id: [u8; 32],
+    pub created_at: i64,
+}
+
+#[event]
+pub struct GuardianAdded {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub guardian_type: GuardianType,
+    pub weight: u8,
+}
+
+#[event]
+pub struct TransferExecuted {
+    pub wallet: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+    pub category: u8,
+    pub reference: [u8; 32],
+    pub mint: Pubkey,
+    pub decimals: Option<u8>,       // Set when the mint account was passed
+}
+
+#[event]
+pub struct AssociatedAccountCreated {
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[event]
+pub struct WalletSwept {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SafeAddressSet {
+    pub wallet: Pubkey,
+    pub safe_address: Pubkey,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawal {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub safe_address: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TransactionExecuted {
+    pub wallet: Pubkey,
+    pub instruction_hash: [u8; 32],
+    pub nonce: u64,
+}
+
+#[event]
+pub struct RecoveryInitiated {
+    pub wallet: Pubkey,
+    pub new_authority: Pubkey,
+    pub executable_at: i64,
+    pub challenge: [u8; 32],
+}
+
+#[event]
+pub struct RecoveryApproved {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub total_approvals: u8,
+    pub total_weight: u16,
+}
+
+#[event]
+pub struct RecoveryExecuted {
+    pub wallet: Pubkey,
+    pub new_authority: Pubkey,
+    pub rotated_by_owner: bool,     // Set by rotate_authority rather than guardian recovery
+    pub authority_is_group: bool,
+    pub group_pubkey: Option<Pubkey>, // new_authority when it is an MPC group key
+}
+
+#[event]
+pub struct WalletFrozen {
+    pub wallet: Pubkey,
+    pub frozen_by: Pubkey,
+    pub reason: FreezeReason,
+}
+
+#[event]
+pub struct WalletUnfrozen {
+    pub wallet: Pubkey,
+}
+
+#[event]
+pub struct LimitUpdated {
+    pub wallet: Pubkey,
+    pub new_limit: u64,
+}
+
+#[event]
+pub struct FreezeApproved {
+    pub wallet: Pubkey,
+    pub approver: Pubkey,
+    pub approvals: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct FreezeThresholdUpdated {
+    pub wallet: Pubkey,
+    pub freeze_threshold: u8,
+}
+
+#[event]
+pub struct LimitDecreaseFlagged {
+    pub wallet: Pubkey,
+    pub previous_limit: u64,
+    pub new_limit: u64,
+    pub drop_bps: u16,
+}
+
+#[event]
+pub struct LimitDecreasePolicyUpdated {
+    pub wallet: Pubkey,
+    pub floor: u64,
+    pub alert_bps: u16,
+}
+
+#[event]
+pub struct GuardianDiversityUpdated {
+    pub wallet: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct DelegateApproved {
+    pub wallet: Pubkey,
+    pub token_account: Pubkey,
+    pub delegate: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DelegateRevoked {
+    pub wallet: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[event]
+pub struct TransferWindowUpdated {
+    pub wallet: Pubkey,
+    pub start_second: u32,
+    pub end_second: u32,
+}
+
+#[event]
+pub struct TransferCooldownUpdated {
+    pub wallet: Pubkey,
+    pub min_interval: i64,
+}
+
+#[event]
+pub struct SpendReconciled {
+    pub wallet: Pubkey,
+    pub token_account: Pubkey,
+    pub mint: Pubkey,
+    pub balance: u64,
+    pub recorded_spend: u64,        // lifetime_spent growth since the last baseline
+    pub observed_outflow: u64,      // Net balance decrease since the last baseline
+    pub discrepancy: u64,           // Outflow not covered by recorded spend
+    pub corrected: bool,
+}
+
+#[event]
+pub struct ReconcileModeUpdated {
+    pub wallet: Pubkey,
+    pub corrects: bool,
+}
+
+#[event]
+pub struct PdaVerified {
+    pub wallet: Pubkey,
+    pub wallet_id: [u8; 32],
+    pub expected: Pubkey,
+    pub expected_bump: u8,
+    pub stored_bump: u8,
+    pub valid: bool,
+}
+
+#[event]
+pub struct WalletVersion {
+    pub wallet: Pubkey,
+    pub program_version: u32,
+    pub feature_flags: u64,
+}
+
+#[event]
+pub struct RemainingLimit {
+    pub wallet: Pubkey,
+    pub remaining: u64,
+    pub day: i64,
+}
+
+#[event]
+pub struct GuardianExpired {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub expired_at: i64,
+}
+
+#[event]
+pub struct TransferRequested {
+    pub wallet: Pubkey,
+    pub request: Pubkey,
+    pub destination: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TransferRequestApproved {
+    pub wallet: Pubkey,
+    pub request: Pubkey,
+    pub guardian: Pubkey,
+    pub total_approvals: u8,
+}
+
+#[event]
+pub struct TransferRequestExecuted {
+    pub wallet: Pubkey,
+    pub request: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct TransferRequestCancelled {
+    pub wallet: Pubkey,
+    pub request: Pubkey,
+}
+
+#[event]
+pub struct LimitPenaltyConfigUpdated {
+    pub wallet: Pubkey,
+    pub penalty_bps: u16,
+    pub duration: i64,
+}
+
+#[event]
+pub struct LimitPenaltyApplied {
+    pub wallet: Pubkey,
+    pub applied_by: Pubkey,         // Guardian, or the program for an anomaly
+    pub penalty_bps: u16,
+    pub until: i64,
+}
+
+#[event]
+pub struct LimitPenaltyExpired {
+    pub wallet: Pubkey,
+    pub expired_at: i64,
+}
+
+#[event]
+pub struct TransferReviewUpdated {
+    pub wallet: Pubkey,
+    pub review_window: i64,
+    pub review_threshold: u64,
+}
+
+#[event]
+pub struct TransferHeld {
+    pub wallet: Pubkey,
+    pub held_transfer: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub releasable_at: i64,
+}
+
+#[event]
+pub struct TransferVetoed {
+    pub wallet: Pubkey,
+    pub held_transfer: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct HeldTransferExecuted {
+    pub wallet: Pubkey,
+    pub held_transfer: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct CategoryLimitUpdated {
+    pub wallet: Pubkey,
+    pub category: u8,
+    pub daily_limit: u64,
+}
+
+#[event]
+pub struct NftTransferred {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub to: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct SoulboundMarked {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct BlocklistInitialized {
+    pub blocklist: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct PubkeyBlocked {
+    pub pubkey: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct PubkeyUnblocked {
+    pub pubkey: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct SenderBlocked {
+    pub sender: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct SenderUnblocked {
+    pub sender: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct VacationLockSet {
+    pub wallet: Pubkey,
+    pub lock_from: i64,
+    pub lock_until: i64,
+}
+
+#[event]
+pub struct DailyLimitBpsUpdated {
+    pub wallet: Pubkey,
+    pub daily_limit_bps: u16,
+}
+
+#[event]
+pub struct WeekdayLimitsUpdated {
+    pub wallet: Pubkey,
+    pub weekday_limits: Option<[u64; 7]>,
+}
+
+#[event]
+pub struct EventEmissionUpdated {
+    pub wallet: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct DepositReceived {
+    pub wallet: Pubkey,
+    pub deposit: Pubkey,
+    pub sender: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DepositClaimed {
+    pub wallet: Pubkey,
+    pub deposit: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DepositRefunded {
+    pub wallet: Pubkey,
+    pub deposit: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OwnerAdded {
+    pub wallet: Pubkey,
+    pub owner: Pubkey,
+    pub owner_count: u8,
+}
+
+#[event]
+pub struct OwnerRemoved {
+    pub wallet: Pubkey,
+    pub owner: Pubkey,
+    pub owner_count: u8,
+}
+
+#[event]
+pub struct OwnerThresholdUpdated {
+    pub wallet: Pubkey,
+    pub owner_threshold: u8,
+}
+
+#[event]
+pub struct RecoveryThresholdUpdated {
+    pub wallet: Pubkey,
+    pub recovery_threshold: u8,
+}
+
+#[event]
+pub struct GuardianThresholdUpdated {
+    pub wallet: Pubkey,
+    pub guardian_threshold: u8,
+}
+
+#[event]
+pub struct AnomalyThresholdUpdated {
+    pub wallet: Pubkey,
+    pub threshold: u64,
+    pub window: i64,
+}
+
+#[event]
+pub struct SolWrapped {
+    pub wallet: Pubkey,
+    pub wsol_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SolUnwrapped {
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub lamports_returned: u64,
+}
+
+#[event]
+pub struct LimitChangeRequested {
+    pub wallet: Pubkey,
+    pub new_limit: u64,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct LimitChangeCancelled {
+    pub wallet: Pubkey,
+    pub new_limit: u64,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct GuardianActed {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub action: GuardianAction,
+    pub action_count: u32,
+}
+
+#[event]
+pub struct LimitRampUpdated {
+    pub wallet: Pubkey,
+    pub per_day: u64,
+    pub target: u64,
+}
+
+#[event]
+pub struct AddressAdded {
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+    pub label: [u8; 32],
+}
+
+#[event]
+pub struct AddressRemoved {
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct PanicEnabledUpdated {
+    pub wallet: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct StakeDelegated {
+    pub wallet: Pubkey,
+    pub stake_account: Pubkey,
+    pub vote_account: Pubkey,
+}
+
+#[event]
+pub struct StakeDeactivated {
+    pub wallet: Pubkey,
+    pub stake_account: Pubkey,
+}
+
+#[event]
+pub struct ValidatorAllowed {
+    pub wallet: Pubkey,
+    pub vote_account: Pubkey,
+}
+
+#[event]
+pub struct GuardianInvited {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub guardian_type: GuardianType,
+    pub weight: u8,
+}
+
+#[event]
+pub struct GuardianAccepted {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct GuardianInviteCancelled {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct GuardianInviteDeclined {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct GuardiansAdded {
+    pub wallet: Pubkey,
+    pub count: u8,
+}
+
+#[event]
+pub struct ProgramBudgetGranted {
+    pub wallet: Pubkey,
+    pub program_id: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ProgramBudgetRevoked {
+    pub wallet: Pubkey,
+    pub program_id: Pubkey,
+}
+
+#[event]
+pub struct ProgramBudgetDrawn {
+    pub wallet: Pubkey,
+    pub program_id: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct ComplianceAdminUpdated {
+    pub wallet: Pubkey,
+    pub previous_admin: Option<Pubkey>,
+    pub admin: Option<Pubkey>,
+}
+
+#[event]
+pub struct ComplianceHoldSet {
+    pub wallet: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct ComplianceHoldReleased {
+    pub wallet: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct MpcModeUpdated {
+    pub wallet: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct GuardianCommitmentUpdated {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct PendingRecovery {
-    pub new_authority: Pubkey,
-    pub initiated_at: i64,
-    pub approvals: u8,
-    pub executed: bool,
+#[event]
+pub struct MaxPendingItemsUpdated {
+    pub wallet: Pubkey,
+    pub max_pending_items: u8,
 }
 
-#[account]
-pub struct Guardian {
+#[event]
+pub struct LifetimeLimitUpdated {
     pub wallet: Pubkey,
-    pub pubkey: Pubkey,
-    pub guardian_type: GuardianType,
-    pub added_at: i64,
-    pub is_active: bool,
-    pub bump: u8,
+    pub lifetime_limit: u64,
+    pub lifetime_spent: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum GuardianType {
-    Email,
-    Phone,
-    Wallet,
-    Hardware,
-    Institution,
+#[event]
+pub struct LimitChangeGuardiansUpdated {
+    pub wallet: Pubkey,
+    pub enabled: bool,
 }
 
-impl Default for GuardianType {
-    fn default() -> Self {
-        GuardianType::Wallet
-    }
+#[event]
+pub struct PolicySnapshot {
+    pub wallet: Pubkey,
+    pub effective_daily_limit: u64,
+    pub remaining_today: u64,
+    pub is_frozen: bool,
+    pub compliance_hold: bool,
+    pub vacation_locked: bool,
+    pub within_transfer_window: bool,
+    pub cooldown_elapsed: bool,
+    pub next_reset_at: i64,
 }
 
-// ============ Context Structures ============
+#[event]
+pub struct OwnershipTransferred {
+    pub wallet: Pubkey,
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
 
-#[derive(Accounts)]
-#[instruction(wallet_id: [u8; 32])]
-pub struct InitializeWallet<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + std::mem::size_of::<SmartWallet>() + 100,
-        seeds = [b"wallet", wallet_id.as_ref()],
-        bump
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct NotifyProgramUpdated {
+    pub wallet: Pubkey,
+    pub notify_program: Option<Pubkey>,
+    pub strict_hook: bool,
+}
 
-    /// CHECK: Owner identifier (can be any pubkey representing the user)
-    pub owner: UncheckedAccount<'info>,
+#[event]
+pub struct VestingScheduleSet {
+    pub wallet: Pubkey,
+    pub start: i64,
+    pub cliff: i64,
+    pub duration: i64,
+    pub total: u64,
+}
 
-    /// CHECK: MPC-derived authority
-    pub authority: UncheckedAccount<'info>,
+#[event]
+pub struct WalletMigrated {
+    pub old_wallet: Pubkey,
+    pub new_wallet: Pubkey,
+    pub new_wallet_id: [u8; 32],
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+#[event]
+pub struct ExecRateLimitUpdated {
+    pub wallet: Pubkey,
+    pub max_exec_daily_count: u32,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ExecPayloadLimitUpdated {
+    pub wallet: Pubkey,
+    pub max_exec_payload_len: u16,
 }
 
-#[derive(Accounts)]
-pub struct AddGuardian<'info> {
-    #[account(
-        mut,
-        has_one = authority,
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct ExecNonceWindowUpdated {
+    pub wallet: Pubkey,
+    pub window: u8,
+}
 
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + std::mem::size_of::<Guardian>(),
-        seeds = [b"guardian", wallet.key().as_ref(), &[wallet.guardian_count]],
-        bump
-    )]
-    pub guardian: Account<'info, Guardian>,
+#[event]
+pub struct PostRecoveryCooldownUpdated {
+    pub wallet: Pubkey,
+    pub cooldown: i64,
+}
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct SpendWindowUpdated {
+    pub wallet: Pubkey,
+    pub window_seconds: i64,
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+#[event]
+pub struct GuardianAlert {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub reason: FreezeReason,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct MintFrozen {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct TransferSPL<'info> {
-    #[account(
-        mut,
-        seeds = [b"wallet", wallet.wallet_id.as_ref()],
-        bump = wallet.bump,
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct MintUnfrozen {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+}
 
-    #[account(mut)]
-    pub from_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct GuardianRateLimitUpdated {
+    pub wallet: Pubkey,
+    pub max_guardians_per_day: u8,
+}
 
-    #[account(mut)]
-    pub to_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct TransferEligibility {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub eligible: bool,
+    pub paused: bool,
+    pub frozen: bool,
+    pub compliance_hold: bool,
+    pub spending_disabled: bool,
+    pub vacation_locked: bool,
+    pub within_transfer_window: bool,
+    pub cooldown_elapsed: bool,
+    pub requires_review: bool,
+    pub within_daily_limit: bool,
+    pub within_lifetime_limit: bool,
+    pub within_spend_window: bool,
+    pub within_category_limit: bool,
+    pub within_reference_budget: bool,
+    pub mint_frozen: bool,
+    pub in_address_book: bool,
+}
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct GuardianDeactivated {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+}
 
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct GuardianReactivationRequested {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct ExecuteTransaction<'info> {
-    #[account(
-        mut,
-        has_one = authority,
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct GuardianReactivated {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+}
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct GuardianRecoveryPermissionUpdated {
+    pub wallet: Pubkey,
+    pub guardian: Pubkey,
+    pub can_approve_recovery: bool,
 }
 
-#[derive(Accounts)]
-pub struct InitiateRecovery<'info> {
-    #[account(mut)]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct UnfreezeThresholdUpdated {
+    pub wallet: Pubkey,
+    pub unfreeze_threshold: u8,
+}
 
-    #[account(
-        constraint = guardian.wallet == wallet.key(),
-        constraint = guardian.is_active,
-    )]
-    pub guardian: Account<'info, Guardian>,
+#[event]
+pub struct GlobalConfigInitialized {
+    pub config: Pubkey,
+    pub admin: Pubkey,
+}
 
-    pub initiator: Signer<'info>,
+#[event]
+pub struct ProgramPausedUpdated {
+    pub paused: bool,
+    pub admin: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct ApproveRecovery<'info> {
-    #[account(mut)]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct GlobalAdminTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
 
-    #[account(
-        constraint = guardian.wallet == wallet.key(),
-        constraint = guardian.is_active,
-    )]
-    pub guardian: Account<'info, Guardian>,
+#[event]
+pub struct PlatformFeeUpdated {
+    pub fee_bps: u16,
+    pub fee_destination: Pubkey,
+}
 
-    pub approver: Signer<'info>,
+#[event]
+pub struct PriceProgramUpdated {
+    pub price_program: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct ExecuteRecovery<'info> {
-    #[account(mut)]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct FeeCollected {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub fee_account: Pubkey,
+    pub amount: u64,                // Amount received by the recipient
+    pub fee: u64,
 }
 
-#[derive(Accounts)]
-pub struct FreezeWallet<'info> {
-    #[account(
-        mut,
-        has_one = authority,
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct ReferenceBudgetUpdated {
+    pub wallet: Pubkey,
+    pub reference_mint: Option<Pubkey>,
+    pub daily_limit: u64,
+    pub effective_at: i64,          // Now for immediate changes, else when apply_reference_budget may run
+}
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct RecoveryDelayUpdated {
+    pub wallet: Pubkey,
+    pub new_delay: i64,
+    pub effective_at: i64,          // Now for immediate changes, else when apply_recovery_delay may run
 }
 
-#[derive(Accounts)]
-pub struct UnfreezeWallet<'info> {
-    #[account(mut)]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct OwnerHeartbeat {
+    pub wallet: Pubkey,
+    pub owner: Pubkey,
+    pub at: i64,
+}
 
-    // Requires guardian signatures (verified off-chain)
-    pub authority: Signer<'info>,
+#[event]
+pub struct OwnerInactivityRequirementUpdated {
+    pub wallet: Pubkey,
+    pub required: bool,
 }
 
-#[derive(Accounts)]
-pub struct UpdateLimit<'info> {
-    #[account(
-        mut,
-        has_one = authority,
-    )]
-    pub wallet: Account<'info, SmartWallet>,
+#[event]
+pub struct RecoveryCancelled {
+    pub wallet: Pubkey,
+    pub reason: RecoveryCancelReason,
+    pub cancelled_at: i64,
+}
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct RecoverySessionGraceUpdated {
+    pub wallet: Pubkey,
+    pub grace: i64,
 }
 
-// ============ Events ============
+#[event]
+pub struct RecoverySessionClosed {
+    pub wallet: Pubkey,
+    pub payer: Pubkey,
+}
 
 #[event]
-pub struct WalletInitialized {
+pub struct SpendingDisabledAttempt {
     pub wallet: Pubkey,
-    pub owner: Pubkey,
-    pub wallet_id: [u8; 32],
+    pub amount: u64,
 }
 
 #[event]
-pub struct GuardianAdded {
+pub struct GuardianTypeCapUpdated {
     pub wallet: Pubkey,
-    pub guardian: Pubkey,
-    pub guardian_type: GuardianType,
+    pub max_approvals_per_type: u8,
 }
 
 #[event]
-pub struct TransferExecuted {
+pub struct GuardianGroupKeyUpdated {
     pub wallet: Pubkey,
-    pub to: Pubkey,
-    pub amount: u64,
-    pub nonce: u64,
+    pub group_key: Option<Pubkey>,
+    pub threshold: u8,
 }
 
 #[event]
-pub struct TransactionExecuted {
+pub struct ObserveOnlyUpdated {
     pub wallet: Pubkey,
-    pub instruction_hash: [u8; 32],
-    pub nonce: u64,
+    pub observe_only: bool,
 }
 
 #[event]
-pub struct RecoveryInitiated {
+pub struct LimitWouldBlock {
     pub wallet: Pubkey,
-    pub new_authority: Pubkey,
-    pub executable_at: i64,
+    pub rule: LimitRule,
+    pub amount: u64,
 }
 
 #[event]
-pub struct RecoveryApproved {
+pub struct OwnerIndexEntryAdded {
+    pub owner: Pubkey,
     pub wallet: Pubkey,
-    pub guardian: Pubkey,
-    pub total_approvals: u8,
+    pub wallet_id: [u8; 32],
+    pub len: u8,
 }
 
 #[event]
-pub struct RecoveryExecuted {
+pub struct OwnerIndexFull {
+    pub owner: Pubkey,
     pub wallet: Pubkey,
-    pub new_authority: Pubkey,
+    pub wallet_id: [u8; 32],
 }
 
 #[event]
-pub struct WalletFrozen {
+pub struct OwnerIndexEntryRemoved {
+    pub owner: Pubkey,
     pub wallet: Pubkey,
-    pub frozen_by: Pubkey,
+    pub wallet_id: [u8; 32],
 }
 
 #[event]
-pub struct WalletUnfrozen {
+pub struct RequiredGuardianTypesUpdated {
     pub wallet: Pubkey,
+    pub required_types_mask: u8,
+    pub enforce_on_recovery: bool,
 }
 
 #[event]
-pub struct LimitUpdated {
+pub struct GuardianSetValidated {
     pub wallet: Pubkey,
-    pub new_limit: u64,
+    pub required_types_mask: u8,
+    pub covered_types: u8,
+    pub missing_types: u8,          // Required types with no active guardian
+    pub valid: bool,
 }
 
 // ============ Errors ============
@@ -566,4 +9675,211 @@ pub enum WalletError {
     InvalidSignature,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Recovery approvals must come from at least two guardian types")]
+    InsufficientGuardianDiversity,
+    #[msg("Invalid guardian accounts")]
+    InvalidGuardianAccounts,
+    #[msg("Nothing to sweep")]
+    NothingToSweep,
+    #[msg("No safe address is active")]
+    NoSafeAddress,
+    #[msg("Destination is not the safe address")]
+    InvalidSafeAddress,
+    #[msg("Guardian weight must be greater than zero")]
+    InvalidGuardianWeight,
+    #[msg("Transfer outside the allowed time window")]
+    OutsideTransferWindow,
+    #[msg("Transfer window bounds must be below 86400")]
+    InvalidTransferWindow,
+    #[msg("Transfer cooldown has not elapsed")]
+    TransferTooSoon,
+    #[msg("Transfer cooldown must not be negative")]
+    InvalidTransferCooldown,
+    #[msg("Guardian has not expired")]
+    GuardianNotExpired,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Guardian has already approved")]
+    AlreadyApproved,
+    #[msg("Category spending limit exceeded")]
+    CategoryLimitExceeded,
+    #[msg("Mint is not an NFT (requires 0 decimals and supply 1)")]
+    NotAnNft,
+    #[msg("Asset is soulbound and cannot be transferred")]
+    SoulboundAsset,
+    #[msg("Guardian key is on the platform blocklist")]
+    GuardianBlocked,
+    #[msg("Wallet is in a scheduled vacation lock")]
+    VacationLock,
+    #[msg("Vacation lock must end after it starts")]
+    InvalidVacationLock,
+    #[msg("Basis points must not exceed 10000")]
+    InvalidBasisPoints,
+    #[msg("Nonce does not match the expected value")]
+    InvalidNonce,
+    #[msg("Deposit sender is blocklisted")]
+    SenderBlocked,
+    #[msg("Insufficient owner signatures")]
+    InsufficientOwnerSignatures,
+    #[msg("Too many owners (max 5)")]
+    TooManyOwners,
+    #[msg("Owner already present")]
+    DuplicateOwner,
+    #[msg("Owner not found")]
+    OwnerNotFound,
+    #[msg("Owner threshold must be between 1 and the owner count")]
+    InvalidOwnerThreshold,
+    #[msg("Threshold must be nonzero and achievable by the registered guardians")]
+    InvalidThreshold,
+    #[msg("Anomaly window must not be negative")]
+    InvalidAnomalyWindow,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("No limit change pending")]
+    NoLimitChangePending,
+    #[msg("Limit change timelock has not elapsed")]
+    LimitChangeTimelocked,
+    #[msg("Panic instruction is not enabled for this wallet")]
+    PanicDisabled,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("Duplicate guardian in batch")]
+    DuplicateGuardian,
+    #[msg("Program budget has expired")]
+    ProgramBudgetExpired,
+    #[msg("Program budget exhausted")]
+    ProgramBudgetExhausted,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("Wallet is under compliance hold")]
+    ComplianceHold,
+    #[msg("Guardian has no identity commitment")]
+    NoIdentityCommitment,
+    #[msg("Identity does not match the guardian commitment")]
+    IdentityCommitmentMismatch,
+    #[msg("Too many pending items")]
+    TooManyPendingItems,
+    #[msg("Wallet is not initialized")]
+    WalletNotInitialized,
+    #[msg("Lifetime spending limit exceeded")]
+    LifetimeLimitExceeded,
+    #[msg("Lifetime limit can only be lowered")]
+    LifetimeLimitIncrease,
+    #[msg("Notify program does not match the wallet configuration")]
+    InvalidNotifyProgram,
+    #[msg("Strict hook requires the notify program account")]
+    NotifyProgramMissing,
+    #[msg("Amount is still locked by the vesting schedule")]
+    AmountStillLocked,
+    #[msg("Vesting schedule is already set")]
+    VestingAlreadySet,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Wallet has been migrated")]
+    WalletMigrated,
+    #[msg("Wallet has not been migrated to this destination")]
+    WalletNotMigrated,
+    #[msg("Daily execute_transaction limit reached")]
+    ExecRateLimited,
+    #[msg("Nonce already used or outside the accepted window")]
+    ExpiredOrReusedNonce,
+    #[msg("Nonce window must be at most 64")]
+    InvalidNonceWindow,
+    #[msg("A recovery was executed too recently")]
+    RecoveryCooldownActive,
+    #[msg("Recovery cooldown out of range")]
+    InvalidRecoveryCooldown,
+    #[msg("Spend window account required")]
+    SpendWindowRequired,
+    #[msg("Spend window must be between 0 and 86400 seconds")]
+    InvalidSpendWindow,
+    #[msg("Wallet is not frozen")]
+    WalletNotFrozen,
+    #[msg("Mint is frozen for this wallet")]
+    MintFrozen,
+    #[msg("No guardians to remove")]
+    NoGuardiansToRemove,
+    #[msg("Daily guardian addition limit reached")]
+    GuardianRateLimited,
+    #[msg("Guardian is already active")]
+    GuardianAlreadyActive,
+    #[msg("No reactivation pending for this guardian")]
+    NoReactivationPending,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Transfer would leave the account below the rent-exempt minimum")]
+    WouldBreakRentExemption,
+    #[msg("Guardian type mask has unknown bits")]
+    InvalidGuardianTypeMask,
+    #[msg("Active guardians do not cover every required guardian type")]
+    GuardianSetIncomplete,
+    #[msg("Fee token account required while the platform fee is on")]
+    FeeAccountRequired,
+    #[msg("Fee account does not match the platform fee destination")]
+    InvalidFeeDestination,
+    #[msg("Owner index does not belong to this owner")]
+    InvalidOwnerIndex,
+    #[msg("Wallet is not in this owner index")]
+    WalletNotIndexed,
+    #[msg("No guardian group key is registered")]
+    NoGuardianGroupKey,
+    #[msg("Spending is disabled for this wallet (daily limit is 0)")]
+    SpendingDisabled,
+    #[msg("Transfer with this idempotency key was already processed")]
+    DuplicateRequest,
+    #[msg("Idempotency key must not be all zeros")]
+    InvalidIdempotencyKey,
+    #[msg("Guardian is not permitted to approve recovery")]
+    GuardianCannotApprove,
+    #[msg("execute_transaction payload exceeds the configured maximum")]
+    PayloadTooLarge,
+    #[msg("Payload limit must be between 1 and 1232 bytes")]
+    InvalidPayloadLimit,
+    #[msg("Recovery delay must be between 0 and 90 days")]
+    InvalidRecoveryDelay,
+    #[msg("No recovery delay change pending")]
+    NoDelayChangePending,
+    #[msg("Recovery delay change timelock has not elapsed")]
+    DelayChangeTimelocked,
+    #[msg("Price quote account required while a reference budget is set")]
+    PriceQuoteRequired,
+    #[msg("Price quote does not match the reference budget")]
+    InvalidPriceQuote,
+    #[msg("Price quote is stale")]
+    StalePriceQuote,
+    #[msg("Reference-value daily limit exceeded")]
+    ReferenceLimitExceeded,
+    #[msg("Recovery session expired; initiate a new recovery")]
+    RecoverySessionExpired,
+    #[msg("Recovery session has not expired yet")]
+    RecoverySessionNotExpired,
+    #[msg("Recovery session grace period out of range")]
+    InvalidRecoverySessionGrace,
+    #[msg("Source token account is not owned by the wallet")]
+    InvalidSourceAccount,
+    #[msg("Transfer exceeds the review threshold; hold it for guardian review")]
+    TransferRequiresReview,
+    #[msg("Transfer review is not enabled")]
+    TransferReviewDisabled,
+    #[msg("Review window out of range")]
+    InvalidReviewWindow,
+    #[msg("Held transfer is still in its review window")]
+    ReviewWindowActive,
+    #[msg("Limit penalty duration out of range")]
+    InvalidPenaltyDuration,
+    #[msg("No limit penalty configured")]
+    LimitPenaltyNotConfigured,
+    #[msg("Aggregated guardian signatures can't be used while a per-type approval cap is set")]
+    GuardianTypeCapActive,
+    #[msg("Freezing needs multiple approvals; use approve_freeze")]
+    FreezeNeedsApprovals,
+    #[msg("Destination is not the one this budget pays")]
+    InvalidDestination,
+    #[msg("Another limit change is already queued; cancel or apply it first")]
+    LimitChangeAlreadyPending,
+    #[msg("The primary owner can only change through recovery")]
+    PrimaryOwnerNotRemovable,
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,4631 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar::clock::Clock;
+use anchor_lang::solana_program::sysvar::instructions::{
+    construct_instructions_data, BorrowedInstruction,
+};
+use anchor_lang::solana_program::{ed25519_program, sysvar};
+use anchor_lang::{system_program, Bumps, Discriminator};
+use anchor_spl::token::spl_token;
+
+use super::*;
+
+/// Clock for every test unless moved with `set_clock`, 80_000 seconds into its UTC day
+const NOW: i64 = 1_700_000_000;
+
+struct TestStubs;
+
+impl SyscallStubs for TestStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: CLOCK.with(|clock| clock.get()),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(fields.concat()));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &anchor_lang::solana_program::instruction::Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> std::result::Result<(), ProgramError> {
+        INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+        if let Some(hook) = CPI_HOOK.with(|hook| hook.borrow_mut().take()) {
+            hook();
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Data logged by `emit!` on this test's thread
+    static EVENTS: std::cell::RefCell<Vec<Vec<u8>>> = const { std::cell::RefCell::new(Vec::new()) };
+
+    /// Unix time seen by `Clock::get` on this test's thread
+    static CLOCK: std::cell::Cell<i64> = const { std::cell::Cell::new(NOW) };
+
+    /// Run once by the next CPI on this test's thread, standing in for a
+    /// transfer hook that calls back into the program
+    static CPI_HOOK: RefCell<Option<Box<dyn FnOnce()>>> = const { RefCell::new(None) };
+
+    /// Every CPI made so far on this test's thread, in order
+    static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+}
+
+fn set_clock(unix_timestamp: i64) {
+    CLOCK.with(|clock| clock.set(unix_timestamp));
+}
+
+fn set_cpi_hook(hook: impl FnOnce() + 'static) {
+    CPI_HOOK.with(|slot| *slot.borrow_mut() = Some(Box::new(hook)));
+}
+
+/// The SystemInstruction variant index of each System Program CPI so far
+fn system_instructions_invoked() -> Vec<u32> {
+    INVOKED.with(|invoked| {
+        invoked
+            .borrow()
+            .iter()
+            .filter(|ix| ix.program_id == system_program::ID)
+            .map(|ix| u32::from_le_bytes(ix.data[..4].try_into().unwrap()))
+            .collect()
+    })
+}
+
+/// Every `E` emitted so far on this test's thread, in order
+fn emitted<E: Discriminator + AnchorDeserialize>() -> Vec<E> {
+    EVENTS.with(|events| {
+        events
+            .borrow()
+            .iter()
+            .filter_map(|data| data.strip_prefix(E::DISCRIMINATOR))
+            .map(|mut body| E::deserialize(&mut body).unwrap())
+            .collect()
+    })
+}
+
+fn install_stubs() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        set_syscall_stubs(Box::new(TestStubs));
+    });
+}
+
+/// AccountInfo backed by leaked buffers laid out like the runtime's input:
+/// the original data length in the 4 bytes before the key and the current
+/// length in the 8 bytes before the data, which AccountInfo::realloc uses
+fn account_info(
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+) -> AccountInfo<'static> {
+    let key_buf = Box::leak(Box::new([0u8; 36]));
+    key_buf[..4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    key_buf[4..].copy_from_slice(key.as_ref());
+    let key = unsafe { &*(key_buf[4..].as_ptr() as *const Pubkey) };
+
+    let words = Box::leak(vec![0u64; 1 + data.len().div_ceil(8)].into_boxed_slice());
+    words[0] = data.len() as u64;
+    let data_buf = unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr().add(1) as *mut u8, data.len()) };
+    data_buf.copy_from_slice(&data);
+
+    AccountInfo::new(
+        key,
+        is_signer,
+        is_writable,
+        Box::leak(Box::new(lamports)),
+        data_buf,
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )
+}
+
+fn leak(infos: Vec<AccountInfo<'static>>) -> &'static [AccountInfo<'static>] {
+    infos.leak()
+}
+
+fn signer(key: Pubkey) -> AccountInfo<'static> {
+    account_info(key, system_program::ID, 1_000_000_000, vec![], true, false)
+}
+
+/// Placeholder for an optional account that isn't passed
+fn absent() -> AccountInfo<'static> {
+    account_info(crate::ID, Pubkey::default(), 0, vec![], false, false)
+}
+
+/// Serialize a program account with its discriminator, padded to `space`
+fn program_account<T: AccountSerialize>(key: Pubkey, value: &T, space: usize) -> AccountInfo<'static> {
+    let mut data = Vec::with_capacity(space);
+    value.try_serialize(&mut data).unwrap();
+    data.resize(space.max(data.len()), 0);
+    let lamports = Rent::default().minimum_balance(data.len());
+    account_info(key, crate::ID, lamports, data, false, true)
+}
+
+fn read_account<T: AccountDeserialize>(info: &AccountInfo) -> T {
+    T::try_deserialize(&mut &info.data.borrow()[..]).unwrap()
+}
+
+fn write_account<T: AccountSerialize>(info: &AccountInfo, value: &T) {
+    value.try_serialize(&mut &mut info.data.borrow_mut()[..]).unwrap();
+}
+
+fn token_account(key: Pubkey, owner: Pubkey, mint: Pubkey, amount: u64) -> AccountInfo<'static> {
+    let state = spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    };
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(state, &mut data).unwrap();
+    account_info(key, spl_token::ID, 2_039_280, data, false, true)
+}
+
+/// The token program; CPIs into it succeed without effect under the stubs
+fn token_program() -> AccountInfo<'static> {
+    let mut info = account_info(spl_token::ID, Pubkey::default(), 1, vec![], false, false);
+    info.executable = true;
+    info
+}
+
+const WALLET_SPACE: usize = 8 + std::mem::size_of::<SmartWallet>() + 256;
+
+fn test_wallet(owner: Pubkey, authority: Pubkey) -> SmartWallet {
+    SmartWallet {
+        owner,
+        authority,
+        daily_limit: 1_000,
+        last_reset_day: NOW / 86400,
+        max_exec_payload_len: MAX_EXEC_PAYLOAD_LEN,
+        recovery_session_grace: DEFAULT_RECOVERY_SESSION_GRACE,
+        max_pending_items: DEFAULT_MAX_PENDING_ITEMS,
+        freeze_threshold: 1,
+        is_initialized: true,
+        ..SmartWallet::default()
+    }
+}
+
+fn guardian_account(
+    wallet: &Pubkey,
+    index: u8,
+    pubkey: Pubkey,
+    guardian_type: GuardianType,
+    weight: u8,
+) -> AccountInfo<'static> {
+    let (key, bump) = Pubkey::find_program_address(&[b"guardian", wallet.as_ref(), &[index]], &crate::ID);
+    let guardian = Guardian {
+        wallet: *wallet,
+        pubkey,
+        guardian_type,
+        weight,
+        added_at: NOW - 86400,
+        expires_at: 0,
+        last_action_at: 0,
+        action_count: 0,
+        identity_commitment: None,
+        pending_reactivation: false,
+        is_active: true,
+        can_approve_recovery: true,
+        bump,
+    };
+    program_account(key, &guardian, 8 + std::mem::size_of::<Guardian>())
+}
+
+/// `count` `[guardian_pda, signer]` pairs for the wallet's guardian co-signers
+fn co_signers(wallet: &Pubkey, count: u8) -> &'static [AccountInfo<'static>] {
+    let pairs = (0..count).flat_map(|index| {
+        let pubkey = Pubkey::new_unique();
+        [guardian_account(wallet, index, pubkey, GuardianType::Email, 1), signer(pubkey)]
+    });
+    leak(pairs.collect())
+}
+
+thread_local! {
+    /// The program-wide GlobalConfig PDA, shared by every instruction on this
+    /// test's thread the way the singleton is on-chain
+    static GLOBAL_CONFIG: AccountInfo<'static> = {
+        let (key, bump) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+        let config = GlobalConfig {
+            admin: Pubkey::new_unique(),
+            paused: false,
+            fee_bps: 0,
+            fee_destination: Pubkey::default(),
+            price_program: Pubkey::default(),
+            bump,
+        };
+        program_account(key, &config, 8 + std::mem::size_of::<GlobalConfig>())
+    };
+}
+
+fn global_config() -> AccountInfo<'static> {
+    GLOBAL_CONFIG.with(|config| config.clone())
+}
+
+fn assert_error<T: std::fmt::Debug>(result: Result<T>, expected: WalletError) {
+    let expected: Error = expected.into();
+    match result {
+        Err(err) => assert_eq!(err, expected),
+        Ok(value) => panic!("expected {expected:?}, got Ok({value:?})"),
+    }
+}
+
+/// Run `handler` on `T` built from `infos` like the runtime would, writing
+/// the accounts back afterwards
+fn run<T, R>(
+    infos: &'static [AccountInfo<'static>],
+    remaining: &'static [AccountInfo<'static>],
+    handler: impl FnOnce(Context<'_, '_, 'static, 'static, T>) -> Result<R>,
+) -> Result<R>
+where
+    T: Accounts<'static, T::Bumps> + Bumps + AccountsExit<'static>,
+    T::Bumps: Default,
+{
+    run_with_args(infos, remaining, (), handler)
+}
+
+/// `run` for contexts whose constraints read `#[instruction(..)]` arguments
+fn run_with_args<T, R>(
+    infos: &'static [AccountInfo<'static>],
+    remaining: &'static [AccountInfo<'static>],
+    args: impl AnchorSerialize,
+    handler: impl FnOnce(Context<'_, '_, 'static, 'static, T>) -> Result<R>,
+) -> Result<R>
+where
+    T: Accounts<'static, T::Bumps> + Bumps + AccountsExit<'static>,
+    T::Bumps: Default,
+{
+    install_stubs();
+    let ix_data = args.try_to_vec().unwrap();
+    let mut bumps = T::Bumps::default();
+    let mut reallocs = BTreeSet::new();
+    let mut cursor = infos;
+    let mut accounts = T::try_accounts(&crate::ID, &mut cursor, &ix_data, &mut bumps, &mut reallocs)?;
+    let result = handler(Context::new(&crate::ID, &mut accounts, remaining, bumps))?;
+    accounts.exit(&crate::ID)?;
+    Ok(result)
+}
+
+// ============ Transfer window ============
+
+/// `[wallet, global_config, authority]` for an authority-signed config update
+fn update_config_accounts(wallet: &SmartWallet) -> &'static [AccountInfo<'static>] {
+    leak(vec![
+        program_account(Pubkey::new_unique(), wallet, WALLET_SPACE),
+        global_config(),
+        signer(wallet.authority),
+    ])
+}
+
+#[test]
+fn transfer_window_allows_inside_and_rejects_outside() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let second_of_day = (NOW % 86400) as u32;
+    assert!(wallet.within_transfer_window(NOW));
+
+    wallet.allowed_start_second = second_of_day - 60;
+    wallet.allowed_end_second = second_of_day + 60;
+    assert!(wallet.within_transfer_window(NOW));
+
+    wallet.allowed_start_second = second_of_day + 1;
+    wallet.allowed_end_second = second_of_day + 60;
+    assert!(!wallet.within_transfer_window(NOW));
+
+    // The end of the window is exclusive
+    wallet.allowed_start_second = second_of_day - 60;
+    wallet.allowed_end_second = second_of_day;
+    assert!(!wallet.within_transfer_window(NOW));
+}
+
+#[test]
+fn transfer_window_wraps_past_midnight() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let second_of_day = (NOW % 86400) as u32;
+    let midnight = NOW - second_of_day as i64;
+
+    wallet.allowed_start_second = second_of_day + 60;
+    wallet.allowed_end_second = second_of_day - 60;
+    assert!(!wallet.within_transfer_window(NOW));
+
+    // 22:00-06:00 admits both sides of midnight
+    wallet.allowed_start_second = 22 * 3600;
+    wallet.allowed_end_second = 6 * 3600;
+    assert!(wallet.within_transfer_window(midnight + 23 * 3600));
+    assert!(wallet.within_transfer_window(midnight + 3600));
+    assert!(!wallet.within_transfer_window(midnight + 12 * 3600));
+}
+
+#[test]
+fn transfer_window_bounds_must_fit_in_a_day() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+
+    let result = run::<UpdateConfig, _>(update_config_accounts(&wallet), &[], |ctx| {
+        leet_wallet::set_transfer_window(ctx, 0, 86400)
+    });
+    assert_error(result, WalletError::InvalidTransferWindow);
+
+    let infos = update_config_accounts(&wallet);
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_transfer_window(ctx, 9 * 3600, 17 * 3600)).unwrap();
+    let wallet: SmartWallet = read_account(&infos[0]);
+    assert_eq!((wallet.allowed_start_second, wallet.allowed_end_second), (9 * 3600, 17 * 3600));
+}
+
+#[test]
+fn empty_transfer_window_is_rejected_but_zero_turns_it_off() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let infos = update_config_accounts(&wallet);
+    let set_window = |start, end| {
+        run::<UpdateConfig, _>(infos, &[], move |ctx| leet_wallet::set_transfer_window(ctx, start, end))
+    };
+
+    assert_error(set_window(3600, 3600), WalletError::InvalidTransferWindow);
+    set_window(0, 0).unwrap();
+    assert!(read_account::<SmartWallet>(&infos[0]).within_transfer_window(NOW));
+}
+
+// ============ Transfers ============
+
+/// The wallet at its PDA, as transfer instructions expect it
+fn wallet_pda(mut wallet: SmartWallet) -> AccountInfo<'static> {
+    wallet.wallet_id = Pubkey::new_unique().to_bytes();
+    let (key, bump) = crate::wallet_pda(&wallet.wallet_id);
+    wallet.bump = bump;
+    program_account(key, &wallet, WALLET_SPACE)
+}
+
+/// `[wallet, global_config, from, to, category_limit, frozen_mint, spend_window,
+/// authority, notify_program, price_quote, mint, fee_token_account, token_program]` for
+/// transfer_spl of `mint` in category 0, which has no budget
+fn transfer_accounts(
+    wallet_info: &AccountInfo<'static>,
+    mint: Pubkey,
+    balance: u64,
+    frozen_mint: AccountInfo<'static>,
+    spend_window: AccountInfo<'static>,
+) -> &'static [AccountInfo<'static>] {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let (category_key, _) = Pubkey::find_program_address(&[b"cat", wallet_info.key.as_ref(), &[0]], &crate::ID);
+    leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, balance),
+        token_account(Pubkey::new_unique(), Pubkey::new_unique(), mint, 0),
+        account_info(category_key, system_program::ID, 0, vec![], false, true),
+        frozen_mint,
+        spend_window,
+        signer(wallet.authority),
+        absent(),
+        absent(),
+        absent(),
+        absent(),
+        token_program(),
+    ])
+}
+
+fn frozen_mint_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"fmint", wallet.as_ref(), mint.as_ref()], &crate::ID).0
+}
+
+/// The empty FrozenMint PDA of a mint that isn't frozen
+fn unfrozen_mint(wallet: &Pubkey, mint: &Pubkey) -> AccountInfo<'static> {
+    account_info(frozen_mint_address(wallet, mint), system_program::ID, 0, vec![], false, false)
+}
+
+fn transfer_spl(wallet_info: &AccountInfo<'static>, amount: u64) -> Result<()> {
+    transfer_spl_from_balance(wallet_info, 1_000_000, amount)
+}
+
+/// transfer_spl out of a source token account holding `balance`
+fn transfer_spl_from_balance(wallet_info: &AccountInfo<'static>, balance: u64, amount: u64) -> Result<()> {
+    transfer_spl_with_reference(wallet_info, balance, amount, None, None)
+}
+
+fn transfer_spl_with_reference(
+    wallet_info: &AccountInfo<'static>,
+    balance: u64,
+    amount: u64,
+    reference: Option<[u8; 32]>,
+    idempotency_key: Option<[u8; 16]>,
+) -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let infos = transfer_accounts(wallet_info, mint, balance, unfrozen_mint(wallet_info.key, &mint), absent());
+    run_with_args::<TransferSPL, _>(infos, &[], (amount, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, amount, 0, reference, idempotency_key)
+    })
+}
+
+/// transfer_spl charging `spend_window` as well as the calendar day
+fn transfer_spl_in_window(
+    wallet_info: &AccountInfo<'static>,
+    spend_window: &AccountInfo<'static>,
+    amount: u64,
+) -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let frozen_mint = unfrozen_mint(wallet_info.key, &mint);
+    let infos = transfer_accounts(wallet_info, mint, 1_000_000, frozen_mint, spend_window.clone());
+    run_with_args::<TransferSPL, _>(infos, &[], (amount, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, amount, 0, None, None)
+    })
+}
+
+#[test]
+fn reentrant_transfer_sees_the_outer_spend() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let reentrant = std::rc::Rc::new(RefCell::new(None));
+    let hook_wallet = wallet_info.clone();
+    let hook_result = reentrant.clone();
+    set_cpi_hook(move || *hook_result.borrow_mut() = Some(transfer_spl(&hook_wallet, 600)));
+
+    transfer_spl(&wallet_info, 600).unwrap();
+
+    assert_error(reentrant.take().unwrap(), WalletError::DailyLimitExceeded);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_spent, 600);
+}
+
+#[test]
+fn transfer_inside_the_cooldown_is_rejected() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.min_transfer_interval = 60;
+    wallet.last_transfer_at = NOW - 59;
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(transfer_spl(&wallet_info, 10), WalletError::TransferTooSoon);
+}
+
+#[test]
+fn transfer_at_the_cooldown_boundary_succeeds() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.min_transfer_interval = 60;
+    wallet.last_transfer_at = NOW - 60;
+    let wallet_info = wallet_pda(wallet);
+
+    transfer_spl(&wallet_info, 10).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.last_transfer_at, NOW);
+    assert_eq!(wallet.daily_spent, 10);
+    // The next transfer waits out the cooldown again
+    assert_error(transfer_spl(&wallet_info, 10), WalletError::TransferTooSoon);
+}
+
+#[test]
+fn zero_cooldown_allows_back_to_back_transfers() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+
+    transfer_spl(&wallet_info, 10).unwrap();
+    transfer_spl(&wallet_info, 10).unwrap();
+}
+
+#[test]
+fn negative_cooldown_is_rejected() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+
+    let result = run::<UpdateConfig, _>(update_config_accounts(&wallet), &[], |ctx| {
+        leet_wallet::set_transfer_cooldown(ctx, -1)
+    });
+    assert_error(result, WalletError::InvalidTransferCooldown);
+}
+
+#[test]
+fn vacation_lock_covers_its_start_but_not_its_end() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lock_from = NOW;
+    wallet.lock_until = NOW + 86400;
+    let wallet_info = wallet_pda(wallet);
+    assert_error(transfer_spl(&wallet_info, 10), WalletError::VacationLock);
+
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lock_from = NOW - 86400;
+    wallet.lock_until = NOW;
+    let wallet_info = wallet_pda(wallet);
+    transfer_spl(&wallet_info, 10).unwrap();
+}
+
+#[test]
+fn vacation_lock_clears_itself_once_passed() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lock_from = NOW + 1;
+    wallet.lock_until = NOW + 86400;
+
+    assert!(!wallet.is_vacation_locked(NOW));
+    assert!(wallet.is_vacation_locked(NOW + 1));
+    assert!(wallet.is_vacation_locked(NOW + 86399));
+    assert!(!wallet.is_vacation_locked(NOW + 86400));
+}
+
+#[test]
+fn vacation_lock_must_end_after_it_starts() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+
+    let result = run::<UpdateConfig, _>(update_config_accounts(&wallet), &[], |ctx| {
+        leet_wallet::set_vacation_lock(ctx, NOW, NOW - 1)
+    });
+    assert_error(result, WalletError::InvalidVacationLock);
+}
+
+#[test]
+fn percentage_cap_moves_with_the_balance() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_limit_bps = 1_000;
+    let wallet_info = wallet_pda(wallet);
+
+    // 10% of 5_000 is below the absolute 1_000
+    transfer_spl_from_balance(&wallet_info, 5_000, 400).unwrap();
+    assert_error(transfer_spl_from_balance(&wallet_info, 5_000, 101), WalletError::DailyLimitExceeded);
+
+    // A smaller balance shrinks the cap below what was already spent
+    assert_error(transfer_spl_from_balance(&wallet_info, 3_000, 1), WalletError::DailyLimitExceeded);
+
+    // A larger balance raises it, up to the absolute limit
+    transfer_spl_from_balance(&wallet_info, 9_000, 500).unwrap();
+    assert_error(transfer_spl_from_balance(&wallet_info, 50_000, 101), WalletError::DailyLimitExceeded);
+}
+
+#[test]
+fn transfer_from_a_zeroed_wallet_is_rejected() {
+    let wallet_info = wallet_pda(SmartWallet::default());
+
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::WalletNotInitialized);
+}
+
+#[test]
+fn transfers_drain_to_the_lifetime_cap() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_limit = 10_000;
+    wallet.lifetime_limit = Some(1_500);
+    let wallet_info = wallet_pda(wallet);
+
+    transfer_spl(&wallet_info, 1_000).unwrap();
+    assert_error(transfer_spl(&wallet_info, 501), WalletError::LifetimeLimitExceeded);
+    transfer_spl(&wallet_info, 500).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).lifetime_spent, 1_500);
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::LifetimeLimitExceeded);
+}
+
+#[test]
+fn lifetime_limit_can_only_be_lowered() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lifetime_limit = Some(1_500);
+    let infos = update_config_accounts(&wallet);
+
+    let result = run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_lifetime_limit(ctx, 1_501));
+    assert_error(result, WalletError::LifetimeLimitIncrease);
+
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_lifetime_limit(ctx, 1_000)).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).lifetime_limit, Some(1_000));
+}
+
+/// An empty SpendWindow PDA for `wallet_info`
+fn spend_window(wallet_info: &AccountInfo<'static>) -> AccountInfo<'static> {
+    let (window_key, bump) = Pubkey::find_program_address(&[b"spend_window", wallet_info.key.as_ref()], &crate::ID);
+    let window = SpendWindow {
+        wallet: *wallet_info.key,
+        bump,
+        ..SpendWindow::default()
+    };
+    program_account(window_key, &window, 8 + std::mem::size_of::<SpendWindow>())
+}
+
+#[test]
+fn trailing_window_blocks_a_double_spend_across_midnight() {
+    let midnight = (NOW / 86400 + 1) * 86400;
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.spend_window_seconds = 86400;
+    wallet.last_reset_day = midnight / 86400 - 1;
+    let wallet_info = wallet_pda(wallet);
+    let window_info = spend_window(&wallet_info);
+
+    set_clock(midnight - 60);
+    transfer_spl_in_window(&wallet_info, &window_info, 1_000).unwrap();
+
+    // The calendar day has rolled over, but the trailing window hasn't
+    set_clock(midnight + 60);
+    let result = transfer_spl_in_window(&wallet_info, &window_info, 1_000);
+    assert_error(result, WalletError::DailyLimitExceeded);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).spent_today(midnight + 60), 0);
+}
+
+// ============ Signed operations ============
+
+/// Instructions sysvar holding one self-contained Ed25519 instruction that
+/// verified `signature` by `pubkey` over `message`
+fn ed25519_instructions(pubkey: &Pubkey, message: &[u8], signature: &[u8; 64]) -> AccountInfo<'static> {
+    instructions_sysvar(&[ed25519_instruction_data(pubkey, message, signature)])
+}
+
+fn ed25519_instruction_data(pubkey: &Pubkey, message: &[u8], signature: &[u8; 64]) -> Vec<u8> {
+    const HEADER: usize = 2 + 14;
+    let (key_offset, sig_offset, msg_offset) = (HEADER, HEADER + 32, HEADER + 96);
+    let this_ix = u16::MAX as usize;
+    let offsets = [sig_offset, this_ix, key_offset, this_ix, msg_offset, message.len(), this_ix];
+
+    let mut data = vec![1, 0];
+    for offset in offsets {
+        data.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    data
+}
+
+/// Instructions sysvar holding one Ed25519 instruction per entry of `ed25519_data`
+fn instructions_sysvar(ed25519_data: &[Vec<u8>]) -> AccountInfo<'static> {
+    let instructions: Vec<_> = ed25519_data
+        .iter()
+        .map(|data| BorrowedInstruction { program_id: &ed25519_program::ID, accounts: vec![], data })
+        .collect();
+    let sysvar_data = construct_instructions_data(&instructions);
+    account_info(sysvar::instructions::ID, sysvar::ID, 1, sysvar_data, false, false)
+}
+
+/// execute_transaction over `b"payload"`, with `signature` over `message`
+/// verified in the same transaction: by the group key in MPC mode, otherwise
+/// by a guardian
+fn execute_signed(
+    wallet_info: &AccountInfo<'static>,
+    message: [u8; 32],
+    signature: [u8; 64],
+    signatures: ExecSignatures,
+    nonce: u64,
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let guardian = Pubkey::new_unique();
+    let signing_key = if wallet.mpc_mode { wallet.authority } else { guardian };
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(wallet.authority),
+        ed25519_instructions(&signing_key, &message, &signature),
+    ]);
+    let guardians = leak(vec![guardian_account(wallet_info.key, 0, guardian, GuardianType::Wallet, 1)]);
+    run::<ExecuteTransaction, _>(infos, guardians, |ctx| {
+        leet_wallet::execute_transaction(ctx, b"payload".to_vec(), signatures, nonce)
+    })
+}
+
+fn execute_transaction(
+    wallet_info: &AccountInfo<'static>,
+    message: [u8; 32],
+    nonce: u64,
+) -> Result<()> {
+    let signature = [7u8; 64];
+    execute_signed(wallet_info, message, signature, ExecSignatures::Individual(vec![signature]), nonce)
+}
+
+fn hex<const N: usize>(digits: &str) -> [u8; N] {
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(&digits[at..at + 2], 16).unwrap())
+        .collect();
+    bytes.try_into().unwrap()
+}
+
+#[test]
+fn execute_accepts_a_signature_over_its_own_operation() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let message = operation_message(OperationType::Execute, wallet_info.key, 0, b"payload");
+
+    execute_transaction(&wallet_info, message, 0).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 1);
+    // The consumed nonce can't be replayed
+    assert_error(execute_transaction(&wallet_info, message, 0), WalletError::ExpiredOrReusedNonce);
+}
+
+#[test]
+fn strict_nonces_reject_a_gap() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let message = operation_message(OperationType::Execute, wallet_info.key, 1, b"payload");
+
+    assert_error(execute_transaction(&wallet_info, message, 1), WalletError::ExpiredOrReusedNonce);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 0);
+}
+
+#[test]
+fn nonce_window_accepts_out_of_order_nonces_once() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.exec_nonce_window = 4;
+    let wallet_info = wallet_pda(wallet);
+    let execute = |nonce| {
+        let message = operation_message(OperationType::Execute, wallet_info.key, nonce, b"payload");
+        execute_transaction(&wallet_info, message, nonce)
+    };
+
+    execute(2).unwrap();
+    assert_error(execute(2), WalletError::ExpiredOrReusedNonce);
+    assert_error(execute(4), WalletError::ExpiredOrReusedNonce);
+    execute(0).unwrap();
+    execute(1).unwrap();
+
+    // 0..=2 are consumed, so the window now starts at 3
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 3);
+    assert_error(execute(1), WalletError::ExpiredOrReusedNonce);
+}
+
+#[test]
+fn transfer_signature_is_not_replayable_as_execute() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let message = operation_message(OperationType::Transfer, wallet_info.key, 0, b"payload");
+
+    assert_error(execute_transaction(&wallet_info, message, 0), WalletError::InsufficientSignatures);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 0);
+}
+
+#[test]
+fn repeated_signature_counts_once_toward_the_guardian_threshold() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = 2;
+    wallet.guardian_count = 2;
+    let wallet_info = wallet_pda(wallet);
+    let message = operation_message(OperationType::Execute, wallet_info.key, 0, b"payload");
+    let signature = [7u8; 64];
+
+    let repeated = ExecSignatures::Individual(vec![signature, signature]);
+    assert_error(
+        execute_signed(&wallet_info, message, signature, repeated, 0),
+        WalletError::InsufficientSignatures,
+    );
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 0);
+}
+
+/// FROST(Ed25519, SHA-512) aggregates into a plain Ed25519 signature under
+/// the group key. The group key here is the RFC 8032 test 1 key, and the
+/// signature was produced independently over the expected operation message.
+#[test]
+fn mpc_mode_accepts_a_known_aggregate_signature() {
+    let group_key = Pubkey::new_from_array(hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"));
+    let message: [u8; 32] = hex("cd74883d3aa52ee751c2cb77be8189e07c20ed22dee8a7442fa9d1aa3b2a6c18");
+    let signature: [u8; 64] = hex(
+        "04ff141a560f94ec311ee337cd210c8f646b28a9eabff31e546b237043a92e25\
+         9e72586d8e7f4769b0846ce1bec7acdd1af521044eff4f6cad3699d010a8a203",
+    );
+
+    let mut wallet = test_wallet(Pubkey::new_unique(), group_key);
+    wallet.mpc_mode = true;
+    let wallet_info = program_account(Pubkey::new_from_array([3; 32]), &wallet, WALLET_SPACE);
+    assert_eq!(operation_message(OperationType::Execute, wallet_info.key, 0, b"payload"), message);
+
+    // Individual signatures are refused once the wallet is in MPC mode
+    assert_error(
+        execute_signed(&wallet_info, message, signature, ExecSignatures::Individual(vec![signature]), 0),
+        WalletError::InvalidSignature,
+    );
+    let mut tampered = signature;
+    tampered[63] ^= 1;
+    assert_error(
+        execute_signed(&wallet_info, message, signature, ExecSignatures::Aggregated(tampered), 0),
+        WalletError::InvalidSignature,
+    );
+
+    execute_signed(&wallet_info, message, signature, ExecSignatures::Aggregated(signature), 0).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 1);
+}
+
+#[test]
+fn switching_mpc_mode_needs_co_signers_once_guardians_exist() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let set_mpc = |infos, co_signers| {
+        run::<UpdateConfig, _>(infos, co_signers, |ctx| leet_wallet::set_mpc_mode(ctx, true))
+    };
+
+    // Without guardians there is nobody to co-sign
+    let infos = update_config_accounts(&wallet);
+    set_mpc(infos, &[]).unwrap();
+    assert!(read_account::<SmartWallet>(&infos[0]).mpc_mode);
+
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    let infos = update_config_accounts(&wallet);
+    assert_error(set_mpc(infos, co_signers(infos[0].key, 1)), WalletError::InsufficientApprovals);
+    set_mpc(infos, co_signers(infos[0].key, 2)).unwrap();
+}
+
+#[test]
+fn executions_stop_at_the_daily_cap_until_the_next_day() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.max_exec_daily_count = 2;
+    wallet.exec_count_day = NOW / 86400;
+    let wallet_info = wallet_pda(wallet);
+    let execute = |nonce| {
+        let message = operation_message(OperationType::Execute, wallet_info.key, nonce, b"payload");
+        execute_transaction(&wallet_info, message, nonce)
+    };
+
+    execute(0).unwrap();
+    execute(1).unwrap();
+    assert_error(execute(2), WalletError::ExecRateLimited);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.exec_count_day -= 1;
+    write_account(&wallet_info, &wallet);
+    execute(2).unwrap();
+}
+
+#[test]
+fn transfer_reference_surfaces_in_the_event() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.emit_events = true;
+    let wallet_info = wallet_pda(wallet);
+
+    transfer_spl_with_reference(&wallet_info, 1_000_000, 10, Some([9; 32]), None).unwrap();
+    transfer_spl(&wallet_info, 10).unwrap();
+
+    let references: Vec<_> = emitted::<TransferExecuted>().iter().map(|event| event.reference).collect();
+    assert_eq!(references, [[9; 32], [0; 32]]);
+}
+
+#[test]
+fn transfer_event_names_the_mint_and_its_decimals() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.emit_events = true;
+    let wallet_info = wallet_pda(wallet);
+    let mint_info = mint_account(6, 1_000_000);
+    let mint = *mint_info.key;
+    let mut infos = transfer_accounts(&wallet_info, mint, 1_000, unfrozen_mint(wallet_info.key, &mint), absent()).to_vec();
+    infos[10] = mint_info;
+
+    run_with_args::<TransferSPL, _>(leak(infos), &[], (10u64, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, 10, 0, None, None)
+    })
+    .unwrap();
+    transfer_spl(&wallet_info, 10).unwrap();
+
+    let events = emitted::<TransferExecuted>();
+    assert_eq!((events[0].mint, events[0].decimals), (mint, Some(6)));
+    // Without the mint account the decimals are left out rather than required
+    assert_eq!(events[1].decimals, None);
+}
+
+// ============ Recovery ============
+
+thread_local! {
+    /// Each wallet's RecoverySession PDA on this test's thread, so later
+    /// recovery instructions see the session an earlier one opened
+    static RECOVERY_SESSIONS: RefCell<HashMap<Pubkey, AccountInfo<'static>>> = RefCell::new(HashMap::new());
+}
+
+/// The RecoverySession PDA of `wallet`, uninitialized until a recovery opens
+/// it and again once the session is closed
+fn recovery_session(wallet: &Pubkey) -> AccountInfo<'static> {
+    RECOVERY_SESSIONS.with(|sessions| {
+        let (key, _) = Pubkey::find_program_address(&[b"recovery", wallet.as_ref()], &crate::ID);
+        let fresh = || uninitialized_account(key, 8 + std::mem::size_of::<RecoverySession>());
+        let mut sessions = sessions.borrow_mut();
+        let session = sessions.entry(*wallet).or_insert_with(fresh);
+        if session.lamports() == 0 {
+            *session = fresh();
+        }
+        session.clone()
+    })
+}
+
+fn pending_recovery(wallet_info: &AccountInfo<'static>) -> RecoverySession {
+    read_account(&recovery_session(wallet_info.key))
+}
+
+/// A wallet with a recovery waiting on `threshold` approval weight, past its delay
+fn pending_recovery_wallet(threshold: u8) -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = threshold;
+    wallet.recovery_threshold = threshold;
+    wallet.guardian_count = threshold;
+    wallet.total_guardian_weight = threshold as u16;
+    wallet.recovery_pending = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    let (_, bump) = Pubkey::find_program_address(&[b"recovery", wallet_info.key.as_ref()], &crate::ID);
+    let session = RecoverySession {
+        wallet: *wallet_info.key,
+        new_authority: Pubkey::new_unique(),
+        new_authority_is_group: false,
+        new_owner: None,
+        challenge: [0; 32],
+        initiated_at: NOW - 86400,
+        expires_at: NOW - 86400 + wallet.recovery_delay + wallet.recovery_session_grace,
+        approvals: 0,
+        approvers: [Pubkey::default(); MAX_GUARDIANS],
+        approved_weight: 0,
+        approved_types: 0,
+        type_approvals: [0; GuardianType::COUNT],
+        payer: Pubkey::new_unique(),
+        bump,
+    };
+    write_account(&recovery_session(wallet_info.key), &session);
+    wallet_info
+}
+
+fn approve_recovery_as(
+    wallet_info: &AccountInfo<'static>,
+    index: u8,
+    guardian_type: GuardianType,
+    weight: u8,
+) -> Result<()> {
+    let pubkey = Pubkey::new_unique();
+    let guardian_info = guardian_account(wallet_info.key, index, pubkey, guardian_type, weight);
+    approve_recovery_signing(wallet_info, guardian_info, pubkey, pending_recovery(wallet_info).challenge)
+}
+
+/// approve_recovery by `pubkey`, whose Ed25519 signature covers `challenge`
+fn approve_recovery_signing(
+    wallet_info: &AccountInfo<'static>,
+    guardian_info: AccountInfo<'static>,
+    pubkey: Pubkey,
+    challenge: [u8; 32],
+) -> Result<()> {
+    let signature = [5u8; 64];
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        guardian_info,
+        recovery_session(wallet_info.key),
+        signer(pubkey),
+        ed25519_instructions(&pubkey, &challenge, &signature),
+        absent(),
+    ]);
+    run::<ApproveRecovery, _>(infos, &[], |ctx| leet_wallet::approve_recovery(ctx, signature))
+}
+
+fn execute_recovery(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    execute_recovery_with(wallet_info, &[])
+}
+
+/// execute_recovery with guardian PDAs in remaining_accounts
+fn execute_recovery_with(
+    wallet_info: &AccountInfo<'static>,
+    guardians: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    run::<ExecuteRecovery, _>(execute_recovery_accounts(wallet_info, instructions_sysvar(&[])), guardians, |ctx| {
+        leet_wallet::execute_recovery(ctx, None)
+    })
+}
+
+/// ExecuteRecovery accounts, with the session's payer to take back its rent
+fn execute_recovery_accounts(
+    wallet_info: &AccountInfo<'static>,
+    instructions: AccountInfo<'static>,
+) -> &'static [AccountInfo<'static>] {
+    let payer = pending_recovery(wallet_info).payer;
+    leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        recovery_session(wallet_info.key),
+        account_info(payer, system_program::ID, 0, vec![], false, true),
+        absent(),
+        instructions,
+        absent(),
+    ])
+}
+
+/// execute_recovery with `aggregate`, alongside an Ed25519 instruction that
+/// verified `signature` by `group_key` over the pending challenge
+fn execute_recovery_aggregated(
+    wallet_info: &AccountInfo<'static>,
+    group_key: Pubkey,
+    signature: [u8; 64],
+    aggregate: [u8; 64],
+) -> Result<()> {
+    let challenge = pending_recovery(wallet_info).challenge;
+    let infos = execute_recovery_accounts(wallet_info, ed25519_instructions(&group_key, &challenge, &signature));
+    run::<ExecuteRecovery, _>(infos, &[], |ctx| leet_wallet::execute_recovery(ctx, Some(aggregate)))
+}
+
+fn initiate_recovery(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    let pubkey = Pubkey::new_unique();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1),
+        recovery_session(wallet_info.key),
+        signer(pubkey),
+        payer(),
+        system_program_account(),
+        absent(),
+    ]);
+    run::<InitiateRecovery, _>(infos, &[], |ctx| {
+        leet_wallet::initiate_recovery(ctx, Pubkey::new_unique(), None, false)
+    })
+}
+
+#[test]
+fn diversity_can_only_be_required_by_a_mixed_guardian_set() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    let infos = update_config_accounts(&wallet);
+    let set_diversity = |co_signers, required| {
+        run::<UpdateConfig, _>(infos, co_signers, |ctx| leet_wallet::set_guardian_diversity(ctx, required))
+    };
+    let co_signers_of = |types: [GuardianType; 2]| -> &'static [AccountInfo<'static>] {
+        let pairs = types.into_iter().enumerate().flat_map(|(index, guardian_type)| {
+            let pubkey = Pubkey::new_unique();
+            [guardian_account(infos[0].key, index as u8, pubkey, guardian_type, 1), signer(pubkey)]
+        });
+        leak(pairs.collect())
+    };
+
+    assert_error(set_diversity(co_signers(infos[0].key, 1), true), WalletError::InsufficientApprovals);
+    assert_error(
+        set_diversity(co_signers_of([GuardianType::Email, GuardianType::Email]), true),
+        WalletError::InsufficientGuardianDiversity,
+    );
+    set_diversity(co_signers_of([GuardianType::Email, GuardianType::Hardware]), true).unwrap();
+    assert!(read_account::<SmartWallet>(&infos[0]).require_diverse_guardians);
+
+    // Dropping the requirement still needs the quorum
+    assert_error(set_diversity(&[], false), WalletError::InsufficientApprovals);
+    set_diversity(co_signers(infos[0].key, 2), false).unwrap();
+    assert!(!read_account::<SmartWallet>(&infos[0]).require_diverse_guardians);
+}
+
+#[test]
+fn single_type_approvals_fail_the_diversity_requirement() {
+    let wallet_info = pending_recovery_wallet(2);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.require_diverse_guardians = true;
+    write_account(&wallet_info, &wallet);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Wallet, 1).unwrap();
+    approve_recovery_as(&wallet_info, 1, GuardianType::Wallet, 1).unwrap();
+
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientGuardianDiversity);
+}
+
+#[test]
+fn mixed_type_approvals_pass_the_diversity_requirement() {
+    let wallet_info = pending_recovery_wallet(2);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.require_diverse_guardians = true;
+    write_account(&wallet_info, &wallet);
+    let new_authority = pending_recovery(&wallet_info).new_authority;
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Wallet, 1).unwrap();
+    approve_recovery_as(&wallet_info, 1, GuardianType::Hardware, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).authority, new_authority);
+}
+
+#[test]
+fn single_type_approvals_suffice_without_the_requirement() {
+    let wallet_info = pending_recovery_wallet(2);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Wallet, 1).unwrap();
+    approve_recovery_as(&wallet_info, 1, GuardianType::Wallet, 1).unwrap();
+
+    execute_recovery(&wallet_info).unwrap();
+}
+
+#[test]
+fn one_heavy_guardian_meets_the_weight_threshold() {
+    let wallet_info = pending_recovery_wallet(2);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Institution, 2).unwrap();
+
+    execute_recovery(&wallet_info).unwrap();
+}
+
+#[test]
+fn approvals_short_of_the_weight_threshold_fail() {
+    let wallet_info = pending_recovery_wallet(3);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    approve_recovery_as(&wallet_info, 1, GuardianType::Email, 1).unwrap();
+
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+}
+
+#[test]
+fn guardian_cannot_replay_its_recovery_approval() {
+    let wallet_info = pending_recovery_wallet(2);
+    let pubkey = Pubkey::new_unique();
+    let guardian_info = guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1);
+    let challenge = pending_recovery(&wallet_info).challenge;
+
+    approve_recovery_signing(&wallet_info, guardian_info.clone(), pubkey, challenge).unwrap();
+    assert_error(
+        approve_recovery_signing(&wallet_info, guardian_info, pubkey, challenge),
+        WalletError::AlreadyApproved,
+    );
+
+    assert_eq!(pending_recovery(&wallet_info).approved_weight, 1);
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+}
+
+#[test]
+fn approvals_past_the_type_cap_add_no_weight() {
+    let wallet_info = pending_recovery_wallet(3);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.max_approvals_per_type = 2;
+    write_account(&wallet_info, &wallet);
+
+    for index in 0..3 {
+        approve_recovery_as(&wallet_info, index, GuardianType::Email, 1).unwrap();
+    }
+    let session = pending_recovery(&wallet_info);
+    assert_eq!((session.approvals, session.approved_weight), (3, 2));
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+
+    approve_recovery_as(&wallet_info, 3, GuardianType::Phone, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+}
+
+#[test]
+fn approval_weights_add_up_across_guardians() {
+    let wallet_info = pending_recovery_wallet(3);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    approve_recovery_as(&wallet_info, 1, GuardianType::Hardware, 2).unwrap();
+
+    assert_eq!(pending_recovery(&wallet_info).approved_weight, 3);
+    execute_recovery(&wallet_info).unwrap();
+}
+
+#[test]
+fn approval_must_sign_this_recoverys_challenge() {
+    let wallet_info = pending_recovery_wallet(1);
+    let mut session = pending_recovery(&wallet_info);
+    session.challenge = [4; 32];
+    write_account(&recovery_session(wallet_info.key), &session);
+    let pubkey = Pubkey::new_unique();
+    let guardian_info = guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1);
+
+    // A signature over another recovery's challenge doesn't count
+    let result = approve_recovery_signing(&wallet_info, guardian_info.clone(), pubkey, [3; 32]);
+    assert_error(result, WalletError::InvalidSignature);
+
+    approve_recovery_signing(&wallet_info, guardian_info, pubkey, [4; 32]).unwrap();
+    assert_eq!(pending_recovery(&wallet_info).approvals, 1);
+}
+
+#[test]
+fn recovery_rotates_the_owner_along_with_the_authority() {
+    let wallet_info = pending_recovery_wallet(1);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.emit_events = true;
+    wallet.owners = vec![wallet.owner];
+    write_account(&wallet_info, &wallet);
+    let new_owner = Pubkey::new_unique();
+    let mut session = pending_recovery(&wallet_info);
+    session.new_owner = Some(new_owner);
+    write_account(&recovery_session(wallet_info.key), &session);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+
+    let recovered: SmartWallet = read_account(&wallet_info);
+    assert_eq!(recovered.authority, session.new_authority);
+    assert_eq!((recovered.owner, recovered.owners), (new_owner, vec![new_owner]));
+    assert_eq!(emitted::<RecoveryExecuted>().len(), 1);
+    let transferred = emitted::<OwnershipTransferred>();
+    assert_eq!((transferred[0].previous_owner, transferred[0].new_owner), (wallet.owner, new_owner));
+}
+
+#[test]
+fn recovery_to_a_group_key_marks_the_authority_as_a_group() {
+    let wallet_info = pending_recovery_wallet(1);
+    let mut session = pending_recovery(&wallet_info);
+    session.new_authority_is_group = true;
+    write_account(&recovery_session(wallet_info.key), &session);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+
+    assert!(read_account::<SmartWallet>(&wallet_info).authority_is_group);
+    let executed = &emitted::<RecoveryExecuted>()[0];
+    assert!(executed.authority_is_group);
+    assert_eq!(executed.group_pubkey, Some(session.new_authority));
+
+    let owner = read_account::<SmartWallet>(&wallet_info).owner;
+    rotate_authority(&wallet_info, owner, Pubkey::new_unique()).unwrap();
+    assert!(!read_account::<SmartWallet>(&wallet_info).authority_is_group);
+    assert_eq!(emitted::<RecoveryExecuted>()[1].group_pubkey, None);
+}
+
+#[test]
+fn recovery_cannot_start_inside_the_post_recovery_cooldown() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.post_recovery_cooldown = 86400;
+    wallet.last_recovery_executed_at = NOW - 86399;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    assert_error(initiate_recovery(&wallet_info), WalletError::RecoveryCooldownActive);
+
+    wallet.last_recovery_executed_at = NOW - 86400;
+    write_account(&wallet_info, &wallet);
+    initiate_recovery(&wallet_info).unwrap();
+    assert!(read_account::<SmartWallet>(&wallet_info).recovery_pending);
+}
+
+#[test]
+fn frozen_wallet_queues_but_does_not_execute_recovery() {
+    let wallet_info = pending_recovery_wallet(1);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.is_frozen = true;
+    write_account(&wallet_info, &wallet);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    assert_error(execute_recovery(&wallet_info), WalletError::WalletFrozen);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.is_frozen = false;
+    write_account(&wallet_info, &wallet);
+    execute_recovery(&wallet_info).unwrap();
+    assert!(!read_account::<SmartWallet>(&wallet_info).recovery_pending);
+}
+
+// ============ Guardian expiry ============
+
+/// A guardian of `wallet_info` that lapsed an hour ago
+fn expired_guardian(wallet_info: &AccountInfo<'static>, pubkey: Pubkey) -> AccountInfo<'static> {
+    let info = guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1);
+    let mut guardian: Guardian = read_account(&info);
+    guardian.expires_at = NOW - 3600;
+    write_account(&info, &guardian);
+    info
+}
+
+fn prune_expired_guardian(wallet_info: &AccountInfo<'static>, guardian_info: &AccountInfo<'static>) -> Result<()> {
+    run::<PruneExpiredGuardian, _>(
+        leak(vec![wallet_info.clone(), global_config(), guardian_info.clone(), absent()]),
+        &[],
+        leet_wallet::prune_expired_guardian,
+    )
+}
+
+#[test]
+fn expired_guardian_approval_is_rejected() {
+    let wallet_info = pending_recovery_wallet(1);
+    let pubkey = Pubkey::new_unique();
+    let challenge = pending_recovery(&wallet_info).challenge;
+
+    let result = approve_recovery_signing(&wallet_info, expired_guardian(&wallet_info, pubkey), pubkey, challenge);
+    assert_error(result, WalletError::GuardianInactive);
+}
+
+#[test]
+fn pruning_closes_an_expired_guardian() {
+    let wallet_info = pending_recovery_wallet(1);
+    let guardian_info = expired_guardian(&wallet_info, Pubkey::new_unique());
+    let rent = guardian_info.lamports();
+    let wallet_lamports = wallet_info.lamports();
+
+    prune_expired_guardian(&wallet_info, &guardian_info).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).guardian_count, 0);
+    assert_eq!(guardian_info.lamports(), 0);
+    assert_eq!(wallet_info.lamports(), wallet_lamports + rent);
+}
+
+#[test]
+fn unexpired_guardian_cannot_be_pruned() {
+    let wallet_info = pending_recovery_wallet(1);
+    let guardian_info = guardian_account(wallet_info.key, 0, Pubkey::new_unique(), GuardianType::Email, 1);
+
+    assert_error(prune_expired_guardian(&wallet_info, &guardian_info), WalletError::GuardianNotExpired);
+}
+
+#[test]
+fn pruning_with_no_guardians_counted_fails_cleanly() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    let guardian_info = expired_guardian(&wallet_info, Pubkey::new_unique());
+
+    assert_error(prune_expired_guardian(&wallet_info, &guardian_info), WalletError::NoGuardiansToRemove);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).guardian_count, 0);
+}
+
+// ============ NFT transfers ============
+
+fn mint_account(decimals: u8, supply: u64) -> AccountInfo<'static> {
+    let state = spl_token::state::Mint {
+        supply,
+        decimals,
+        is_initialized: true,
+        ..spl_token::state::Mint::default()
+    };
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(state, &mut data).unwrap();
+    account_info(Pubkey::new_unique(), spl_token::ID, 1_461_600, data, false, false)
+}
+
+/// A zeroed account at `key` for an `init` constraint to take over; system
+/// program CPIs are no-ops under the stubs, so it is pre-funded and pre-sized
+fn uninitialized_account(key: Pubkey, space: usize) -> AccountInfo<'static> {
+    let lamports = Rent::default().minimum_balance(space);
+    account_info(key, crate::ID, lamports, vec![0; space], false, true)
+}
+
+fn payer() -> AccountInfo<'static> {
+    account_info(Pubkey::new_unique(), system_program::ID, 1_000_000_000, vec![], true, true)
+}
+
+fn system_program_account() -> AccountInfo<'static> {
+    let mut info = account_info(system_program::ID, Pubkey::default(), 1, vec![], false, false);
+    info.executable = true;
+    info
+}
+
+/// The SoulboundMint PDA for `mint`, created through mark_soulbound when `marked`
+fn soulbound_account(
+    wallet_info: &AccountInfo<'static>,
+    mint_info: &AccountInfo<'static>,
+    marked: bool,
+) -> AccountInfo<'static> {
+    let (key, _) = Pubkey::find_program_address(
+        &[b"soulbound", wallet_info.key.as_ref(), mint_info.key.as_ref()],
+        &crate::ID,
+    );
+    if !marked {
+        return account_info(key, system_program::ID, 0, vec![], false, false);
+    }
+
+    let wallet: SmartWallet = read_account(wallet_info);
+    let soulbound = uninitialized_account(key, 8 + std::mem::size_of::<SoulboundMint>());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        mint_info.clone(),
+        soulbound.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run::<MarkSoulbound, _>(infos, &[], leet_wallet::mark_soulbound).unwrap();
+    soulbound
+}
+
+fn transfer_nft(
+    wallet_info: &AccountInfo<'static>,
+    mint_info: &AccountInfo<'static>,
+    soulbound: AccountInfo<'static>,
+) -> Result<()> {
+    transfer_nft_of_mint(wallet_info, mint_info, soulbound, unfrozen_mint(wallet_info.key, mint_info.key))
+}
+
+fn transfer_nft_of_mint(
+    wallet_info: &AccountInfo<'static>,
+    mint_info: &AccountInfo<'static>,
+    soulbound: AccountInfo<'static>,
+    frozen_mint: AccountInfo<'static>,
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        mint_info.clone(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, *mint_info.key, 1),
+        token_account(Pubkey::new_unique(), Pubkey::new_unique(), *mint_info.key, 0),
+        soulbound,
+        frozen_mint,
+        signer(wallet.authority),
+        token_program(),
+    ]);
+    run::<TransferNft, _>(infos, &[], leet_wallet::transfer_nft)
+}
+
+#[test]
+fn nft_moves_one_unit() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let mint_info = mint_account(0, 1);
+
+    transfer_nft(&wallet_info, &mint_info, soulbound_account(&wallet_info, &mint_info, false)).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).nonce, 1);
+}
+
+#[test]
+fn nft_transfers_leave_the_token_limits_alone() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lifetime_limit = Some(0);
+    let wallet_info = wallet_pda(wallet);
+    let mint_info = mint_account(0, 1);
+
+    transfer_nft(&wallet_info, &mint_info, soulbound_account(&wallet_info, &mint_info, false)).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.daily_spent, wallet.lifetime_spent), (0, 0));
+}
+
+#[test]
+fn frozen_nft_mint_cannot_be_transferred() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let mint_info = mint_account(0, 1);
+    let frozen_info = freeze_mint(&wallet_info, &mint_info);
+
+    let soulbound = soulbound_account(&wallet_info, &mint_info, false);
+    assert_error(transfer_nft_of_mint(&wallet_info, &mint_info, soulbound, frozen_info), WalletError::MintFrozen);
+}
+
+#[test]
+fn fungible_mint_is_not_an_nft() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+
+    for mint_info in [mint_account(6, 1), mint_account(0, 2)] {
+        let soulbound = soulbound_account(&wallet_info, &mint_info, false);
+        assert_error(transfer_nft(&wallet_info, &mint_info, soulbound), WalletError::NotAnNft);
+    }
+}
+
+#[test]
+fn soulbound_nft_cannot_be_transferred() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let mint_info = mint_account(0, 1);
+
+    let soulbound = soulbound_account(&wallet_info, &mint_info, true);
+
+    assert_error(transfer_nft(&wallet_info, &mint_info, soulbound), WalletError::SoulboundAsset);
+}
+
+// ============ Guardian blocklist ============
+
+fn blocklist_account(admin: Pubkey) -> AccountInfo<'static> {
+    let (key, bump) = Pubkey::find_program_address(&[b"blocklist"], &crate::ID);
+    let blocklist = Blocklist {
+        admin,
+        blocked_count: 0,
+        bump,
+    };
+    program_account(key, &blocklist, 8 + std::mem::size_of::<Blocklist>())
+}
+
+fn blocked_key_address(pubkey: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"blocked", pubkey.as_ref()], &crate::ID).0
+}
+
+/// Block `pubkey` as `admin`, returning the created BlockedKey account
+fn block_pubkey(
+    blocklist_info: &AccountInfo<'static>,
+    admin: AccountInfo<'static>,
+    pubkey: Pubkey,
+) -> Result<AccountInfo<'static>> {
+    let space = 8 + std::mem::size_of::<BlockedKey>();
+    let blocked_key = uninitialized_account(blocked_key_address(&pubkey), space);
+    let infos = leak(vec![blocklist_info.clone(), blocked_key.clone(), admin, system_program_account()]);
+    run_with_args::<BlockPubkey, _>(infos, &[], pubkey, |ctx| leet_wallet::block_pubkey(ctx, pubkey))?;
+    Ok(blocked_key)
+}
+
+fn add_guardian(wallet_info: &AccountInfo<'static>, pubkey: Pubkey, blocked_key: AccountInfo<'static>) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let (guardian_key, _) = Pubkey::find_program_address(
+        &[b"guardian", wallet_info.key.as_ref(), &[wallet.next_guardian_index]],
+        &crate::ID,
+    );
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        uninitialized_account(guardian_key, 8 + std::mem::size_of::<Guardian>()),
+        blocked_key,
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+        absent(),
+    ]);
+    let args = (pubkey, GuardianType::Email, 1u8, 0i64);
+    run_with_args::<AddGuardian, _>(infos, &[], args, |ctx| {
+        leet_wallet::add_guardian(ctx, pubkey, GuardianType::Email, 1, 0)
+    })
+}
+
+#[test]
+fn blocked_key_cannot_become_a_guardian() {
+    let admin = payer();
+    let blocklist_info = blocklist_account(*admin.key);
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    let pubkey = Pubkey::new_unique();
+
+    let blocked_key = block_pubkey(&blocklist_info, admin, pubkey).unwrap();
+
+    assert_eq!(read_account::<Blocklist>(&blocklist_info).blocked_count, 1);
+    assert_error(add_guardian(&wallet_info, pubkey, blocked_key), WalletError::GuardianBlocked);
+}
+
+#[test]
+fn unblocked_key_can_become_a_guardian() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    let pubkey = Pubkey::new_unique();
+    let blocked_key = account_info(blocked_key_address(&pubkey), system_program::ID, 0, vec![], false, false);
+
+    add_guardian(&wallet_info, pubkey, blocked_key).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).guardian_count, 1);
+}
+
+#[test]
+fn only_the_blocklist_admin_can_block() {
+    let blocklist_info = blocklist_account(Pubkey::new_unique());
+
+    let result = block_pubkey(&blocklist_info, payer(), Pubkey::new_unique()).map(|_| ());
+
+    let err = result.unwrap_err();
+    assert_eq!(err, Error::from(ErrorCode::ConstraintHasOne));
+}
+
+#[test]
+fn only_the_upgrade_authority_creates_the_blocklist() {
+    let upgrade_authority = Pubkey::new_unique();
+    let (key, _) = Pubkey::find_program_address(&[b"blocklist"], &crate::ID);
+    let blocklist_info = uninitialized_account(key, 8 + std::mem::size_of::<Blocklist>());
+    let admin = Pubkey::new_unique();
+    let initialize = |caller: Pubkey| {
+        let (program, program_data) = upgradeable_program(upgrade_authority);
+        let infos = leak(vec![
+            blocklist_info.clone(),
+            program,
+            program_data,
+            signer(caller),
+            payer(),
+            system_program_account(),
+        ]);
+        run_with_args::<InitializeBlocklist, _>(infos, &[], admin, |ctx| leet_wallet::initialize_blocklist(ctx, admin))
+    };
+
+    assert_error(initialize(Pubkey::new_unique()), WalletError::Unauthorized);
+    initialize(upgrade_authority).unwrap();
+    assert_eq!(read_account::<Blocklist>(&blocklist_info).admin, admin);
+}
+
+// ============ Escrowed deposits ============
+
+/// Deposit `amount` into `wallet_info`'s escrow, returning the PendingDeposit
+/// account and the sender
+fn deposit(wallet_info: &AccountInfo<'static>, amount: u64) -> (AccountInfo<'static>, Pubkey) {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let (escrow_key, _) = Pubkey::find_program_address(&[b"escrow", wallet_info.key.as_ref()], &crate::ID);
+    let (deposit_key, _) = Pubkey::find_program_address(
+        &[b"deposit", wallet_info.key.as_ref(), &wallet.next_deposit_id.to_le_bytes()],
+        &crate::ID,
+    );
+    let sender = payer();
+    let mint = Pubkey::new_unique();
+    let pending_deposit = uninitialized_account(deposit_key, 8 + std::mem::size_of::<PendingDeposit>());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        pending_deposit.clone(),
+        account_info(escrow_key, system_program::ID, 0, vec![], false, false),
+        token_account(Pubkey::new_unique(), escrow_key, mint, 0),
+        token_account(Pubkey::new_unique(), *sender.key, mint, amount),
+        sender.clone(),
+        token_program(),
+        system_program_account(),
+    ]);
+    run::<Deposit, _>(infos, &[], |ctx| leet_wallet::deposit(ctx, amount)).unwrap();
+    (pending_deposit, *sender.key)
+}
+
+fn blocked_sender_address(sender: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"blocked_sender", sender.as_ref()], &crate::ID).0
+}
+
+/// Block `sender`'s deposits as `admin`, returning the created PDA
+fn block_sender(
+    blocklist_info: &AccountInfo<'static>,
+    admin: AccountInfo<'static>,
+    sender: Pubkey,
+) -> Result<AccountInfo<'static>> {
+    let space = 8 + std::mem::size_of::<BlockedKey>();
+    let blocked_sender = uninitialized_account(blocked_sender_address(&sender), space);
+    let infos = leak(vec![blocklist_info.clone(), blocked_sender.clone(), admin, system_program_account()]);
+    run_with_args::<BlockSender, _>(infos, &[], sender, |ctx| leet_wallet::block_sender(ctx, sender))?;
+    Ok(blocked_sender)
+}
+
+fn claim_deposit(
+    wallet_info: &AccountInfo<'static>,
+    pending_deposit: &AccountInfo<'static>,
+    blocked_sender: AccountInfo<'static>,
+) -> Result<AccountInfo<'static>> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let record: PendingDeposit = read_account(pending_deposit);
+    let (escrow_key, _) = Pubkey::find_program_address(&[b"escrow", wallet_info.key.as_ref()], &crate::ID);
+    let sender = account_info(record.sender, system_program::ID, 0, vec![], false, true);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        pending_deposit.clone(),
+        account_info(escrow_key, system_program::ID, 0, vec![], false, false),
+        token_account(Pubkey::new_unique(), escrow_key, record.mint, record.amount),
+        token_account(Pubkey::new_unique(), *wallet_info.key, record.mint, 0),
+        blocked_sender,
+        sender.clone(),
+        signer(wallet.authority),
+        token_program(),
+    ]);
+    run::<ClaimDeposit, _>(infos, &[], leet_wallet::claim_deposit)?;
+    Ok(sender)
+}
+
+#[test]
+fn deposit_is_recorded_until_claimed() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+
+    let (pending_deposit, sender) = deposit(&wallet_info, 250);
+
+    let record: PendingDeposit = read_account(&pending_deposit);
+    assert_eq!((record.sender, record.amount, record.id), (sender, 250, 0));
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).next_deposit_id, 1);
+
+    let blocked_sender = account_info(blocked_sender_address(&sender), system_program::ID, 0, vec![], false, false);
+    let rent = pending_deposit.lamports();
+    let sender_info = claim_deposit(&wallet_info, &pending_deposit, blocked_sender).unwrap();
+    assert_eq!(pending_deposit.lamports(), 0);
+    assert_eq!(sender_info.lamports(), rent);
+}
+
+#[test]
+fn deposit_from_a_blocked_sender_cannot_be_claimed() {
+    let admin = payer();
+    let blocklist_info = blocklist_account(*admin.key);
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    let (pending_deposit, sender) = deposit(&wallet_info, 250);
+
+    let blocked_sender = block_sender(&blocklist_info, admin, sender).unwrap();
+
+    assert_error(
+        claim_deposit(&wallet_info, &pending_deposit, blocked_sender).map(|_| ()),
+        WalletError::SenderBlocked,
+    );
+}
+
+#[test]
+fn blocked_deposit_is_refunded_to_its_sender() {
+    let admin = payer();
+    let blocklist_info = blocklist_account(*admin.key);
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let (pending_deposit, sender) = deposit(&wallet_info, 250);
+    block_sender(&blocklist_info, admin, sender).unwrap();
+
+    let record: PendingDeposit = read_account(&pending_deposit);
+    let (escrow_key, _) = Pubkey::find_program_address(&[b"escrow", wallet_info.key.as_ref()], &crate::ID);
+    let sender_info = account_info(sender, system_program::ID, 0, vec![], false, true);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        pending_deposit.clone(),
+        account_info(escrow_key, system_program::ID, 0, vec![], false, false),
+        token_account(Pubkey::new_unique(), escrow_key, record.mint, record.amount),
+        token_account(Pubkey::new_unique(), sender, record.mint, 0),
+        sender_info.clone(),
+        signer(wallet.authority),
+        token_program(),
+    ]);
+    let rent = pending_deposit.lamports();
+    run::<RefundDeposit, _>(infos, &[], leet_wallet::refund_deposit).unwrap();
+
+    assert_eq!((pending_deposit.lamports(), sender_info.lamports()), (0, rent));
+    let refunded = &emitted::<DepositRefunded>()[0];
+    assert_eq!((refunded.sender, refunded.amount), (sender, 250));
+}
+
+#[test]
+fn guardian_blocklist_does_not_block_deposits() {
+    let admin = payer();
+    let blocklist_info = blocklist_account(*admin.key);
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    let (pending_deposit, sender) = deposit(&wallet_info, 250);
+    block_pubkey(&blocklist_info, admin, sender).unwrap();
+
+    let blocked_sender = account_info(blocked_sender_address(&sender), system_program::ID, 0, vec![], false, false);
+    claim_deposit(&wallet_info, &pending_deposit, blocked_sender).unwrap();
+}
+
+// ============ Audit log ============
+
+fn audit_log(wallet_info: &AccountInfo<'static>) -> AccountInfo<'static> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let (key, _) = Pubkey::find_program_address(&[b"audit", wallet_info.key.as_ref()], &crate::ID);
+    let log_info = uninitialized_account(key, 8 + std::mem::size_of::<AuditLog>());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        log_info.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run::<InitializeAuditLog, _>(infos, &[], leet_wallet::initialize_audit_log).unwrap();
+    log_info
+}
+
+#[test]
+fn audit_log_records_a_sequence_of_actions() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let authority = wallet.authority;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let log_info = audit_log(&wallet_info);
+    let accounts = leak(vec![wallet_info.clone(), signer(authority), log_info.clone()]);
+
+    run::<FreezeWallet, _>(accounts, &[], |ctx| leet_wallet::freeze_wallet(ctx, FreezeReason::Manual)).unwrap();
+    let unfreeze = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(authority),
+        instructions_sysvar(&[]),
+        log_info.clone(),
+    ]);
+    run::<UnfreezeWallet, _>(unfreeze, &[], |ctx| leet_wallet::unfreeze_wallet(ctx, None)).unwrap();
+    let limit = leak(vec![wallet_info.clone(), global_config(), signer(authority), log_info.clone()]);
+    run::<UpdateLimit, _>(limit, &[], |ctx| leet_wallet::update_daily_limit(ctx, 500)).unwrap();
+
+    let log: AuditLog = read_account(&log_info);
+    assert_eq!((log.len, log.head), (3, 3));
+    let actions: Vec<_> = log.entries[..3].iter().map(|entry| entry.action).collect();
+    assert!(actions == [AuditAction::Freeze, AuditAction::Unfreeze, AuditAction::LimitUpdate]);
+    assert!(log.entries[..3].iter().all(|entry| entry.actor == authority && entry.timestamp == NOW));
+}
+
+#[test]
+fn audit_log_overwrites_the_oldest_entry_when_full() {
+    let mut log = AuditLog {
+        wallet: Pubkey::new_unique(),
+        head: 0,
+        len: 0,
+        entries: [AuditEntry::default(); AUDIT_LOG_CAPACITY],
+        bump: 0,
+    };
+
+    for timestamp in 0..=AUDIT_LOG_CAPACITY as i64 {
+        log.record(AuditAction::LimitUpdate, Pubkey::default(), timestamp);
+    }
+
+    assert_eq!((log.len as usize, log.head), (AUDIT_LOG_CAPACITY, 1));
+    assert_eq!(log.entries[0].timestamp, AUDIT_LOG_CAPACITY as i64);
+    assert_eq!(log.entries[1].timestamp, 1);
+}
+
+// ============ Recovery and signing thresholds ============
+
+/// A pending recovery needing `recovery_threshold` weight on a wallet whose
+/// execute_transaction needs `guardian_threshold` signatures
+fn split_threshold_wallet(guardian_threshold: u8, recovery_threshold: u8) -> AccountInfo<'static> {
+    let wallet_info = pending_recovery_wallet(recovery_threshold);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.guardian_threshold = guardian_threshold;
+    wallet.guardian_count = 3;
+    wallet.total_guardian_weight = 3;
+    write_account(&wallet_info, &wallet);
+    wallet_info
+}
+
+fn execute_with_one_signature(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    let message = operation_message(OperationType::Execute, wallet_info.key, 0, b"payload");
+    execute_transaction(wallet_info, message, 0)
+}
+
+#[test]
+fn recovery_threshold_is_enforced_independently_of_signing() {
+    // One approval recovers, but one signature can't execute
+    let wallet_info = split_threshold_wallet(2, 1);
+    assert_error(execute_with_one_signature(&wallet_info), WalletError::InsufficientSignatures);
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+
+    // One signature executes, but one approval can't recover
+    let wallet_info = split_threshold_wallet(1, 2);
+    execute_with_one_signature(&wallet_info).unwrap();
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+}
+
+#[test]
+fn thresholds_must_be_reachable_by_the_guardians() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.total_guardian_weight = 4;
+    let infos = update_config_accounts(&wallet);
+    let co_signed = co_signers(infos[0].key, 1);
+
+    let update_recovery = |threshold| {
+        run::<UpdateConfig, _>(infos, co_signed, move |ctx| leet_wallet::update_recovery_threshold(ctx, threshold))
+    };
+    assert_error(update_recovery(0), WalletError::InvalidThreshold);
+    assert_error(update_recovery(5), WalletError::InvalidThreshold);
+    update_recovery(4).unwrap();
+
+    let update_guardian = |threshold| {
+        run::<UpdateConfig, _>(infos, co_signed, move |ctx| leet_wallet::update_guardian_threshold(ctx, threshold))
+    };
+    assert_error(update_guardian(3), WalletError::InvalidThreshold);
+    update_guardian(2).unwrap();
+}
+
+#[test]
+fn threshold_updates_need_co_signers_and_no_pending_recovery() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    wallet.guardian_threshold = 2;
+    wallet.total_guardian_weight = 3;
+    let infos = update_config_accounts(&wallet);
+    let update_recovery = |threshold, co_signers| {
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::update_recovery_threshold(ctx, threshold))
+    };
+    let update_guardian = |threshold, co_signers| {
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::update_guardian_threshold(ctx, threshold))
+    };
+
+    assert_error(update_recovery(1, co_signers(infos[0].key, 1)), WalletError::InsufficientApprovals);
+    assert_error(update_guardian(1, co_signers(infos[0].key, 1)), WalletError::InsufficientApprovals);
+
+    let mut pending: SmartWallet = read_account(&infos[0]);
+    pending.recovery_pending = true;
+    write_account(&infos[0], &pending);
+    let result = update_recovery(3, co_signers(infos[0].key, 2));
+    assert_error(result, WalletError::RecoveryAlreadyPending);
+    assert_error(update_guardian(3, co_signers(infos[0].key, 2)), WalletError::RecoveryAlreadyPending);
+}
+
+#[test]
+fn recovery_and_guardian_thresholds_change_independently() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    wallet.guardian_threshold = 2;
+    wallet.recovery_threshold = 2;
+    wallet.total_guardian_weight = 3;
+    let infos = update_config_accounts(&wallet);
+
+    let co_signed = co_signers(infos[0].key, 2);
+    run::<UpdateConfig, _>(infos, co_signed, |ctx| leet_wallet::update_recovery_threshold(ctx, 3)).unwrap();
+    let updated: SmartWallet = read_account(&infos[0]);
+    assert_eq!((updated.recovery_threshold, updated.guardian_threshold), (3, 2));
+
+    run::<UpdateConfig, _>(infos, co_signed, |ctx| leet_wallet::update_guardian_threshold(ctx, 1)).unwrap();
+    let updated: SmartWallet = read_account(&infos[0]);
+    assert_eq!((updated.recovery_threshold, updated.guardian_threshold), (3, 1));
+}
+
+#[test]
+fn wallet_needs_a_reachable_nonzero_guardian_threshold() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, 0);
+    let initialize = |id, threshold| {
+        let wallet_id = [id; 32];
+        let (key, _) = crate::wallet_pda(&wallet_id);
+        let infos = leak(vec![
+            uninitialized_account(key, 8 + std::mem::size_of::<SmartWallet>() + 100 + 32 * MAX_OWNERS),
+            signer(owner),
+            account_info(Pubkey::new_unique(), system_program::ID, 0, vec![], false, false),
+            index_info.clone(),
+            payer(),
+            system_program_account(),
+        ]);
+        run_with_args::<InitializeWallet, _>(infos, &[], wallet_id, move |ctx| {
+            leet_wallet::initialize_wallet(ctx, wallet_id, threshold, 1_000, 86_400)
+        })
+    };
+
+    assert_error(initialize(1, 0), WalletError::InvalidThreshold);
+    assert_error(initialize(2, MAX_GUARDIANS as u8 + 1), WalletError::InvalidThreshold);
+    initialize(3, MAX_GUARDIANS as u8).unwrap();
+}
+
+// ============ Limit increase timelock ============
+
+fn update_daily_limit(wallet_info: &AccountInfo<'static>, new_limit: u64) -> Result<()> {
+    update_daily_limit_co_signed(wallet_info, new_limit, &[])
+}
+
+fn update_daily_limit_co_signed(
+    wallet_info: &AccountInfo<'static>,
+    new_limit: u64,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority), absent()]);
+    run::<UpdateLimit, _>(infos, co_signers, |ctx| leet_wallet::update_daily_limit(ctx, new_limit))
+}
+
+fn apply_limit_change(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    run::<ApplyLimitChange, _>(leak(vec![wallet_info.clone(), global_config()]), &[], leet_wallet::apply_limit_change)
+}
+
+#[test]
+fn limit_increase_waits_out_the_timelock() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+
+    update_daily_limit(&wallet_info, 5_000).unwrap();
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_limit, 1_000);
+    let pending = wallet.pending_limit_change.clone().unwrap();
+    assert_eq!((pending.new_limit, pending.effective_at), (5_000, NOW + LIMIT_INCREASE_DELAY));
+    assert_error(apply_limit_change(&wallet_info), WalletError::LimitChangeTimelocked);
+
+    wallet.pending_limit_change = Some(PendingLimitChange {
+        effective_at: NOW,
+        ..pending
+    });
+    write_account(&wallet_info, &wallet);
+    apply_limit_change(&wallet_info).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_limit, 5_000);
+    assert!(wallet.pending_limit_change.is_none());
+}
+
+#[test]
+fn limit_decrease_applies_immediately() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    update_daily_limit(&wallet_info, 5_000).unwrap();
+
+    update_daily_limit(&wallet_info, 400).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_limit, 400);
+    // A decrease also drops any queued increase
+    assert!(wallet.pending_limit_change.is_none());
+}
+
+/// A wallet with limit 1_000, a 2-of-2 guardian set, and the decrease
+/// policy flagging drops of half or more below a 500 floor
+fn decrease_policy_wallet() -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = 2;
+    wallet.guardian_count = 2;
+    wallet.limit_decrease_floor = 500;
+    wallet.limit_drop_alert_bps = 5_000;
+    program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE)
+}
+
+#[test]
+fn large_limit_drop_needs_guardian_approval() {
+    let wallet_info = decrease_policy_wallet();
+
+    update_daily_limit(&wallet_info, 10).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_limit, 1_000);
+    assert_eq!(wallet.pending_limit_change.unwrap().new_limit, 10);
+    let flagged = emitted::<LimitDecreaseFlagged>();
+    assert_eq!((flagged[0].new_limit, flagged[0].drop_bps), (10, 9_900));
+
+    update_daily_limit_co_signed(&wallet_info, 10, co_signers(wallet_info.key, 2)).unwrap();
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_limit, 10);
+    assert!(wallet.pending_limit_change.is_none());
+}
+
+#[test]
+fn small_limit_drop_applies_freely() {
+    let wallet_info = decrease_policy_wallet();
+
+    update_daily_limit(&wallet_info, 800).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_limit, 800);
+    assert!(emitted::<LimitDecreaseFlagged>().is_empty());
+    // Disabling spending outright stays an immediate brake
+    update_daily_limit(&wallet_info, 0).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_limit, 0);
+}
+
+#[test]
+fn below_floor_drop_does_not_replace_a_queued_increase() {
+    let wallet_info = decrease_policy_wallet();
+    update_daily_limit(&wallet_info, 5_000).unwrap();
+
+    assert_error(update_daily_limit(&wallet_info, 10), WalletError::LimitChangeAlreadyPending);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).pending_limit_change.unwrap().new_limit, 5_000);
+}
+
+#[test]
+fn percentage_cap_counts_as_a_limit_decrease() {
+    let wallet_info = decrease_policy_wallet();
+    let wallet: SmartWallet = read_account(&wallet_info);
+    let set_bps = |bps, co_signers| {
+        let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::set_daily_limit_bps(ctx, bps))
+    };
+
+    assert_error(set_bps(100, &[]), WalletError::InsufficientApprovals);
+    set_bps(100, co_signers(wallet_info.key, 2)).unwrap();
+    // Widening the cap is not a decrease
+    set_bps(200, &[]).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_limit_bps, 200);
+}
+
+#[test]
+fn guardian_cancels_a_queued_increase() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    update_daily_limit(&wallet_info, 5_000).unwrap();
+    let pubkey = Pubkey::new_unique();
+
+    let cancel = |guardian_signer: Pubkey| {
+        let infos = leak(vec![
+            wallet_info.clone(),
+            guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1),
+            signer(guardian_signer),
+        ]);
+        run::<CancelLimitChange, _>(infos, &[], leet_wallet::cancel_limit_change)
+    };
+    assert_error(cancel(Pubkey::new_unique()), WalletError::Unauthorized);
+    cancel(pubkey).unwrap();
+
+    assert!(read_account::<SmartWallet>(&wallet_info).pending_limit_change.is_none());
+}
+
+#[test]
+fn guarded_limit_increase_needs_the_guardian_quorum() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = 2;
+    wallet.guardian_count = 2;
+    wallet.limit_change_requires_guardians = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    assert_error(update_daily_limit(&wallet_info, 5_000), WalletError::InsufficientApprovals);
+    let one = co_signers(wallet_info.key, 1);
+    assert_error(update_daily_limit_co_signed(&wallet_info, 5_000, one), WalletError::InsufficientApprovals);
+
+    update_daily_limit_co_signed(&wallet_info, 5_000, co_signers(wallet_info.key, 2)).unwrap();
+    let pending = read_account::<SmartWallet>(&wallet_info).pending_limit_change.unwrap();
+    assert_eq!(pending.new_limit, 5_000);
+
+    // Decreases stay authority-only
+    update_daily_limit(&wallet_info, 500).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_limit, 500);
+}
+
+// ============ Limit ramp ============
+
+#[test]
+fn ramped_limit_grows_with_wallet_age_up_to_the_target() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.created_at = NOW;
+    wallet.limit_ramp_per_day = 100;
+    wallet.limit_ramp_target = 1_500;
+
+    assert_eq!(wallet.ramped_daily_limit(NOW), 1_000);
+    assert_eq!(wallet.ramped_daily_limit(NOW + 86399), 1_000);
+    assert_eq!(wallet.ramped_daily_limit(NOW + 86400), 1_100);
+    assert_eq!(wallet.ramped_daily_limit(NOW + 3 * 86400), 1_300);
+    assert_eq!(wallet.ramped_daily_limit(NOW + 30 * 86400), 1_500);
+}
+
+#[test]
+fn ramp_is_opt_in() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.created_at = NOW - 30 * 86400;
+    wallet.limit_ramp_target = 5_000;
+
+    assert_eq!(wallet.ramped_daily_limit(NOW), 1_000);
+}
+
+#[test]
+fn transfers_use_the_ramped_limit() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.created_at = NOW - 2 * 86400;
+    wallet.limit_ramp_per_day = 100;
+    wallet.limit_ramp_target = 5_000;
+    let wallet_info = wallet_pda(wallet);
+
+    transfer_spl(&wallet_info, 1_200).unwrap();
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::DailyLimitExceeded);
+}
+
+#[test]
+fn raising_ramp_waits_out_the_limit_timelock() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let infos = update_config_accounts(&wallet);
+    let set_ramp = |per_day, target| {
+        run::<UpdateConfig, _>(infos, &[], move |ctx| leet_wallet::set_limit_ramp(ctx, per_day, target))
+    };
+
+    set_ramp(100, 5_000).unwrap();
+    let queued: SmartWallet = read_account(&infos[0]);
+    assert_eq!((queued.limit_ramp_per_day, queued.limit_ramp_target), (0, 0));
+    assert_eq!(queued.pending_limit_change.unwrap().effective_at, NOW + LIMIT_INCREASE_DELAY);
+
+    set_clock(NOW + LIMIT_INCREASE_DELAY);
+    apply_limit_change(&infos[0]).unwrap();
+    let ramped: SmartWallet = read_account(&infos[0]);
+    assert_eq!((ramped.limit_ramp_per_day, ramped.limit_ramp_target, ramped.daily_limit), (100, 5_000, 1_000));
+
+    // Slowing the ramp applies at once
+    set_ramp(50, 5_000).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).limit_ramp_per_day, 50);
+}
+
+// ============ Dormant wallets ============
+
+#[test]
+fn rollover_after_a_400_day_gap_records_the_skipped_days() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_spent = 900;
+    let later = NOW + 400 * 86400;
+
+    assert_eq!(wallet.spent_today(later), 0);
+    assert_eq!(wallet.remaining_today(later), 1_000);
+
+    wallet.reset_daily_spent(later);
+    assert_eq!(wallet.daily_spent, 0);
+    assert_eq!(wallet.last_reset_day, later / 86400);
+    assert_eq!(wallet.days_skipped, 399);
+
+    // The next day's rollover skips nothing
+    wallet.reset_daily_spent(later + 86400);
+    assert_eq!(wallet.days_skipped, 0);
+}
+
+#[test]
+fn ramp_stays_capped_across_a_400_day_gap() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.created_at = NOW;
+    wallet.limit_ramp_per_day = u64::MAX / 2;
+    wallet.limit_ramp_target = 50_000;
+
+    assert_eq!(wallet.ramped_daily_limit(NOW + 400 * 86400), 50_000);
+    assert_eq!(wallet.ramped_daily_limit(i64::MAX), 50_000);
+    // A clock behind created_at never ramps below the base limit
+    assert_eq!(wallet.ramped_daily_limit(i64::MIN), 1_000);
+}
+
+// ============ Vesting ============
+
+/// A wallet vesting 1_000 over 1_000 seconds with a 250 second cliff,
+/// `elapsed` seconds into the schedule
+fn vesting_wallet(elapsed: i64) -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.vesting = Some(VestingSchedule {
+        start: NOW - elapsed,
+        cliff: 250,
+        duration: 1_000,
+        total: 1_000,
+    });
+    wallet_pda(wallet)
+}
+
+#[test]
+fn nothing_is_spendable_before_the_cliff() {
+    let wallet_info = vesting_wallet(249);
+
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::AmountStillLocked);
+}
+
+#[test]
+fn mid_vest_spend_is_capped_at_the_unlocked_amount() {
+    let wallet_info = vesting_wallet(500);
+
+    transfer_spl(&wallet_info, 300).unwrap();
+    transfer_spl(&wallet_info, 200).unwrap();
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::AmountStillLocked);
+}
+
+#[test]
+fn fully_vested_total_is_spendable() {
+    let wallet_info = vesting_wallet(5_000);
+
+    transfer_spl(&wallet_info, 1_000).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).lifetime_spent, 1_000);
+}
+
+#[test]
+fn emergency_withdrawal_leaves_the_locked_amount() {
+    let wallet_info = vesting_wallet(500);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    let safe = Pubkey::new_unique();
+    wallet.safe_address = Some(safe);
+    write_account(&wallet_info, &wallet);
+    let mint = Pubkey::new_unique();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        token_account(Pubkey::new_unique(), safe, mint, 0),
+        signer(wallet.authority),
+        token_program(),
+    ]);
+
+    run::<EmergencyWithdraw, _>(infos, &[], leet_wallet::emergency_withdraw_to_safe).unwrap();
+    assert_eq!(emitted::<EmergencyWithdrawal>()[0].amount, 500);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).lifetime_spent, 500);
+
+    let result = run::<EmergencyWithdraw, _>(infos, &[], leet_wallet::emergency_withdraw_to_safe);
+    assert_error(result, WalletError::NothingToSweep);
+}
+
+// ============ Compliance hold ============
+
+/// A frozen wallet at its PDA under a hold placed by the returned admin
+fn held_wallet() -> (AccountInfo<'static>, Pubkey) {
+    let admin = Pubkey::new_unique();
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.compliance_admin = Some(admin);
+    wallet.is_frozen = true;
+    let wallet_info = wallet_pda(wallet);
+
+    let infos = leak(vec![wallet_info.clone(), signer(admin)]);
+    run::<ManageComplianceHold, _>(infos, &[], leet_wallet::set_compliance_hold).unwrap();
+    (wallet_info, admin)
+}
+
+#[test]
+fn user_cannot_bypass_a_compliance_hold() {
+    let (wallet_info, _) = held_wallet();
+    let authority = read_account::<SmartWallet>(&wallet_info).authority;
+
+    assert_error(unfreeze_wallet(&wallet_info, &[]), WalletError::ComplianceHold);
+
+    let release = leak(vec![wallet_info.clone(), signer(authority)]);
+    let result = run::<ManageComplianceHold, _>(release, &[], leet_wallet::release_compliance_hold);
+    assert_error(result, WalletError::Unauthorized);
+
+    let replace_admin = leak(vec![wallet_info.clone(), signer(authority)]);
+    let result = run::<SetComplianceAdmin, _>(replace_admin, &[], |ctx| {
+        leet_wallet::set_compliance_admin(ctx, Some(authority))
+    });
+    assert_error(result, WalletError::Unauthorized);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.is_frozen = false;
+    write_account(&wallet_info, &wallet);
+    assert_error(transfer_spl(&wallet_info, 10), WalletError::ComplianceHold);
+}
+
+#[test]
+fn admin_release_leaves_the_user_freeze_in_place() {
+    let (wallet_info, admin) = held_wallet();
+
+    let release = leak(vec![wallet_info.clone(), signer(admin)]);
+    run::<ManageComplianceHold, _>(release, &[], leet_wallet::release_compliance_hold).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert!(!wallet.compliance_hold);
+    assert!(wallet.is_frozen);
+    assert_error(transfer_spl(&wallet_info, 10), WalletError::WalletFrozen);
+}
+
+#[test]
+fn compliance_admin_changes_are_announced() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = wallet_pda(wallet.clone());
+    let admin = Pubkey::new_unique();
+    let set_admin = |signer_key, new_admin| {
+        let infos = leak(vec![wallet_info.clone(), signer(signer_key)]);
+        run::<SetComplianceAdmin, _>(infos, &[], |ctx| leet_wallet::set_compliance_admin(ctx, new_admin))
+    };
+
+    set_admin(wallet.authority, Some(admin)).unwrap();
+    set_admin(admin, None).unwrap();
+
+    let events = emitted::<ComplianceAdminUpdated>();
+    assert_eq!((events[0].previous_admin, events[0].admin), (None, Some(admin)));
+    assert_eq!((events[1].previous_admin, events[1].admin), (Some(admin), None));
+}
+
+// ============ Freeze alerts ============
+
+fn broadcast_freeze_alert(wallet_info: &AccountInfo<'static>, guardians: &'static [AccountInfo<'static>]) -> Result<()> {
+    run::<ReadWallet, _>(leak(vec![wallet_info.clone()]), guardians, leet_wallet::broadcast_freeze_alert)
+}
+
+#[test]
+fn freeze_alert_reaches_each_guardian() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.is_frozen = true;
+    wallet.freeze_reason = Some(FreezeReason::SuspectedCompromise);
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let pubkeys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let guardians = pubkeys
+        .iter()
+        .enumerate()
+        .map(|(index, pubkey)| guardian_account(wallet_info.key, index as u8, *pubkey, GuardianType::Email, 1));
+
+    broadcast_freeze_alert(&wallet_info, leak(guardians.collect())).unwrap();
+
+    let alerts = emitted::<GuardianAlert>();
+    assert_eq!(alerts.iter().map(|alert| alert.guardian).collect::<Vec<_>>(), pubkeys);
+    assert!(alerts.iter().all(|alert| alert.reason == FreezeReason::SuspectedCompromise));
+}
+
+#[test]
+fn freeze_alert_needs_a_frozen_wallet() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+
+    assert_error(broadcast_freeze_alert(&wallet_info, &[]), WalletError::WalletNotFrozen);
+}
+
+// ============ Per-mint freeze ============
+
+/// Run freeze_mint for `mint_info`, returning the created FrozenMint PDA
+fn freeze_mint(wallet_info: &AccountInfo<'static>, mint_info: &AccountInfo<'static>) -> AccountInfo<'static> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let frozen_key = frozen_mint_address(wallet_info.key, mint_info.key);
+    let frozen_info = uninitialized_account(frozen_key, 8 + std::mem::size_of::<FrozenMint>());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        mint_info.clone(),
+        frozen_info.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run::<FreezeMint, _>(infos, &[], leet_wallet::freeze_mint).unwrap();
+    frozen_info
+}
+
+fn transfer_mint(
+    wallet_info: &AccountInfo<'static>,
+    mint: Pubkey,
+    frozen_mint: AccountInfo<'static>,
+    amount: u64,
+) -> Result<()> {
+    let infos = transfer_accounts(wallet_info, mint, 1_000_000, frozen_mint, absent());
+    run_with_args::<TransferSPL, _>(infos, &[], (amount, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, amount, 0, None, None)
+    })
+}
+
+#[test]
+fn frozen_mint_is_blocked_while_other_mints_move() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let (tainted, clean) = (mint_account(6, 1_000_000), Pubkey::new_unique());
+
+    let frozen_info = freeze_mint(&wallet_info, &tainted);
+
+    assert_error(transfer_mint(&wallet_info, *tainted.key, frozen_info, 10), WalletError::MintFrozen);
+    transfer_mint(&wallet_info, clean, unfrozen_mint(wallet_info.key, &clean), 10).unwrap();
+    assert!(!read_account::<SmartWallet>(&wallet_info).is_frozen);
+}
+
+#[test]
+fn unfrozen_mint_moves_again() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let wallet: SmartWallet = read_account(&wallet_info);
+    let mint_info = mint_account(6, 1_000_000);
+    let frozen_info = freeze_mint(&wallet_info, &mint_info);
+
+    let authority = account_info(wallet.authority, system_program::ID, 0, vec![], true, true);
+    let infos = leak(vec![wallet_info.clone(), frozen_info.clone(), authority]);
+    run::<UnfreezeMint, _>(infos, &[], leet_wallet::unfreeze_mint).unwrap();
+
+    transfer_mint(&wallet_info, *mint_info.key, frozen_info, 10).unwrap();
+}
+
+// ============ Guardian rate limit ============
+
+fn add_unblocked_guardian(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    let pubkey = Pubkey::new_unique();
+    let blocked_key = account_info(blocked_key_address(&pubkey), system_program::ID, 0, vec![], false, false);
+    add_guardian(wallet_info, pubkey, blocked_key)
+}
+
+#[test]
+fn guardian_additions_stop_at_the_daily_cap() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.max_guardians_per_day = 2;
+    wallet.guardian_add_day = NOW / 86400;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    add_unblocked_guardian(&wallet_info).unwrap();
+    add_unblocked_guardian(&wallet_info).unwrap();
+    assert_error(add_unblocked_guardian(&wallet_info), WalletError::GuardianRateLimited);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.guardian_count, 2);
+    wallet.guardian_add_day -= 1;
+    write_account(&wallet_info, &wallet);
+    add_unblocked_guardian(&wallet_info).unwrap();
+}
+
+// ============ Unfreeze threshold ============
+
+fn unfreeze_wallet(wallet_info: &AccountInfo<'static>, co_signers: &'static [AccountInfo<'static>]) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(wallet.authority),
+        instructions_sysvar(&[]),
+        absent(),
+    ]);
+    run::<UnfreezeWallet, _>(infos, co_signers, |ctx| leet_wallet::unfreeze_wallet(ctx, None))
+}
+
+/// unfreeze_wallet approved off-chain by the guardians in `signed`, each
+/// signing the unfreeze message; `guardians` are passed as remaining accounts
+fn unfreeze_wallet_signed(
+    wallet_info: &AccountInfo<'static>,
+    guardians: &'static [AccountInfo<'static>],
+    signed: &[(Pubkey, [u8; 32])],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let signature = [9u8; 64];
+    let data: Vec<_> = signed
+        .iter()
+        .map(|(pubkey, message)| ed25519_instruction_data(pubkey, message, &signature))
+        .collect();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(wallet.authority),
+        instructions_sysvar(&data),
+        absent(),
+    ]);
+    run::<UnfreezeWallet, _>(infos, guardians, |ctx| leet_wallet::unfreeze_wallet(ctx, Some(vec![signature])))
+}
+
+#[test]
+fn unfreeze_uses_its_own_threshold_below_recovery() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    wallet.guardian_threshold = 2;
+    wallet.recovery_threshold = 2;
+    wallet.unfreeze_threshold = 1;
+    wallet.is_frozen = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    assert_error(unfreeze_wallet(&wallet_info, &[]), WalletError::InsufficientApprovals);
+    unfreeze_wallet(&wallet_info, co_signers(wallet_info.key, 1)).unwrap();
+
+    assert!(!read_account::<SmartWallet>(&wallet_info).is_frozen);
+}
+
+#[test]
+fn one_key_behind_two_guardians_counts_once_toward_unfreeze() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.unfreeze_threshold = 2;
+    wallet.max_approvals_per_type = 2;
+    wallet.is_frozen = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let pubkey = Pubkey::new_unique();
+    let same_key = leak(vec![
+        guardian_account(wallet_info.key, 0, pubkey, GuardianType::Email, 1),
+        signer(pubkey),
+        guardian_account(wallet_info.key, 1, pubkey, GuardianType::Email, 1),
+        signer(pubkey),
+    ]);
+
+    assert_error(unfreeze_wallet(&wallet_info, same_key), WalletError::InsufficientApprovals);
+    unfreeze_wallet(&wallet_info, co_signers(wallet_info.key, 2)).unwrap();
+}
+
+#[test]
+fn unfreeze_threshold_cannot_exceed_the_guardian_count() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    let infos = update_config_accounts(&wallet);
+
+    let result = run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_unfreeze_threshold(ctx, 4));
+    assert_error(result, WalletError::InvalidThreshold);
+
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_unfreeze_threshold(ctx, 1)).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).unfreeze_threshold, 1);
+}
+
+#[test]
+fn lowering_the_unfreeze_threshold_needs_co_signers() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    wallet.guardian_threshold = 2;
+    wallet.unfreeze_threshold = 2;
+    let infos = update_config_accounts(&wallet);
+    let set = |threshold, co_signers| {
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::set_unfreeze_threshold(ctx, threshold))
+    };
+
+    // Guardians exist, so unfreezing can't be left to the authority alone
+    assert_error(set(0, co_signers(infos[0].key, 2)), WalletError::InvalidThreshold);
+    assert_error(set(1, co_signers(infos[0].key, 1)), WalletError::InsufficientApprovals);
+    set(1, co_signers(infos[0].key, 2)).unwrap();
+    set(3, &[]).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).unfreeze_threshold, 3);
+}
+
+#[test]
+fn zero_unfreeze_threshold_still_needs_a_guardian() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.is_frozen = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    assert_error(unfreeze_wallet(&wallet_info, &[]), WalletError::InsufficientApprovals);
+    unfreeze_wallet(&wallet_info, co_signers(wallet_info.key, 1)).unwrap();
+}
+
+// ============ Program pause ============
+
+fn set_program_paused(paused: bool) -> Result<()> {
+    let config: GlobalConfig = read_account(&global_config());
+    let infos = leak(vec![global_config(), signer(config.admin)]);
+    run::<GlobalAdmin, _>(infos, &[], |ctx| leet_wallet::set_program_paused(ctx, paused))
+}
+
+#[test]
+fn pause_blocks_transfers_from_every_wallet() {
+    let first = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let second = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+
+    set_program_paused(true).unwrap();
+    assert_error(transfer_spl(&first, 10), WalletError::ProgramPaused);
+    assert_error(transfer_spl(&second, 10), WalletError::ProgramPaused);
+
+    set_program_paused(false).unwrap();
+    transfer_spl(&first, 10).unwrap();
+}
+
+#[test]
+fn only_the_global_admin_can_pause() {
+    let infos = leak(vec![global_config(), signer(Pubkey::new_unique())]);
+
+    let result = run::<GlobalAdmin, _>(infos, &[], |ctx| leet_wallet::set_program_paused(ctx, true));
+    assert_eq!(result.unwrap_err(), Error::from(ErrorCode::ConstraintHasOne));
+    assert!(!read_account::<GlobalConfig>(&global_config()).paused);
+}
+
+#[test]
+fn pause_blocks_configuration_changes() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+
+    set_program_paused(true).unwrap();
+    assert_error(update_daily_limit(&wallet_info, 500), WalletError::ProgramPaused);
+    set_program_paused(false).unwrap();
+    update_daily_limit(&wallet_info, 500).unwrap();
+}
+
+#[test]
+fn pause_blocks_guardian_pruning_but_not_delegate_revocation() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 1;
+    wallet.total_guardian_weight = 1;
+    let wallet_info = wallet_pda(wallet.clone());
+    let guardian_info = expired_guardian(&wallet_info, Pubkey::new_unique());
+    let revoke = || {
+        let infos = leak(vec![
+            wallet_info.clone(),
+            token_account(Pubkey::new_unique(), *wallet_info.key, Pubkey::new_unique(), 100),
+            signer(wallet.authority),
+            token_program(),
+        ]);
+        run::<RevokeDelegate, _>(infos, &[], leet_wallet::revoke_delegate)
+    };
+
+    set_program_paused(true).unwrap();
+    assert_error(prune_expired_guardian(&wallet_info, &guardian_info), WalletError::ProgramPaused);
+    revoke().unwrap();
+
+    set_program_paused(false).unwrap();
+    prune_expired_guardian(&wallet_info, &guardian_info).unwrap();
+}
+
+/// The program account and its ProgramData under the upgradeable loader,
+/// upgradeable by `upgrade_authority`
+fn upgradeable_program(upgrade_authority: Pubkey) -> (AccountInfo<'static>, AccountInfo<'static>) {
+    let loader = anchor_lang::solana_program::bpf_loader_upgradeable::ID;
+    let (program_data, _) = Pubkey::find_program_address(&[crate::ID.as_ref()], &loader);
+
+    let mut data = 2u32.to_le_bytes().to_vec();
+    data.extend_from_slice(program_data.as_ref());
+    let mut program = account_info(crate::ID, loader, 1, data, false, false);
+    program.executable = true;
+
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    (program, account_info(program_data, loader, 1, data, false, false))
+}
+
+#[test]
+fn only_the_upgrade_authority_creates_the_global_config() {
+    let upgrade_authority = Pubkey::new_unique();
+    let (key, _) = Pubkey::find_program_address(&[b"config"], &crate::ID);
+    let config_info = uninitialized_account(key, 8 + std::mem::size_of::<GlobalConfig>());
+    let admin = Pubkey::new_unique();
+    let initialize = |caller: Pubkey| {
+        let (program, program_data) = upgradeable_program(upgrade_authority);
+        let infos = leak(vec![
+            config_info.clone(),
+            program,
+            program_data,
+            signer(caller),
+            payer(),
+            system_program_account(),
+        ]);
+        run_with_args::<InitializeGlobalConfig, _>(infos, &[], admin, |ctx| {
+            leet_wallet::initialize_global_config(ctx, admin)
+        })
+    };
+
+    assert_error(initialize(Pubkey::new_unique()), WalletError::Unauthorized);
+    initialize(upgrade_authority).unwrap();
+    assert_eq!(read_account::<GlobalConfig>(&config_info).admin, admin);
+}
+
+// ============ Owner rotation ============
+
+fn rotate_authority(wallet_info: &AccountInfo<'static>, caller: Pubkey, new_authority: Pubkey) -> Result<()> {
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(caller)]);
+    run::<RotateAuthority, _>(infos, &[], |ctx| leet_wallet::rotate_authority(ctx, new_authority, false))
+}
+
+#[test]
+fn owner_rotates_the_authority_immediately() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let new_authority = Pubkey::new_unique();
+
+    rotate_authority(&wallet_info, wallet.owner, new_authority).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).authority, new_authority);
+    let events = emitted::<RecoveryExecuted>();
+    assert!(events[0].rotated_by_owner);
+}
+
+#[test]
+fn non_owner_cannot_rotate_the_authority() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+
+    let result = rotate_authority(&wallet_info, wallet.authority, Pubkey::new_unique());
+    assert_eq!(result.unwrap_err(), Error::from(ErrorCode::ConstraintHasOne));
+
+    let result = rotate_authority(&wallet_info, wallet.owner, Pubkey::default());
+    assert_error(result, WalletError::InvalidAuthority);
+}
+
+#[test]
+fn primary_owner_is_not_removed_as_a_co_owner() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let co_owner = Pubkey::new_unique();
+    wallet.owners = vec![wallet.owner, co_owner];
+    wallet.owner_threshold = 1;
+    let infos = leak(vec![program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE), global_config()]);
+    let remove = |owner| {
+        run::<ManageOwners, _>(infos, leak(vec![signer(co_owner)]), |ctx| leet_wallet::remove_owner(ctx, owner))
+    };
+
+    assert_error(remove(wallet.owner), WalletError::PrimaryOwnerNotRemovable);
+    remove(co_owner).unwrap();
+
+    let stored: SmartWallet = read_account(&infos[0]);
+    assert_eq!((stored.owner, stored.owners), (wallet.owner, vec![wallet.owner]));
+}
+
+// ============ Rent floor ============
+
+#[test]
+fn lamport_debits_keep_the_rent_floor() {
+    let wallet_info = program_account(
+        Pubkey::new_unique(),
+        &test_wallet(Pubkey::new_unique(), Pubkey::new_unique()),
+        WALLET_SPACE,
+    );
+    **wallet_info.lamports.borrow_mut() += 1_000;
+
+    assert_rent_exempt_after(&wallet_info, 1_000).unwrap();
+    assert_error(assert_rent_exempt_after(&wallet_info, 1_001), WalletError::WouldBreakRentExemption);
+    assert_error(assert_rent_exempt_after(&wallet_info, u64::MAX), WalletError::WouldBreakRentExemption);
+}
+
+// ============ Required guardian types ============
+
+fn validate_guardian_set(wallet_info: &AccountInfo<'static>, types: &[GuardianType]) -> GuardianSetValidated {
+    let guardians = types
+        .iter()
+        .enumerate()
+        .map(|(index, guardian_type)| {
+            guardian_account(wallet_info.key, index as u8, Pubkey::new_unique(), *guardian_type, 1)
+        });
+    let infos = leak(vec![wallet_info.clone()]);
+    run::<ReadWallet, _>(infos, leak(guardians.collect()), leet_wallet::validate_guardian_set).unwrap();
+    emitted::<GuardianSetValidated>().pop().unwrap()
+}
+
+/// Hardware and Institution guardians are required
+fn typed_wallet() -> SmartWallet {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.required_types_mask = GuardianType::Hardware.mask() | GuardianType::Institution.mask();
+    wallet
+}
+
+#[test]
+fn guardian_set_covering_the_mask_is_valid() {
+    let wallet_info = program_account(Pubkey::new_unique(), &typed_wallet(), WALLET_SPACE);
+
+    let result = validate_guardian_set(
+        &wallet_info,
+        &[GuardianType::Email, GuardianType::Hardware, GuardianType::Institution],
+    );
+
+    assert!(result.valid);
+    assert_eq!(result.missing_types, 0);
+}
+
+#[test]
+fn guardian_set_missing_a_type_is_invalid() {
+    let wallet_info = program_account(Pubkey::new_unique(), &typed_wallet(), WALLET_SPACE);
+
+    let result = validate_guardian_set(&wallet_info, &[GuardianType::Hardware, GuardianType::Hardware]);
+
+    assert!(!result.valid);
+    assert_eq!(result.missing_types, GuardianType::Institution.mask());
+}
+
+#[test]
+fn enforced_types_block_recovery_with_an_incomplete_set() {
+    let wallet_info = pending_recovery_wallet(1);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.required_types_mask = typed_wallet().required_types_mask;
+    wallet.enforce_guardian_types = true;
+    write_account(&wallet_info, &wallet);
+    approve_recovery_as(&wallet_info, 0, GuardianType::Hardware, 1).unwrap();
+
+    let hardware = guardian_account(wallet_info.key, 0, Pubkey::new_unique(), GuardianType::Hardware, 1);
+    assert_error(execute_recovery_with(&wallet_info, leak(vec![hardware])), WalletError::GuardianSetIncomplete);
+}
+
+// ============ Platform fee ============
+
+fn set_platform_fee(fee_bps: u16, fee_destination: Pubkey) -> Result<()> {
+    let config: GlobalConfig = read_account(&global_config());
+    let infos = leak(vec![global_config(), signer(config.admin)]);
+    run::<GlobalAdmin, _>(infos, &[], |ctx| leet_wallet::set_platform_fee(ctx, fee_bps, fee_destination))
+}
+
+#[test]
+fn fee_rounds_down_in_basis_points() {
+    let config = GlobalConfig {
+        admin: Pubkey::new_unique(),
+        paused: false,
+        fee_bps: 25,
+        fee_destination: Pubkey::new_unique(),
+        price_program: Pubkey::default(),
+        bump: 0,
+    };
+
+    assert_eq!(config.fee_for(10_000), 25);
+    assert_eq!(config.fee_for(10_399), 25);
+    assert_eq!(config.fee_for(399), 0);
+    assert_eq!(config.fee_for(u64::MAX), u64::MAX / 400);
+}
+
+#[test]
+fn recipient_gets_the_full_amount_and_the_fee_is_charged_on_top() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.emit_events = true;
+    let wallet_info = wallet_pda(wallet);
+    let treasury = Pubkey::new_unique();
+    set_platform_fee(100, treasury).unwrap();
+
+    let mint = Pubkey::new_unique();
+    let frozen_mint = unfrozen_mint(wallet_info.key, &mint);
+    let mut infos = transfer_accounts(&wallet_info, mint, 1_000_000, frozen_mint, absent()).to_vec();
+    // Without the treasury account the fee can't be collected
+    let result = run_with_args::<TransferSPL, _>(leak(infos.clone()), &[], (500u64, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, 500, 0, None, None)
+    });
+    assert_error(result, WalletError::FeeAccountRequired);
+
+    infos[11] = token_account(Pubkey::new_unique(), treasury, mint, 0);
+    run_with_args::<TransferSPL, _>(leak(infos), &[], (500u64, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, 500, 0, None, None)
+    })
+    .unwrap();
+
+    let fee = &emitted::<FeeCollected>()[0];
+    assert_eq!((fee.amount, fee.fee), (500, 5));
+    assert_eq!(emitted::<TransferExecuted>()[0].amount, 500);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 505);
+}
+
+#[test]
+fn unfreeze_counts_only_guardians_that_signed_the_unfreeze_message() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 3;
+    wallet.unfreeze_threshold = 2;
+    wallet.is_frozen = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let guardians = leak(
+        (0..3)
+            .map(|index| guardian_account(wallet_info.key, index, keys[index as usize], GuardianType::Email, 1))
+            .collect(),
+    );
+    let message = operation_message(OperationType::Unfreeze, wallet_info.key, wallet.nonce, &[]);
+    let stale = operation_message(OperationType::Unfreeze, wallet_info.key, wallet.nonce + 1, &[]);
+
+    let result = unfreeze_wallet_signed(&wallet_info, guardians, &[(keys[0], message), (keys[1], stale)]);
+    assert_error(result, WalletError::InsufficientApprovals);
+    let result = unfreeze_wallet_signed(&wallet_info, guardians, &[(keys[0], message), (keys[0], message)]);
+    assert_error(result, WalletError::InsufficientApprovals);
+
+    unfreeze_wallet_signed(&wallet_info, guardians, &[(keys[0], message), (keys[2], message)]).unwrap();
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert!(!wallet.is_frozen);
+    assert_eq!(wallet.nonce, 1);
+}
+
+// ============ Owner index ============
+
+/// An OwnerIndex PDA as `init_if_needed` leaves it on first use: allocated
+/// and owned by the program, with every field zeroed
+fn owner_index_account(owner: &Pubkey, len: u8) -> AccountInfo<'static> {
+    let (key, bump) = Pubkey::find_program_address(&[b"owner_index", owner.as_ref()], &crate::ID);
+    let index = OwnerIndex {
+        owner: if len == 0 { Pubkey::default() } else { *owner },
+        len,
+        wallet_ids: std::array::from_fn(|slot| if slot < len as usize { [slot as u8 + 1; 32] } else { [0; 32] }),
+        unindexed_count: 0,
+        bump: if len == 0 { 0 } else { bump },
+    };
+    program_account(key, &index, 8 + std::mem::size_of::<OwnerIndex>())
+}
+
+fn initialize_wallet(owner: Pubkey, index_info: &AccountInfo<'static>, wallet_id: [u8; 32]) -> Result<()> {
+    initialize_wallet_with_delay(owner, index_info, wallet_id, 86_400)
+}
+
+fn initialize_wallet_with_delay(
+    owner: Pubkey,
+    index_info: &AccountInfo<'static>,
+    wallet_id: [u8; 32],
+    recovery_delay: i64,
+) -> Result<()> {
+    initialize_wallet_as(signer(owner), index_info.clone(), wallet_id, recovery_delay)
+}
+
+/// initialize_wallet for `owner_info`, which only signs if it is a signer
+fn initialize_wallet_as(
+    owner_info: AccountInfo<'static>,
+    index_info: AccountInfo<'static>,
+    wallet_id: [u8; 32],
+    recovery_delay: i64,
+) -> Result<()> {
+    let (key, _) = Pubkey::find_program_address(&[b"wallet", wallet_id.as_ref()], &crate::ID);
+    let infos = leak(vec![
+        uninitialized_account(key, 8 + std::mem::size_of::<SmartWallet>() + 100 + 32 * MAX_OWNERS),
+        owner_info,
+        account_info(Pubkey::new_unique(), system_program::ID, 0, vec![], false, false),
+        index_info,
+        payer(),
+        system_program_account(),
+    ]);
+    run_with_args::<InitializeWallet, _>(infos, &[], wallet_id, |ctx| {
+        leet_wallet::initialize_wallet(ctx, wallet_id, 1, 1_000, recovery_delay)
+    })
+}
+
+#[test]
+fn owner_index_lists_every_wallet_created_for_an_owner() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, 0);
+
+    initialize_wallet(owner, &index_info, [7; 32]).unwrap();
+    initialize_wallet(owner, &index_info, [8; 32]).unwrap();
+
+    let index: OwnerIndex = read_account(&index_info);
+    assert_eq!(index.owner, owner);
+    assert_eq!(&index.wallet_ids[..index.len as usize], &[[7; 32], [8; 32]]);
+    assert_eq!(emitted::<OwnerIndexEntryAdded>().len(), 2);
+}
+
+#[test]
+fn full_owner_index_counts_the_overflow_instead_of_failing() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, OWNER_INDEX_CAPACITY as u8);
+
+    initialize_wallet(owner, &index_info, [0xee; 32]).unwrap();
+
+    let index: OwnerIndex = read_account(&index_info);
+    assert_eq!((index.len as usize, index.unindexed_count), (OWNER_INDEX_CAPACITY, 1));
+    assert_eq!(emitted::<OwnerIndexFull>().len(), 1);
+}
+
+#[test]
+fn only_a_signing_owner_is_indexed() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, 0);
+    let unsigned = || account_info(owner, system_program::ID, 0, vec![], false, false);
+
+    let result = initialize_wallet_as(unsigned(), index_info.clone(), [1; 32], 86_400);
+    assert_error(result, WalletError::Unauthorized);
+    initialize_wallet_as(unsigned(), absent(), [2; 32], 86_400).unwrap();
+    assert_eq!(read_account::<OwnerIndex>(&index_info).len, 0);
+}
+
+#[test]
+fn owner_removes_any_entry_from_their_own_index() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, 2);
+    let remove = |wallet_id: [u8; 32]| {
+        let infos = leak(vec![index_info.clone(), signer(owner)]);
+        run_with_args::<RemoveFromOwnerIndex, _>(infos, &[], wallet_id, |ctx| {
+            leet_wallet::remove_from_owner_index(ctx, wallet_id)
+        })
+    };
+
+    remove([1; 32]).unwrap();
+    assert_error(remove([1; 32]), WalletError::WalletNotIndexed);
+    let index: OwnerIndex = read_account(&index_info);
+    assert_eq!(&index.wallet_ids[..index.len as usize], &[[2; 32]]);
+    assert_eq!(emitted::<OwnerIndexEntryRemoved>()[0].wallet_id, [1; 32]);
+}
+
+// ============ Observe-only limits ============
+
+fn set_observe_only(
+    wallet_info: &AccountInfo<'static>,
+    observe_only: bool,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+    run::<UpdateConfig, _>(infos, co_signers, |ctx| leet_wallet::set_observe_only(ctx, observe_only))
+}
+
+/// A transfer-ready wallet with one guardian, which observe mode needs
+fn guarded_wallet_pda() -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = 1;
+    wallet.guardian_count = 1;
+    wallet_pda(wallet)
+}
+
+#[test]
+fn observe_mode_lets_a_violating_transfer_through_with_a_warning() {
+    let wallet_info = guarded_wallet_pda();
+
+    assert_error(set_observe_only(&wallet_info, true, &[]), WalletError::InsufficientApprovals);
+    set_observe_only(&wallet_info, true, co_signers(wallet_info.key, 1)).unwrap();
+    transfer_spl(&wallet_info, 1_500).unwrap();
+
+    let warnings = emitted::<LimitWouldBlock>();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].rule == LimitRule::Daily);
+    assert_eq!(warnings[0].amount, 1_500);
+
+    set_observe_only(&wallet_info, false, &[]).unwrap();
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::DailyLimitExceeded);
+}
+
+#[test]
+fn observe_mode_keeps_the_lifetime_cap_enforced() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.observe_only = true;
+    wallet.lifetime_limit = Some(500);
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(transfer_spl(&wallet_info, 600), WalletError::LifetimeLimitExceeded);
+    assert!(emitted::<LimitWouldBlock>().is_empty());
+}
+
+#[test]
+fn observe_mode_keeps_freezes_enforced() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.observe_only = true;
+    wallet.is_frozen = true;
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::WalletFrozen);
+    assert!(emitted::<LimitWouldBlock>().is_empty());
+}
+
+// ============ Aggregated recovery ============
+
+/// The group key is the RFC 8032 test 1 key; the signature was produced
+/// independently over the challenge `sha256("recovery challenge")`.
+#[test]
+fn recovery_accepts_a_known_aggregate_signature() {
+    let group_key = Pubkey::new_from_array(hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"));
+    let challenge: [u8; 32] = hex("3c1068dffb0f5d80292c9ebcdcd87476097bf6db32c75f47753afa3ae7195671");
+    let signature: [u8; 64] = hex(
+        "cbb12a24e484e0c88df935c7079346e9c6a5e131e54153d9dc49fa9aa98e10b0\
+         aef783efe34740d215d06d43969442ca20376da03d6074be551805a0ff38870c",
+    );
+
+    let wallet_info = pending_recovery_wallet(2);
+    let mut session = pending_recovery(&wallet_info);
+    session.challenge = challenge;
+    write_account(&recovery_session(wallet_info.key), &session);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.guardian_group_key = Some(group_key);
+    wallet.guardian_group_threshold = 1;
+    write_account(&wallet_info, &wallet);
+
+    // A group that signs with fewer guardians than recovery needs is refused
+    let result = execute_recovery_aggregated(&wallet_info, group_key, signature, signature);
+    assert_error(result, WalletError::InsufficientApprovals);
+
+    wallet.guardian_group_threshold = 2;
+    write_account(&wallet_info, &wallet);
+    let mut tampered = signature;
+    tampered[0] ^= 1;
+    let result = execute_recovery_aggregated(&wallet_info, group_key, signature, tampered);
+    assert_error(result, WalletError::InvalidSignature);
+    // No per-guardian approvals were recorded, so the old path still fails
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+
+    execute_recovery_aggregated(&wallet_info, group_key, signature, signature).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).authority, session.new_authority);
+}
+
+// ============ Spending disabled ============
+
+#[test]
+fn zero_daily_limit_disables_spending_with_its_own_error() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_limit = 0;
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::SpendingDisabled);
+    assert_eq!(emitted::<SpendingDisabledAttempt>().len(), 1);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.daily_limit = 100;
+    write_account(&wallet_info, &wallet);
+    assert_error(transfer_spl(&wallet_info, 101), WalletError::DailyLimitExceeded);
+    assert_eq!(emitted::<SpendingDisabledAttempt>().len(), 1);
+}
+
+// ============ Idempotency keys ============
+
+#[test]
+fn retried_transfer_with_the_same_key_moves_funds_once() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let transfer = |key| transfer_spl_with_reference(&wallet_info, 1_000_000, 100, None, Some(key));
+
+    transfer([1; 16]).unwrap();
+    assert_error(transfer([1; 16]), WalletError::DuplicateRequest);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 100);
+
+    transfer([2; 16]).unwrap();
+    assert_error(transfer([0; 16]), WalletError::InvalidIdempotencyKey);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 200);
+}
+
+// ============ Recovery permission ============
+
+fn set_guardian_recovery_permission(
+    wallet_info: &AccountInfo<'static>,
+    guardian_info: &AccountInfo<'static>,
+    allowed: bool,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), guardian_info.clone(), signer(wallet.authority)]);
+    run::<ManageGuardian, _>(infos, co_signers, |ctx| leet_wallet::set_guardian_recovery_permission(ctx, allowed))
+}
+
+#[test]
+fn alert_only_email_guardian_does_not_count_toward_quorum() {
+    let wallet_info = pending_recovery_wallet(2);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.recovery_pending = false;
+    write_account(&wallet_info, &wallet);
+
+    let email_key = Pubkey::new_unique();
+    let email = guardian_account(wallet_info.key, 0, email_key, GuardianType::Email, 1);
+    let result = set_guardian_recovery_permission(&wallet_info, &email, false, co_signers(wallet_info.key, 1));
+    assert_error(result, WalletError::InsufficientApprovals);
+    set_guardian_recovery_permission(&wallet_info, &email, false, co_signers(wallet_info.key, 2)).unwrap();
+    assert!(!read_account::<Guardian>(&email).can_approve_recovery);
+
+    wallet.recovery_pending = true;
+    write_account(&wallet_info, &wallet);
+    let challenge = pending_recovery(&wallet_info).challenge;
+    let result = approve_recovery_signing(&wallet_info, email.clone(), email_key, challenge);
+    assert_error(result, WalletError::GuardianCannotApprove);
+    // The quorum can't be reshaped while the recovery is in flight
+    let result = set_guardian_recovery_permission(&wallet_info, &email, true, co_signers(wallet_info.key, 2));
+    assert_error(result, WalletError::RecoveryAlreadyPending);
+
+    approve_recovery_as(&wallet_info, 1, GuardianType::Hardware, 1).unwrap();
+    assert_error(execute_recovery(&wallet_info), WalletError::InsufficientApprovals);
+    approve_recovery_as(&wallet_info, 2, GuardianType::Institution, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+}
+
+// ============ Recovery sessions ============
+
+#[test]
+fn recovery_session_opens_on_initiate_and_closes_on_execute() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 1;
+    wallet.guardian_threshold = 1;
+    wallet.recovery_threshold = 1;
+    wallet.total_guardian_weight = 1;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let session_info = recovery_session(wallet_info.key);
+
+    initiate_recovery(&wallet_info).unwrap();
+    assert!(read_account::<SmartWallet>(&wallet_info).recovery_pending);
+    assert_eq!(pending_recovery(&wallet_info).wallet, *wallet_info.key);
+    let rent = session_info.lamports();
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    set_clock(NOW + wallet.recovery_delay);
+    let infos = execute_recovery_accounts(&wallet_info, instructions_sysvar(&[]));
+    run::<ExecuteRecovery, _>(infos, &[], |ctx| leet_wallet::execute_recovery(ctx, None)).unwrap();
+
+    assert!(!read_account::<SmartWallet>(&wallet_info).recovery_pending);
+    assert_eq!((session_info.lamports(), infos[3].lamports()), (0, rent));
+}
+
+#[test]
+fn stale_session_left_by_panic_is_closed_to_its_payer() {
+    let wallet_info = pending_recovery_wallet(1);
+    let session_info = recovery_session(wallet_info.key);
+    let rent = session_info.lamports();
+    let payer = account_info(pending_recovery(&wallet_info).payer, system_program::ID, 0, vec![], false, true);
+    let close = leak(vec![wallet_info.clone(), session_info.clone(), payer]);
+
+    // A live recovery's session can't be closed from under it
+    let result = run::<CloseStaleRecovery, _>(close, &[], leet_wallet::close_stale_recovery);
+    assert_error(result, WalletError::RecoveryAlreadyPending);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.panic_enabled = true;
+    write_account(&wallet_info, &wallet);
+    let caller = leak(vec![wallet_info.clone(), signer(Pubkey::new_unique())]);
+    run::<Panic, _>(caller, co_signers(wallet_info.key, 1), |ctx| leet_wallet::panic(ctx, Pubkey::new_unique(), false))
+        .unwrap();
+
+    assert_eq!(emitted::<RecoveryCancelled>()[0].reason, RecoveryCancelReason::Superseded);
+
+    run::<CloseStaleRecovery, _>(close, &[], leet_wallet::close_stale_recovery).unwrap();
+    assert_eq!((session_info.lamports(), close[2].lamports()), (0, rent));
+    assert_eq!(emitted::<RecoverySessionClosed>().len(), 1);
+}
+
+#[test]
+fn panic_needs_a_guardian_even_at_threshold_zero() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+    let enable = run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_panic_enabled(ctx, true));
+    assert_error(enable, WalletError::InvalidThreshold);
+
+    // A wallet left with panic on and a zero threshold still needs a co-signer
+    wallet.panic_enabled = true;
+    wallet.guardian_count = 1;
+    write_account(&wallet_info, &wallet);
+    let panic = |co_signers: &'static [AccountInfo<'static>]| {
+        let caller = leak(vec![wallet_info.clone(), signer(Pubkey::new_unique())]);
+        run::<Panic, _>(caller, co_signers, |ctx| leet_wallet::panic(ctx, Pubkey::new_unique(), false))
+    };
+    assert_error(panic(&[]), WalletError::InsufficientApprovals);
+    panic(co_signers(wallet_info.key, 1)).unwrap();
+    assert!(read_account::<SmartWallet>(&wallet_info).is_frozen);
+}
+
+// ============ Execute payload cap ============
+
+#[test]
+fn oversized_execute_payload_is_rejected_before_the_nonce() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.max_exec_payload_len = b"payload".len() as u16 - 1;
+    let wallet_info = wallet_pda(wallet);
+    let message = operation_message(OperationType::Execute, wallet_info.key, 0, b"payload");
+
+    assert_error(execute_transaction(&wallet_info, message, 0), WalletError::PayloadTooLarge);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).exec_nonce, 0);
+
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(read_account::<SmartWallet>(&wallet_info).authority),
+    ]);
+    let result = run::<UpdateConfig, _>(infos, &[], |ctx| {
+        leet_wallet::set_max_exec_payload_len(ctx, MAX_EXEC_PAYLOAD_LEN + 1)
+    });
+    assert_error(result, WalletError::InvalidPayloadLimit);
+    // The one 64-byte signature counts against the cap too
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_max_exec_payload_len(ctx, 63)).unwrap();
+    assert_error(execute_transaction(&wallet_info, message, 0), WalletError::PayloadTooLarge);
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_max_exec_payload_len(ctx, 64)).unwrap();
+    execute_transaction(&wallet_info, message, 0).unwrap();
+}
+
+// ============ Owner veto ============
+
+fn heartbeat(wallet_info: &AccountInfo<'static>) {
+    let owner = read_account::<SmartWallet>(wallet_info).owner;
+    let infos = leak(vec![wallet_info.clone(), signer(owner)]);
+    run::<OwnerAction, _>(infos, &[], leet_wallet::heartbeat).unwrap();
+}
+
+#[test]
+fn owner_heartbeat_after_initiation_cancels_the_recovery() {
+    let wallet_info = pending_recovery_wallet(1);
+    let session_info = recovery_session(wallet_info.key);
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.require_owner_inactivity = true;
+    write_account(&wallet_info, &wallet);
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+
+    heartbeat(&wallet_info);
+    execute_recovery(&wallet_info).unwrap();
+
+    let wallet_after: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet_after.authority, wallet.authority);
+    assert!(!wallet_after.recovery_pending);
+    assert_eq!(session_info.lamports(), 0);
+    let cancelled = emitted::<RecoveryCancelled>();
+    assert_eq!((cancelled[0].reason, cancelled[0].cancelled_at), (RecoveryCancelReason::OwnerActivity, NOW));
+    assert_eq!(wallet_after.last_recovery_cancel_reason, Some(RecoveryCancelReason::OwnerActivity));
+}
+
+#[test]
+fn owner_heartbeat_does_not_block_recovery_unless_required() {
+    let wallet_info = pending_recovery_wallet(1);
+    let new_authority = pending_recovery(&wallet_info).new_authority;
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+
+    heartbeat(&wallet_info);
+    execute_recovery(&wallet_info).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).authority, new_authority);
+    assert!(emitted::<RecoveryCancelled>().is_empty());
+}
+
+// ============ Recovery delay bounds ============
+
+#[test]
+fn recovery_delay_must_lie_within_zero_to_ninety_days() {
+    let owner = Pubkey::new_unique();
+    let index_info = owner_index_account(&owner, 0);
+    let initialize = |id, delay| initialize_wallet_with_delay(owner, &index_info, [id; 32], delay);
+
+    for (id, delay) in [(1, -1), (2, MAX_RECOVERY_DELAY + 1), (3, i64::MAX)] {
+        assert_error(initialize(id, delay), WalletError::InvalidRecoveryDelay);
+    }
+    initialize(4, 0).unwrap();
+    initialize(5, MAX_RECOVERY_DELAY).unwrap();
+    assert_eq!(MAX_RECOVERY_DELAY, 90 * 86400);
+}
+
+// ============ Recovery delay updates ============
+
+fn update_recovery_delay(
+    wallet_info: &AccountInfo<'static>,
+    new_delay: i64,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+    run::<UpdateConfig, _>(infos, co_signers, |ctx| leet_wallet::update_recovery_delay(ctx, new_delay))
+}
+
+fn apply_recovery_delay(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    run::<ApplyRecoveryDelay, _>(leak(vec![wallet_info.clone()]), &[], leet_wallet::apply_recovery_delay)
+}
+
+fn delay_wallet() -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.recovery_delay = 86400;
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE)
+}
+
+#[test]
+fn lengthening_the_recovery_delay_applies_immediately() {
+    let wallet_info = delay_wallet();
+
+    update_recovery_delay(&wallet_info, 7 * 86400, &[]).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.recovery_delay, 7 * 86400);
+    assert!(wallet.pending_recovery_delay.is_none());
+    assert_eq!(emitted::<RecoveryDelayUpdated>()[0].effective_at, NOW);
+}
+
+#[test]
+fn shortening_the_recovery_delay_waits_out_the_current_delay() {
+    let wallet_info = delay_wallet();
+
+    update_recovery_delay(&wallet_info, 3600, &[]).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).recovery_delay, 86400);
+    assert_eq!(emitted::<RecoveryDelayUpdated>()[0].effective_at, NOW + 86400);
+
+    set_clock(NOW + 86399);
+    assert_error(apply_recovery_delay(&wallet_info), WalletError::DelayChangeTimelocked);
+    set_clock(NOW + 86400);
+    apply_recovery_delay(&wallet_info).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.recovery_delay, 3600);
+    assert!(wallet.pending_recovery_delay.is_none());
+}
+
+#[test]
+fn guardians_can_shorten_the_recovery_delay_at_once() {
+    let wallet_info = delay_wallet();
+
+    update_recovery_delay(&wallet_info, 3600, co_signers(wallet_info.key, 2)).unwrap();
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).recovery_delay, 3600);
+    assert_error(apply_recovery_delay(&wallet_info), WalletError::NoDelayChangePending);
+}
+
+#[test]
+fn recovery_delay_is_frozen_while_a_recovery_is_pending() {
+    let wallet_info = delay_wallet();
+    update_recovery_delay(&wallet_info, 3600, &[]).unwrap();
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.recovery_pending = true;
+    write_account(&wallet_info, &wallet);
+
+    assert_error(update_recovery_delay(&wallet_info, 7 * 86400, &[]), WalletError::RecoveryAlreadyPending);
+    set_clock(NOW + 86400);
+    assert_error(apply_recovery_delay(&wallet_info), WalletError::RecoveryAlreadyPending);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).recovery_delay, 86400);
+}
+
+// ============ Reference budget ============
+
+/// A PriceQuote owned by `price_program` pricing a whole token of a 0-decimal
+/// `mint` at `price` / PRICE_SCALE
+fn price_quote(
+    price_program: Pubkey,
+    mint: Pubkey,
+    reference_mint: Pubkey,
+    price: u64,
+    published_at: i64,
+) -> AccountInfo<'static> {
+    price_quote_with_decimals(price_program, mint, 0, reference_mint, price, published_at)
+}
+
+fn price_quote_with_decimals(
+    price_program: Pubkey,
+    mint: Pubkey,
+    decimals: u8,
+    reference_mint: Pubkey,
+    price: u64,
+    published_at: i64,
+) -> AccountInfo<'static> {
+    let quote = PriceQuote {
+        mint,
+        reference_mint,
+        decimals,
+        price,
+        published_at,
+    };
+    account_info(Pubkey::new_unique(), price_program, 1, quote.try_to_vec().unwrap(), false, false)
+}
+
+/// transfer_spl of `mint`, priced through `quote`
+fn transfer_priced(
+    wallet_info: &AccountInfo<'static>,
+    mint: Pubkey,
+    quote: AccountInfo<'static>,
+    amount: u64,
+) -> Result<()> {
+    let frozen_mint = unfrozen_mint(wallet_info.key, &mint);
+    let mut infos = transfer_accounts(wallet_info, mint, 10_000_000_000, frozen_mint, absent()).to_vec();
+    infos[9] = quote;
+    run_with_args::<TransferSPL, _>(leak(infos), &[], (amount, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, amount, 0, None, None)
+    })
+}
+
+/// Point this thread's GlobalConfig at `price_program`
+fn pin_price_program(price_program: Pubkey) {
+    let info = global_config();
+    let mut config: GlobalConfig = read_account(&info);
+    config.price_program = price_program;
+    write_account(&info, &config);
+}
+
+#[test]
+fn reference_budget_caps_the_priced_value_of_transfers() {
+    let (mint, reference_mint, price_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_limit = 1_000_000;
+    wallet.reference_budget = Some(ReferenceBudget {
+        reference_mint,
+        daily_limit: 1_000,
+        max_price_age: 60,
+    });
+    let wallet_info = wallet_pda(wallet);
+    pin_price_program(price_program);
+    // 1.5 reference units per token
+    let quote = |published_at| price_quote(price_program, mint, reference_mint, 3 * PRICE_SCALE / 2, published_at);
+
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::PriceQuoteRequired);
+    assert_error(transfer_priced(&wallet_info, mint, quote(NOW - 61), 1), WalletError::StalePriceQuote);
+    let foreign = price_quote(Pubkey::new_unique(), mint, reference_mint, 1, NOW);
+    assert_error(transfer_priced(&wallet_info, mint, foreign, 1), WalletError::InvalidPriceQuote);
+
+    transfer_priced(&wallet_info, mint, quote(NOW - 60), 666).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).reference_spent, 999);
+    // 1 token is worth 1.5 units, rounded up to 2
+    assert_error(transfer_priced(&wallet_info, mint, quote(NOW), 1), WalletError::ReferenceLimitExceeded);
+}
+
+#[test]
+fn reference_budget_prices_mints_of_different_decimals_alike() {
+    let (reference_mint, price_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (six, nine) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_limit = u64::MAX;
+    wallet.reference_budget = Some(ReferenceBudget {
+        reference_mint,
+        daily_limit: 2_000_000,
+        max_price_age: 60,
+    });
+    let wallet_info = wallet_pda(wallet);
+    pin_price_program(price_program);
+    // Both tokens trade at one 6-decimal reference unit apiece
+    let quote = |mint, decimals| {
+        price_quote_with_decimals(price_program, mint, decimals, reference_mint, 1_000_000 * PRICE_SCALE, NOW)
+    };
+
+    transfer_priced(&wallet_info, six, quote(six, 6), 1_000_000).unwrap();
+    transfer_priced(&wallet_info, nine, quote(nine, 9), 1_000_000_000).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).reference_spent, 2_000_000);
+
+    // A single base unit of either is worth under one reference unit but still counts
+    assert_error(transfer_priced(&wallet_info, nine, quote(nine, 9), 1), WalletError::ReferenceLimitExceeded);
+    assert_error(transfer_priced(&wallet_info, six, quote(six, 6), 1), WalletError::ReferenceLimitExceeded);
+}
+
+#[test]
+fn loosening_a_reference_budget_waits_out_the_timelock() {
+    let budget = |daily_limit| ReferenceBudget {
+        reference_mint: Pubkey::new_unique(),
+        daily_limit,
+        max_price_age: 60,
+    };
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let current = budget(1_000);
+    wallet.reference_budget = Some(current);
+    let infos = update_config_accounts(&wallet);
+    let set_budget = |budget: Option<ReferenceBudget>| {
+        run::<UpdateConfig, _>(infos, &[], move |ctx| leet_wallet::set_reference_budget(ctx, budget))
+    };
+    let apply = || {
+        let accounts = leak(vec![infos[0].clone()]);
+        run::<ApplyReferenceBudget, _>(accounts, &[], leet_wallet::apply_reference_budget)
+    };
+
+    let raised = ReferenceBudget { daily_limit: 5_000, ..current };
+    set_budget(Some(raised)).unwrap();
+    let queued: SmartWallet = read_account(&infos[0]);
+    assert_eq!(queued.reference_budget.unwrap().daily_limit, 1_000);
+    assert_eq!(queued.pending_reference_budget.unwrap().effective_at, NOW + LIMIT_INCREASE_DELAY);
+    assert_error(apply(), WalletError::LimitChangeTimelocked);
+
+    set_clock(NOW + LIMIT_INCREASE_DELAY);
+    apply().unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).reference_budget.unwrap().daily_limit, 5_000);
+    assert_error(apply(), WalletError::NoLimitChangePending);
+
+    // Clearing the budget loosens it too, while tightening applies at once
+    set_budget(None).unwrap();
+    assert!(read_account::<SmartWallet>(&infos[0]).reference_budget.is_some());
+    set_budget(Some(ReferenceBudget { daily_limit: 500, ..raised })).unwrap();
+    let tightened: SmartWallet = read_account(&infos[0]);
+    assert_eq!(tightened.reference_budget.unwrap().daily_limit, 500);
+    assert!(tightened.pending_reference_budget.is_none());
+}
+
+// ============ Recovery cancellation ============
+
+#[test]
+fn owner_cancel_closes_the_session_as_a_veto() {
+    let wallet_info = pending_recovery_wallet(1);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    let session_info = recovery_session(wallet_info.key);
+    let rent = session_info.lamports();
+    let payer = account_info(pending_recovery(&wallet_info).payer, system_program::ID, 0, vec![], false, true);
+
+    let stranger = leak(vec![wallet_info.clone(), session_info.clone(), payer.clone(), signer(wallet.authority)]);
+    assert!(run::<CancelRecovery, _>(stranger, &[], leet_wallet::cancel_recovery).is_err());
+
+    let infos = leak(vec![wallet_info.clone(), session_info.clone(), payer, signer(wallet.owner)]);
+    run::<CancelRecovery, _>(infos, &[], leet_wallet::cancel_recovery).unwrap();
+
+    assert!(!read_account::<SmartWallet>(&wallet_info).recovery_pending);
+    assert_eq!((session_info.lamports(), infos[2].lamports()), (0, rent));
+    assert_eq!(emitted::<RecoveryCancelled>()[0].reason, RecoveryCancelReason::OwnerVeto);
+}
+
+#[test]
+fn executed_recovery_emits_no_cancellation() {
+    let wallet_info = pending_recovery_wallet(1);
+
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    execute_recovery(&wallet_info).unwrap();
+
+    assert!(emitted::<RecoveryCancelled>().is_empty());
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).last_recovery_cancel_reason, None);
+}
+
+// ============ Recovery session deadline ============
+
+fn expire_recovery(wallet_info: &AccountInfo<'static>) -> Result<()> {
+    let payer = account_info(pending_recovery(wallet_info).payer, system_program::ID, 0, vec![], false, true);
+    let infos = leak(vec![wallet_info.clone(), recovery_session(wallet_info.key), payer]);
+    run::<ExpireRecovery, _>(infos, &[], leet_wallet::expire_recovery)
+}
+
+#[test]
+fn recovery_past_the_session_deadline_must_restart() {
+    let wallet_info = pending_recovery_wallet(1);
+    let expires_at = pending_recovery(&wallet_info).expires_at;
+    approve_recovery_as(&wallet_info, 0, GuardianType::Email, 1).unwrap();
+    assert_error(expire_recovery(&wallet_info), WalletError::RecoverySessionNotExpired);
+
+    set_clock(expires_at + 1);
+    assert_error(execute_recovery(&wallet_info), WalletError::RecoverySessionExpired);
+    assert_error(approve_recovery_as(&wallet_info, 1, GuardianType::Email, 1), WalletError::RecoverySessionExpired);
+
+    let session_info = recovery_session(wallet_info.key);
+    expire_recovery(&wallet_info).unwrap();
+    assert_eq!(session_info.lamports(), 0);
+    assert_eq!(emitted::<RecoveryCancelled>()[0].reason, RecoveryCancelReason::Expired);
+
+    initiate_recovery(&wallet_info).unwrap();
+    assert_eq!(pending_recovery(&wallet_info).expires_at, expires_at + 1 + DEFAULT_RECOVERY_SESSION_GRACE);
+}
+
+// ============ Weekday limits ============
+
+#[test]
+fn weekend_caps_apply_on_the_utc_weekend() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let authority = read_account::<SmartWallet>(&wallet_info).authority;
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(authority)]);
+    let limits = [1_000, 1_000, 1_000, 1_000, 1_000, 10, 0];
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_weekday_limits(ctx, Some(limits))).unwrap();
+
+    // NOW is a Tuesday
+    assert_eq!(day_of_week(NOW), 1);
+    transfer_spl(&wallet_info, 500).unwrap();
+
+    // The Saturday cap starts at UTC midnight, with the daily rollover
+    set_clock((NOW / 86400 + 4) * 86400 - 1);
+    transfer_spl(&wallet_info, 400).unwrap();
+    set_clock((NOW / 86400 + 4) * 86400);
+    assert_error(transfer_spl(&wallet_info, 11), WalletError::DailyLimitExceeded);
+    transfer_spl(&wallet_info, 10).unwrap();
+
+    set_clock((NOW / 86400 + 5) * 86400);
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::DailyLimitExceeded);
+
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_weekday_limits(ctx, None)).unwrap();
+    transfer_spl(&wallet_info, 1_000).unwrap();
+}
+
+#[test]
+fn loosening_weekday_caps_needs_guardian_co_signers() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_threshold = 1;
+    wallet.guardian_count = 1;
+    wallet.weekday_limits = Some([500; 7]);
+    let infos = update_config_accounts(&wallet);
+    let set = |limits, co_signers| {
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::set_weekday_limits(ctx, limits))
+    };
+
+    set(Some([400; 7]), &[]).unwrap();
+    let mut raised = [400; 7];
+    raised[5] = 401;
+    assert_error(set(Some(raised), &[]), WalletError::InsufficientApprovals);
+    assert_error(set(None, &[]), WalletError::InsufficientApprovals);
+
+    set(None, co_signers(infos[0].key, 1)).unwrap();
+    assert!(read_account::<SmartWallet>(&infos[0]).weekday_limits.is_none());
+}
+
+// ============ Source account ownership ============
+
+#[test]
+fn foreign_source_token_account_is_rejected() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let mint = Pubkey::new_unique();
+    let frozen_mint = unfrozen_mint(wallet_info.key, &mint);
+    let mut infos = transfer_accounts(&wallet_info, mint, 1_000_000, frozen_mint, absent()).to_vec();
+    infos[2] = token_account(Pubkey::new_unique(), Pubkey::new_unique(), mint, 1_000_000);
+
+    let result = run_with_args::<TransferSPL, _>(leak(infos), &[], (10u64, 0u8), |ctx| {
+        leet_wallet::transfer_spl(ctx, 10, 0, None, None)
+    });
+    assert_error(result, WalletError::InvalidSourceAccount);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 0);
+}
+
+// ============ Transfer review ============
+
+/// A wallet whose transfers of 500 or more wait an hour for guardian review
+fn review_wallet() -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 1;
+    wallet.review_window = 3600;
+    wallet.review_threshold = 500;
+    wallet_pda(wallet)
+}
+
+/// hold_transfer of `amount` to `to_info`, returning the HeldTransfer PDA
+fn hold_transfer(
+    wallet_info: &AccountInfo<'static>,
+    to_info: &AccountInfo<'static>,
+    amount: u64,
+) -> Result<AccountInfo<'static>> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let id = wallet.next_held_transfer_id.to_le_bytes();
+    let (key, _) = Pubkey::find_program_address(&[b"held_transfer", wallet_info.key.as_ref(), &id], &crate::ID);
+    let held_info = uninitialized_account(key, 8 + std::mem::size_of::<HeldTransfer>());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        held_info.clone(),
+        to_info.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run::<HoldTransfer, _>(infos, &[], |ctx| leet_wallet::hold_transfer(ctx, amount))?;
+    Ok(held_info)
+}
+
+/// The system account that paid for `held_info`, to take its rent back
+fn held_transfer_payer(held_info: &AccountInfo<'static>) -> AccountInfo<'static> {
+    let payer = read_account::<HeldTransfer>(held_info).payer;
+    account_info(payer, system_program::ID, 0, vec![], false, true)
+}
+
+#[test]
+fn guardian_veto_cancels_a_held_transfer() {
+    let wallet_info = review_wallet();
+    let mint = Pubkey::new_unique();
+    let to_info = token_account(Pubkey::new_unique(), Pubkey::new_unique(), mint, 0);
+
+    assert_error(transfer_spl(&wallet_info, 500), WalletError::TransferRequiresReview);
+    transfer_spl(&wallet_info, 499).unwrap();
+
+    let held_info = hold_transfer(&wallet_info, &to_info, 600).unwrap();
+    assert_eq!(emitted::<TransferHeld>()[0].releasable_at, NOW + 3600);
+
+    let guardian_key = Pubkey::new_unique();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        held_info.clone(),
+        guardian_account(wallet_info.key, 0, guardian_key, GuardianType::Email, 1),
+        signer(guardian_key),
+        held_transfer_payer(&held_info),
+    ]);
+    run::<GuardianVeto, _>(infos, &[], leet_wallet::guardian_veto).unwrap();
+
+    assert_eq!(held_info.lamports(), 0);
+    assert_eq!(emitted::<TransferVetoed>()[0].guardian, guardian_key);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.pending_item_count, wallet.daily_spent), (0, 499));
+}
+
+/// release_held_transfer of `held_info` to `to_info`, with `frozen_mint` as
+/// the mint's FrozenMint PDA
+fn release_held_transfer(
+    wallet_info: &AccountInfo<'static>,
+    held_info: &AccountInfo<'static>,
+    to_info: &AccountInfo<'static>,
+    frozen_mint: AccountInfo<'static>,
+) -> Result<()> {
+    let mint = read_account::<HeldTransfer>(held_info).mint;
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        held_info.clone(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        to_info.clone(),
+        frozen_mint,
+        absent(),
+        absent(),
+        absent(),
+        held_transfer_payer(held_info),
+        token_program(),
+    ]);
+    run::<ReleaseHeldTransfer, _>(infos, &[], leet_wallet::release_held_transfer)
+}
+
+#[test]
+fn unvetoed_held_transfer_executes_after_the_window() {
+    let wallet_info = review_wallet();
+    let mint = Pubkey::new_unique();
+    let to_info = token_account(Pubkey::new_unique(), Pubkey::new_unique(), mint, 0);
+    let held_info = hold_transfer(&wallet_info, &to_info, 600).unwrap();
+    let release = || release_held_transfer(&wallet_info, &held_info, &to_info, unfrozen_mint(wallet_info.key, &mint));
+
+    set_clock(NOW + 3599);
+    assert_error(release(), WalletError::ReviewWindowActive);
+
+    set_clock(NOW + 3600);
+    release().unwrap();
+
+    assert_eq!(held_info.lamports(), 0);
+    assert_eq!(emitted::<HeldTransferExecuted>()[0].amount, 600);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.pending_item_count, wallet.daily_spent), (0, 600));
+}
+
+#[test]
+fn held_transfer_of_a_frozen_mint_is_not_released() {
+    let wallet_info = review_wallet();
+    let mint_info = mint_account(6, 1_000_000);
+    let to_info = token_account(Pubkey::new_unique(), Pubkey::new_unique(), *mint_info.key, 0);
+    let held_info = hold_transfer(&wallet_info, &to_info, 600).unwrap();
+    let frozen_info = freeze_mint(&wallet_info, &mint_info);
+
+    set_clock(NOW + 3600);
+    let result = release_held_transfer(&wallet_info, &held_info, &to_info, frozen_info);
+    assert_error(result, WalletError::MintFrozen);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 0);
+}
+
+// ============ Limit penalty ============
+
+/// A wallet whose limit penalty keeps 50% of the daily limit for ten minutes
+fn penalty_wallet() -> AccountInfo<'static> {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 1;
+    wallet.limit_penalty_bps = 5_000;
+    wallet.limit_penalty_duration = 600;
+    wallet_pda(wallet)
+}
+
+#[test]
+fn guardian_penalty_halves_the_limit_until_it_expires() {
+    let wallet_info = penalty_wallet();
+    let guardian_key = Pubkey::new_unique();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        guardian_account(wallet_info.key, 0, guardian_key, GuardianType::Email, 1),
+        signer(guardian_key),
+    ]);
+    run::<GuardianFlag, _>(infos, &[], leet_wallet::apply_limit_penalty).unwrap();
+    assert_eq!(emitted::<LimitPenaltyApplied>()[0].until, NOW + 600);
+
+    assert_error(transfer_spl(&wallet_info, 501), WalletError::DailyLimitExceeded);
+    transfer_spl(&wallet_info, 500).unwrap();
+    set_clock(NOW + 599);
+    assert_error(transfer_spl(&wallet_info, 1), WalletError::DailyLimitExceeded);
+
+    // Same calendar day, so only the restored limit makes room
+    set_clock(NOW + 600);
+    transfer_spl(&wallet_info, 500).unwrap();
+    assert_eq!(emitted::<LimitPenaltyExpired>().len(), 1);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).limit_penalty_until, 0);
+}
+
+#[test]
+fn first_anomaly_applies_the_penalty_and_a_second_freezes() {
+    let wallet_info = penalty_wallet();
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.anomaly_threshold = 300;
+    wallet.anomaly_window = 3600;
+    write_account(&wallet_info, &wallet);
+
+    transfer_spl(&wallet_info, 301).unwrap();
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.daily_spent, wallet.limit_penalty_until, wallet.is_frozen), (0, NOW + 600, false));
+
+    transfer_spl(&wallet_info, 301).unwrap();
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.daily_spent, wallet.is_frozen), (0, true));
+}
+
+#[test]
+fn loosening_the_anomaly_breaker_needs_co_signers() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    wallet.anomaly_threshold = 300;
+    wallet.anomaly_window = 3600;
+    wallet.anomaly_window_start = NOW;
+    wallet.anomaly_window_spent = 200;
+    let infos = update_config_accounts(&wallet);
+    let set_anomaly = |co_signers, threshold, window| {
+        run::<UpdateConfig, _>(infos, co_signers, |ctx| leet_wallet::set_anomaly_threshold(ctx, threshold, window))
+    };
+
+    for (threshold, window) in [(0, 3600), (301, 3600), (300, 60)] {
+        assert_error(set_anomaly(&[], threshold, window), WalletError::InsufficientApprovals);
+    }
+
+    // Tightening needs no co-signers and keeps the window's spend
+    set_anomaly(&[], 250, 7200).unwrap();
+    let wallet: SmartWallet = read_account(&infos[0]);
+    assert_eq!((wallet.anomaly_threshold, wallet.anomaly_window_spent), (250, 200));
+
+    set_anomaly(co_signers(infos[0].key, 2), 0, 0).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&infos[0]).anomaly_threshold, 0);
+}
+
+// ============ Feature flags ============
+
+#[test]
+fn version_reports_the_features_a_wallet_enabled() {
+    let wallet_info = guarded_wallet_pda();
+    let get_version = || run::<ReadWallet, _>(leak(vec![wallet_info.clone()]), &[], leet_wallet::get_version).unwrap();
+    assert_eq!(get_version(), 0);
+
+    set_observe_only(&wallet_info, true, co_signers(wallet_info.key, 1)).unwrap();
+    let authority = read_account::<SmartWallet>(&wallet_info).authority;
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(authority)]);
+    run::<UpdateConfig, _>(infos, &[], |ctx| leet_wallet::set_weekday_limits(ctx, Some([1_000; 7]))).unwrap();
+
+    let expected = FEATURE_OBSERVE_ONLY | FEATURE_WEEKDAY_LIMITS;
+    assert_eq!(get_version(), expected);
+    let reported = &emitted::<WalletVersion>()[1];
+    assert_eq!((reported.program_version, reported.feature_flags), (VERSION, expected));
+}
+
+// ============ Transfers to new recipients ============
+
+fn associated_token_program() -> AccountInfo<'static> {
+    let mut info = account_info(anchor_spl::associated_token::ID, Pubkey::default(), 1, vec![], false, false);
+    info.executable = true;
+    info
+}
+
+/// transfer_spl_create_ata of `amount` to a fresh recipient, returning the
+/// recipient's associated token account
+fn transfer_to_new_recipient(wallet_info: &AccountInfo<'static>, amount: u64) -> Result<Pubkey> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let mint_info = mint_account(6, 1_000_000);
+    let mint = *mint_info.key;
+    let recipient = Pubkey::new_unique();
+    let ata = get_associated_token_address(&recipient, &mint);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        mint_info,
+        account_info(recipient, system_program::ID, 0, vec![], false, false),
+        account_info(ata, system_program::ID, 0, vec![], false, true),
+        unfrozen_mint(wallet_info.key, &mint),
+        absent(),
+        absent(),
+        absent(),
+        absent(),
+        signer(wallet.authority),
+        payer(),
+        token_program(),
+        associated_token_program(),
+        system_program_account(),
+    ]);
+    run_with_args::<TransferSPLCreateAta, _>(infos, &[], amount, |ctx| {
+        leet_wallet::transfer_spl_create_ata(ctx, amount)
+    })
+    .map(|_| ata)
+}
+
+#[test]
+fn transfer_creates_the_recipients_token_account() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let ata = transfer_to_new_recipient(&wallet_info, 10).unwrap();
+
+    let created = emitted::<AssociatedAccountCreated>();
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].token_account, ata);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!(wallet.daily_spent, 10);
+}
+
+#[test]
+fn transfer_to_a_new_recipient_shares_the_spend_accounting() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lifetime_limit = Some(500);
+    let wallet_info = wallet_pda(wallet);
+
+    transfer_to_new_recipient(&wallet_info, 300).unwrap();
+    transfer_spl(&wallet_info, 100).unwrap();
+    let spent: SmartWallet = read_account(&wallet_info);
+    assert_eq!((spent.daily_spent, spent.lifetime_spent, spent.transfer_nonce), (400, 400, 2));
+    assert_error(transfer_to_new_recipient(&wallet_info, 101), WalletError::LifetimeLimitExceeded);
+}
+
+// ============ PDA diagnostics ============
+
+#[test]
+fn verify_wallet_pda_flags_a_mismatched_wallet_id() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let wallet: SmartWallet = read_account(&wallet_info);
+    let verify = |wallet_id: [u8; 32]| {
+        run_with_args::<ReadWallet, _>(leak(vec![wallet_info.clone()]), &[], wallet_id, |ctx| {
+            leet_wallet::verify_wallet_pda(ctx, wallet_id)
+        })
+        .unwrap()
+    };
+
+    assert!(verify(wallet.wallet_id));
+    assert!(!verify(Pubkey::new_unique().to_bytes()));
+
+    let reports = emitted::<PdaVerified>();
+    assert_eq!((reports[0].expected, reports[0].stored_bump), (*wallet_info.key, wallet.bump));
+    assert_ne!(reports[1].expected, *wallet_info.key);
+    assert!(!reports[1].valid);
+}
+
+// ============ Freeze approvals ============
+
+/// A wallet whose owner and authority must both approve a freeze
+fn shared_custody_wallet() -> (SmartWallet, AccountInfo<'static>) {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.owners = vec![wallet.owner];
+    wallet.freeze_threshold = 2;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    (wallet, wallet_info)
+}
+
+fn approve_freeze(wallet_info: &AccountInfo<'static>, approver: Pubkey) -> Result<()> {
+    let infos = leak(vec![wallet_info.clone(), global_config(), absent(), signer(approver)]);
+    run::<ApproveFreeze, _>(infos, &[], |ctx| leet_wallet::approve_freeze(ctx, FreezeReason::Manual))
+}
+
+#[test]
+fn freeze_under_the_threshold_does_not_freeze() {
+    let (wallet, wallet_info) = shared_custody_wallet();
+    let accounts = leak(vec![wallet_info.clone(), signer(wallet.authority), absent()]);
+
+    assert_error(
+        run::<FreezeWallet, _>(accounts, &[], |ctx| leet_wallet::freeze_wallet(ctx, FreezeReason::Manual)),
+        WalletError::FreezeNeedsApprovals,
+    );
+    approve_freeze(&wallet_info, wallet.authority).unwrap();
+    assert_error(approve_freeze(&wallet_info, wallet.authority), WalletError::AlreadyApproved);
+    assert_error(approve_freeze(&wallet_info, Pubkey::new_unique()), WalletError::Unauthorized);
+
+    assert!(!read_account::<SmartWallet>(&wallet_info).is_frozen);
+    let approved = emitted::<FreezeApproved>();
+    assert_eq!((approved.len(), approved[0].approvals, approved[0].threshold), (1, 1, 2));
+    assert!(emitted::<WalletFrozen>().is_empty());
+}
+
+#[test]
+fn freeze_at_the_threshold_freezes() {
+    let (wallet, wallet_info) = shared_custody_wallet();
+
+    approve_freeze(&wallet_info, wallet.authority).unwrap();
+    approve_freeze(&wallet_info, wallet.owner).unwrap();
+
+    let frozen: SmartWallet = read_account(&wallet_info);
+    assert!(frozen.is_frozen && frozen.pending_freeze.is_none());
+    assert_eq!(emitted::<FreezeApproved>().len(), 2);
+    assert_eq!(emitted::<WalletFrozen>()[0].frozen_by, wallet.owner);
+}
+
+#[test]
+fn stale_freeze_approvals_expire() {
+    let (wallet, wallet_info) = shared_custody_wallet();
+
+    approve_freeze(&wallet_info, wallet.authority).unwrap();
+    set_clock(NOW + FREEZE_APPROVAL_WINDOW);
+    approve_freeze(&wallet_info, wallet.owner).unwrap();
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert!(!wallet.is_frozen);
+    assert_eq!(wallet.pending_freeze.unwrap().approvals, 1);
+}
+
+// ============ Spend reconciliation ============
+
+/// reconcile_spend signed by `caller` against the wallet's token account
+/// `account` holding `balance`
+fn reconcile_spend_as(
+    wallet_info: &AccountInfo<'static>,
+    caller: Pubkey,
+    account: Pubkey,
+    balance: u64,
+) -> Result<SpendReconciled> {
+    let token = token_account(account, *wallet_info.key, Pubkey::new_from_array([4; 32]), balance);
+    let infos = leak(vec![wallet_info.clone(), global_config(), token, signer(caller)]);
+    run::<ReconcileSpend, _>(infos, &[], leet_wallet::reconcile_spend)?;
+    Ok(emitted::<SpendReconciled>().pop().unwrap())
+}
+
+#[test]
+fn reconcile_reports_an_untracked_outflow() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let account = Pubkey::new_unique();
+    reconcile_spend_as(&wallet_info, wallet.authority, account, 1_000).unwrap();
+
+    // 300 left the account but only 100 went through the wallet
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.record_spend(100);
+    write_account(&wallet_info, &wallet);
+    let report = reconcile_spend_as(&wallet_info, Pubkey::new_unique(), account, 700).unwrap();
+
+    assert_eq!((report.recorded_spend, report.observed_outflow, report.discrepancy), (100, 300, 200));
+    assert!(!report.corrected);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).lifetime_spent, 100);
+}
+
+#[test]
+fn reconcile_charges_the_discrepancy_when_correcting() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.reconcile_corrects = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let account = Pubkey::new_unique();
+    reconcile_spend_as(&wallet_info, wallet.authority, account, 1_000).unwrap();
+
+    let report = reconcile_spend_as(&wallet_info, wallet.authority, account, 750).unwrap();
+
+    assert!(report.corrected);
+    let charged: SmartWallet = read_account(&wallet_info);
+    assert_eq!((charged.daily_spent, charged.lifetime_spent), (250, 250));
+    // The charge becomes part of the new baseline rather than reported again
+    assert_eq!(reconcile_spend_as(&wallet_info, wallet.authority, account, 750).unwrap().discrepancy, 0);
+}
+
+#[test]
+fn only_the_authority_pins_the_reconciled_account() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let account = Pubkey::new_unique();
+    reconcile_spend_as(&wallet_info, wallet.authority, account, 1_000).unwrap();
+
+    // Swapping in an emptier account would report a phantom outflow
+    let result = reconcile_spend_as(&wallet_info, Pubkey::new_unique(), Pubkey::new_unique(), 0);
+    assert_error(result.map(|_| ()), WalletError::Unauthorized);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).reconcile_account, account);
+}
+
+// ============ Program budgets ============
+
+/// A 500-token budget for `program_id` paying `destination`, on a wallet PDA
+fn program_budget(program_id: Pubkey, destination: Pubkey) -> (AccountInfo<'static>, AccountInfo<'static>) {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    let budget_info = program_budget_of(&wallet_info, program_id, destination);
+    (wallet_info, budget_info)
+}
+
+fn program_budget_of(
+    wallet_info: &AccountInfo<'static>,
+    program_id: Pubkey,
+    destination: Pubkey,
+) -> AccountInfo<'static> {
+    let (key, bump) =
+        Pubkey::find_program_address(&[b"pbudget", wallet_info.key.as_ref(), program_id.as_ref()], &crate::ID);
+    let budget = ProgramBudget {
+        wallet: *wallet_info.key,
+        program_id,
+        destination,
+        remaining: 500,
+        expires_at: NOW + 86400,
+        bump,
+    };
+    program_account(key, &budget, 8 + std::mem::size_of::<ProgramBudget>())
+}
+
+fn draw_program_budget(
+    wallet_info: &AccountInfo<'static>,
+    budget_info: &AccountInfo<'static>,
+    destination: Pubkey,
+    caller: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let frozen_mint = unfrozen_mint(wallet_info.key, &mint);
+    draw_program_budget_of_mint(wallet_info, budget_info, destination, caller, mint, frozen_mint, amount)
+}
+
+fn draw_program_budget_of_mint(
+    wallet_info: &AccountInfo<'static>,
+    budget_info: &AccountInfo<'static>,
+    destination: Pubkey,
+    caller: Pubkey,
+    mint: Pubkey,
+    frozen_mint: AccountInfo<'static>,
+    amount: u64,
+) -> Result<()> {
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        budget_info.clone(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        token_account(destination, Pubkey::new_unique(), mint, 0),
+        frozen_mint,
+        absent(),
+        absent(),
+        absent(),
+        signer(caller),
+        token_program(),
+    ]);
+    run_with_args::<DrawProgramBudget, _>(infos, &[], amount, |ctx| leet_wallet::draw_program_budget(ctx, amount))
+}
+
+#[test]
+fn only_the_budgeted_programs_pda_draws_to_the_pinned_destination() {
+    let program_id = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let (wallet_info, budget_info) = program_budget(program_id, destination);
+    let (caller, _) = Pubkey::find_program_address(&[BUDGET_CALLER_SEED], &program_id);
+
+    let result = draw_program_budget(&wallet_info, &budget_info, destination, Pubkey::new_unique(), 100);
+    assert_error(result, WalletError::Unauthorized);
+    let result = draw_program_budget(&wallet_info, &budget_info, Pubkey::new_unique(), caller, 100);
+    assert_error(result, WalletError::InvalidDestination);
+
+    draw_program_budget(&wallet_info, &budget_info, destination, caller, 100).unwrap();
+    assert_eq!(read_account::<ProgramBudget>(&budget_info).remaining, 400);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 100);
+}
+
+#[test]
+fn budget_draws_go_through_the_wallet_spend_checks() {
+    let program_id = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let (wallet_info, budget_info) = program_budget(program_id, destination);
+    let (caller, _) = Pubkey::find_program_address(&[BUDGET_CALLER_SEED], &program_id);
+    let mint_info = mint_account(6, 1_000_000);
+    let frozen_info = freeze_mint(&wallet_info, &mint_info);
+
+    let mint = *mint_info.key;
+    let result = draw_program_budget_of_mint(&wallet_info, &budget_info, destination, caller, mint, frozen_info, 100);
+    assert_error(result, WalletError::MintFrozen);
+
+    let mut wallet: SmartWallet = read_account(&wallet_info);
+    wallet.lifetime_limit = Some(150);
+    write_account(&wallet_info, &wallet);
+    draw_program_budget(&wallet_info, &budget_info, destination, caller, 100).unwrap();
+    let result = draw_program_budget(&wallet_info, &budget_info, destination, caller, 100);
+    assert_error(result, WalletError::LifetimeLimitExceeded);
+
+    let wallet: SmartWallet = read_account(&wallet_info);
+    assert_eq!((wallet.lifetime_spent, wallet.transfer_nonce), (100, 1));
+    assert_eq!(read_account::<ProgramBudget>(&budget_info).remaining, 400);
+}
+
+#[test]
+fn granting_a_budget_needs_the_owner_quorum() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let co_owner = Pubkey::new_unique();
+    wallet.owners = vec![wallet.authority, co_owner];
+    wallet.owner_threshold = 2;
+    let wallet_info = wallet_pda(wallet.clone());
+    let program_id = Pubkey::new_unique();
+    let budget_info = program_budget_of(&wallet_info, program_id, Pubkey::new_unique());
+    let infos = leak(vec![
+        wallet_info.clone(),
+        budget_info.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    let grant = |co_owners| {
+        run_with_args::<GrantProgramBudget, _>(infos, co_owners, program_id, |ctx| {
+            leet_wallet::grant_program_budget(ctx, program_id, Pubkey::new_unique(), 1_000, NOW + 3600)
+        })
+    };
+
+    assert_error(grant(&[]), WalletError::InsufficientOwnerSignatures);
+    grant(leak(vec![signer(co_owner)])).unwrap();
+    assert_eq!(read_account::<ProgramBudget>(&budget_info).remaining, 1_000);
+}
+
+// ============ Guardian deactivation ============
+
+fn deactivate_guardian(
+    wallet_info: &AccountInfo<'static>,
+    guardian_info: &AccountInfo<'static>,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), guardian_info.clone(), signer(wallet.authority)]);
+    run::<ManageGuardian, _>(infos, co_signers, leet_wallet::deactivate_guardian)
+}
+
+#[test]
+fn deactivating_a_guardian_needs_a_co_signer_outside_recovery() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.recovery_pending = true;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let guardian_info = guardian_account(wallet_info.key, 1, Pubkey::new_unique(), GuardianType::Email, 1);
+
+    let result = deactivate_guardian(&wallet_info, &guardian_info, co_signers(wallet_info.key, 1));
+    assert_error(result, WalletError::RecoveryAlreadyPending);
+    wallet.recovery_pending = false;
+    write_account(&wallet_info, &wallet);
+    // A zero guardian threshold still needs one co-signer
+    assert_error(deactivate_guardian(&wallet_info, &guardian_info, &[]), WalletError::InsufficientApprovals);
+
+    deactivate_guardian(&wallet_info, &guardian_info, co_signers(wallet_info.key, 1)).unwrap();
+    assert!(!read_account::<Guardian>(&guardian_info).is_active);
+}
+
+// ============ Transfer requests ============
+
+/// execute_transfer_request for a request of `amount` carrying `approvals`
+fn execute_transfer_request(wallet_info: &AccountInfo<'static>, amount: u64, approvals: u8) -> Result<()> {
+    let mint = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let request = TransferRequest {
+        wallet: *wallet_info.key,
+        id: 0,
+        destination,
+        mint,
+        amount,
+        approvals,
+        approvers: [Pubkey::default(); MAX_GUARDIANS],
+        payer,
+        created_at: NOW,
+        bump: 0,
+    };
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        program_account(Pubkey::new_unique(), &request, 8 + std::mem::size_of::<TransferRequest>()),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        token_account(destination, Pubkey::new_unique(), mint, 0),
+        unfrozen_mint(wallet_info.key, &mint),
+        absent(),
+        absent(),
+        absent(),
+        account_info(payer, system_program::ID, 0, vec![], false, true),
+        token_program(),
+    ]);
+    run::<ExecuteTransferRequest, _>(infos, &[], leet_wallet::execute_transfer_request)
+}
+
+#[test]
+fn transfer_request_needs_an_approval_and_stays_within_limits() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+
+    // A zero guardian threshold still needs one approval
+    assert_error(execute_transfer_request(&wallet_info, 100, 0), WalletError::InsufficientApprovals);
+    assert_error(execute_transfer_request(&wallet_info, 1_500, 1), WalletError::DailyLimitExceeded);
+
+    execute_transfer_request(&wallet_info, 100, 1).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 100);
+}
+
+// ============ Delegates ============
+
+/// approve_delegate of `amount`, with `co_owners` in remaining_accounts
+fn approve_delegate(
+    wallet_info: &AccountInfo<'static>,
+    amount: u64,
+    co_owners: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    approve_delegate_in_window(wallet_info, absent(), amount, co_owners)
+}
+
+fn approve_delegate_in_window(
+    wallet_info: &AccountInfo<'static>,
+    spend_window: AccountInfo<'static>,
+    amount: u64,
+    co_owners: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, Pubkey::new_unique(), 1_000_000),
+        account_info(Pubkey::new_unique(), system_program::ID, 0, vec![], false, false),
+        spend_window,
+        signer(wallet.authority),
+        token_program(),
+    ]);
+    run_with_args::<ApproveDelegate, _>(infos, co_owners, amount, |ctx| leet_wallet::approve_delegate(ctx, amount))
+}
+
+#[test]
+fn delegate_allowance_counts_against_the_lifetime_limit() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.lifetime_limit = Some(500);
+    let wallet_info = wallet_pda(wallet);
+
+    approve_delegate(&wallet_info, 400, &[]).unwrap();
+    assert_error(approve_delegate(&wallet_info, 200, &[]), WalletError::LifetimeLimitExceeded);
+
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).lifetime_spent, 400);
+}
+
+#[test]
+fn delegate_approval_needs_the_owner_quorum() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let co_owner = Pubkey::new_unique();
+    wallet.owners = vec![wallet.authority, co_owner];
+    wallet.owner_threshold = 2;
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(approve_delegate(&wallet_info, 100, &[]), WalletError::InsufficientOwnerSignatures);
+    approve_delegate(&wallet_info, 100, leak(vec![signer(co_owner)])).unwrap();
+}
+
+#[test]
+fn delegate_allowance_is_charged_to_the_spend_window() {
+    let midnight = (NOW / 86400 + 1) * 86400;
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.spend_window_seconds = 86400;
+    let wallet_info = wallet_pda(wallet);
+    let window_info = spend_window(&wallet_info);
+
+    assert_error(approve_delegate(&wallet_info, 600, &[]), WalletError::SpendWindowRequired);
+    set_clock(midnight - 60);
+    approve_delegate_in_window(&wallet_info, window_info.clone(), 600, &[]).unwrap();
+    set_clock(midnight + 60);
+    let result = approve_delegate_in_window(&wallet_info, window_info, 600, &[]);
+    assert_error(result, WalletError::DailyLimitExceeded);
+}
+
+// ============ Required guardian types ============
+
+fn set_required_guardian_types(
+    wallet_info: &AccountInfo<'static>,
+    mask: u8,
+    enforce: bool,
+    co_signers: &'static [AccountInfo<'static>],
+) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+    run::<UpdateConfig, _>(infos, co_signers, |ctx| {
+        leet_wallet::set_required_guardian_types(ctx, mask, enforce)
+    })
+}
+
+#[test]
+fn tightening_required_guardian_types_needs_co_signers() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let email = GuardianType::Email.mask();
+
+    // Recording types without enforcing them is harmless
+    set_required_guardian_types(&wallet_info, email, false, &[]).unwrap();
+    let result = set_required_guardian_types(&wallet_info, email, true, co_signers(wallet_info.key, 1));
+    assert_error(result, WalletError::InsufficientApprovals);
+
+    set_required_guardian_types(&wallet_info, email, true, co_signers(wallet_info.key, 2)).unwrap();
+    assert!(read_account::<SmartWallet>(&wallet_info).enforce_guardian_types);
+    // Relaxing it needs none
+    set_required_guardian_types(&wallet_info, 0, true, &[]).unwrap();
+}
+
+// ============ Guardian rate limit ============
+
+#[test]
+fn loosening_the_guardian_rate_limit_needs_co_signers() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    wallet.max_guardians_per_day = 2;
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let set_cap = |cap, co_signers| {
+        let infos = leak(vec![wallet_info.clone(), global_config(), signer(wallet.authority)]);
+        run::<UpdateConfig, _>(infos, co_signers, move |ctx| leet_wallet::set_max_guardians_per_day(ctx, cap))
+    };
+
+    assert_error(set_cap(3, &[]), WalletError::InsufficientApprovals);
+    assert_error(set_cap(0, co_signers(wallet_info.key, 1)), WalletError::InsufficientApprovals);
+    set_cap(1, &[]).unwrap();
+    set_cap(0, co_signers(wallet_info.key, 2)).unwrap();
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).max_guardians_per_day, 0);
+}
+
+// ============ Transfer eligibility ============
+
+/// check_transfer_eligibility of `amount` to a fresh recipient
+fn transfer_eligibility(wallet_info: &AccountInfo<'static>, amount: u64) -> TransferEligibility {
+    let mint = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let (category_key, _) = Pubkey::find_program_address(&[b"cat", wallet_info.key.as_ref(), &[0]], &crate::ID);
+    let (entry_key, _) =
+        Pubkey::find_program_address(&[b"addr", wallet_info.key.as_ref(), recipient.as_ref()], &crate::ID);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 1_000_000),
+        unfrozen_mint(wallet_info.key, &mint),
+        account_info(category_key, system_program::ID, 0, vec![], false, false),
+        absent(),
+        absent(),
+        account_info(entry_key, system_program::ID, 0, vec![], false, false),
+    ]);
+    run_with_args::<CheckTransferEligibility, _>(infos, &[], (amount, mint, recipient, 0u8), |ctx| {
+        leet_wallet::check_transfer_eligibility(ctx, amount, mint, recipient, 0)
+    })
+    .unwrap();
+    emitted::<TransferEligibility>().pop().unwrap()
+}
+
+#[test]
+fn eligibility_charges_the_platform_fee_against_the_limit() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+    set_platform_fee(100, Pubkey::new_unique()).unwrap();
+
+    let report = transfer_eligibility(&wallet_info, 990);
+    assert_eq!((report.fee, report.eligible), (9, true));
+    // 995 fits the 1,000 limit on its own, but not with its fee
+    let report = transfer_eligibility(&wallet_info, 995);
+    assert!(!report.eligible && !report.within_daily_limit && !report.in_address_book);
+}
+
+// ============ Batch guardians ============
+
+#[test]
+fn batch_creates_guardian_pdas_even_when_pre_funded() {
+    let wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    let wallet_info = program_account(Pubkey::new_unique(), &wallet, WALLET_SPACE);
+    let entries: Vec<GuardianEntry> = (0..2)
+        .map(|_| GuardianEntry {
+            pubkey: Pubkey::new_unique(),
+            guardian_type: GuardianType::Email,
+        })
+        .collect();
+    let space = 8 + std::mem::size_of::<Guardian>();
+    let guardian = |index: u8, lamports| {
+        let (key, _) = Pubkey::find_program_address(&[b"guardian", wallet_info.key.as_ref(), &[index]], &crate::ID);
+        account_info(key, system_program::ID, lamports, vec![0; space], false, true)
+    };
+    // Someone sent the first guardian PDA a lamport ahead of the batch
+    let remaining = leak(vec![
+        guardian(0, 1),
+        account_info(blocked_key_address(&entries[0].pubkey), system_program::ID, 0, vec![], false, false),
+        guardian(1, 0),
+        account_info(blocked_key_address(&entries[1].pubkey), system_program::ID, 0, vec![], false, false),
+    ]);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run_with_args::<AddGuardiansBatch, _>(infos, remaining, entries.clone(), move |ctx| {
+        leet_wallet::add_guardians_batch(ctx, entries)
+    })
+    .unwrap();
+
+    // Transfer, Allocate and Assign for the pre-funded PDA, CreateAccount for the other
+    assert_eq!(system_instructions_invoked(), vec![2, 8, 1, 0]);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).guardian_count, 2);
+}
+
+// ============ Wallet migration ============
+
+#[test]
+fn migration_carries_spend_but_not_in_flight_state() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.daily_spent = 300;
+    wallet.freeze_threshold = 2;
+    wallet.pending_limit_change = Some(PendingLimitChange {
+        new_limit: 5_000,
+        effective_at: NOW + LIMIT_INCREASE_DELAY,
+        ramp: None,
+    });
+    wallet.record_idempotency_key([9; 16]);
+    let wallet_info = wallet_pda(wallet);
+    let wallet: SmartWallet = read_account(&wallet_info);
+    let (index_key, index_bump) = Pubkey::find_program_address(&[b"owner_index", wallet.owner.as_ref()], &crate::ID);
+    let mut index = OwnerIndex {
+        owner: wallet.owner,
+        len: 0,
+        wallet_ids: [[0; 32]; OWNER_INDEX_CAPACITY],
+        unindexed_count: 0,
+        bump: index_bump,
+    };
+    add_to_owner_index(&mut index, wallet_info.key, wallet.wallet_id);
+    let index_info = program_account(index_key, &index, 8 + std::mem::size_of::<OwnerIndex>());
+
+    let new_wallet_id = [0x5a; 32];
+    let (new_key, _) = crate::wallet_pda(&new_wallet_id);
+    let space = 8 + std::mem::size_of::<SmartWallet>() + 100 + 32 * MAX_OWNERS;
+    let new_wallet_info = uninitialized_account(new_key, space);
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        new_wallet_info.clone(),
+        index_info.clone(),
+        signer(wallet.authority),
+        payer(),
+        system_program_account(),
+    ]);
+    run_with_args::<MigrateWallet, _>(infos, &[], new_wallet_id, |ctx| {
+        leet_wallet::migrate_to_new_wallet(ctx, new_wallet_id)
+    })
+    .unwrap();
+
+    let migrated: SmartWallet = read_account(&new_wallet_info);
+    assert_eq!((migrated.daily_spent, migrated.freeze_threshold), (300, 1));
+    assert!(migrated.pending_limit_change.is_none());
+    assert!(!migrated.recent_idempotency_keys.contains(&[9; 16]));
+    let index: OwnerIndex = read_account(&index_info);
+    assert_eq!(&index.wallet_ids[..index.len as usize], &[new_wallet_id]);
+}
+
+// ============ Sweep ============
+
+/// sweep_all of a 5,000-token balance, with `co_signers` as guardian pairs
+fn sweep_all(wallet_info: &AccountInfo<'static>, co_signers: &'static [AccountInfo<'static>]) -> Result<()> {
+    let wallet: SmartWallet = read_account(wallet_info);
+    let mint = Pubkey::new_unique();
+    let infos = leak(vec![
+        wallet_info.clone(),
+        global_config(),
+        token_account(Pubkey::new_unique(), *wallet_info.key, mint, 5_000),
+        token_account(Pubkey::new_unique(), Pubkey::new_unique(), mint, 0),
+        signer(wallet.authority),
+        token_program(),
+    ]);
+    run_with_args::<SweepAll, _>(infos, co_signers, 0u8, |ctx| leet_wallet::sweep_all(ctx, 0))
+}
+
+#[test]
+fn sweep_without_guardians_is_refused() {
+    let wallet_info = wallet_pda(test_wallet(Pubkey::new_unique(), Pubkey::new_unique()));
+
+    assert_error(sweep_all(&wallet_info, &[]), WalletError::InsufficientApprovals);
+    assert_eq!(read_account::<SmartWallet>(&wallet_info).daily_spent, 0);
+}
+
+#[test]
+fn guardian_co_signed_sweep_bypasses_the_daily_limit() {
+    let mut wallet = test_wallet(Pubkey::new_unique(), Pubkey::new_unique());
+    wallet.guardian_count = 2;
+    wallet.guardian_threshold = 2;
+    let wallet_info = wallet_pda(wallet);
+
+    assert_error(sweep_all(&wallet_info, co_signers(wallet_info.key, 1)), WalletError::InsufficientApprovals);
+    sweep_all(&wallet_info, co_signers(wallet_info.key, 2)).unwrap();
+    assert_eq!(emitted::<WalletSwept>()[0].amount, 5_000);
+}